@@ -0,0 +1,81 @@
+/*
+ * Waveboard C node ABI.
+ *
+ * A C node is compiled into a shared library exporting the functions below. The host
+ * calls `wmd_init` once after loading the library, `wmd_step` every frame and `wmd_free`
+ * before unloading it, which happens whenever the node source changes.
+ *
+ *     #include <wavemod.h>
+ *
+ *     typedef struct { double time; } State;
+ *
+ *     void *wmd_init(const wmd_api *api) {
+ *         api->log(api->host, "hello");
+ *         return calloc(1, sizeof(State));
+ *     }
+ *
+ *     int wmd_step(void *state, const wmd_api *api, float dt) {
+ *         State *s = state;
+ *         s->time += dt;
+ *         api->set_number(api->host, "time", s->time);
+ *         return 0;
+ *     }
+ *
+ *     void wmd_free(void *state) {
+ *         free(state);
+ *     }
+ */
+
+#ifndef WAVEMOD_H
+#define WAVEMOD_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Bumped on any incompatible change of `wmd_api` or of the node functions. */
+#define WMD_ABI_VERSION 1
+
+/* Result codes of `wmd_step` and of the property getters. */
+#define WMD_OK 0
+#define WMD_ERROR 1
+#define WMD_NOT_FOUND 2
+/* Returned by the host when the node crashed inside of a call. */
+#define WMD_CRASHED -1
+
+typedef struct wmd_host wmd_host;
+
+/* Host services, valid for the duration of the call they are passed to. */
+typedef struct wmd_api {
+	uint32_t abi_version;
+	/* Name of the node. */
+	const char *node;
+	wmd_host *host;
+
+	/* Reads a numeric or boolean property, WMD_NOT_FOUND when it is missing. */
+	int (*get_number)(wmd_host *host, const char *key, double *value);
+	/* Copies a string property into `buffer`, truncated to `size` bytes including the
+	 * terminating zero. `length`, when not NULL, receives the full length. */
+	int (*get_string)(wmd_host *host, const char *key, char *buffer, size_t size, size_t *length);
+	/* Writes a property, applied to the board after the call returns. */
+	void (*set_number)(wmd_host *host, const char *key, double value);
+	void (*set_string)(wmd_host *host, const char *key, const char *value);
+	/* Writes a line to the board log. */
+	void (*log)(wmd_host *host, const char *message);
+} wmd_api;
+
+/* Creates the node state, returned to the other functions. NULL is a valid state. */
+void *wmd_init(const wmd_api *api);
+/* Advances the node by `dt` seconds, returning WMD_OK or an error code. */
+int wmd_step(void *state, const wmd_api *api, float dt);
+/* Releases the node state. */
+void wmd_free(void *state);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WAVEMOD_H */
@@ -0,0 +1,127 @@
+/*
+ * Compiled into every C node next to its source: wraps the node functions so a fault
+ * inside of them is reported to the host as WMD_CRASHED instead of taking the app down.
+ *
+ * The node memory may be left in any state after a fault, the host never calls it again.
+ *
+ * The handlers are process-wide while a call runs, the host makes one guarded call at a
+ * time. Faults of the other threads are handed to the handlers they replaced.
+ */
+
+#define _XOPEN_SOURCE 700
+
+#include "wavemod.h"
+
+#ifndef _WIN32
+
+#include <setjmp.h>
+#include <signal.h>
+#include <string.h>
+
+static const int wmd__signals[] = {SIGSEGV, SIGBUS, SIGFPE, SIGILL};
+#define WMD__SIGNAL_COUNT (sizeof(wmd__signals) / sizeof(wmd__signals[0]))
+
+static _Thread_local sigjmp_buf wmd__jump;
+/* Whether this thread is inside of a guarded call, so `wmd__jump` is set. */
+static _Thread_local volatile sig_atomic_t wmd__active;
+/* Stack the handler runs on, so stack overflows are caught too. */
+static _Thread_local char wmd__signal_stack[64 * 1024];
+/* Handlers replaced during the current call, such as the stack overflow handler of Rust. */
+static struct sigaction wmd__previous[WMD__SIGNAL_COUNT];
+
+static void wmd__on_fault(int signal, siginfo_t *info, void *context) {
+	const struct sigaction *previous = NULL;
+	size_t i;
+
+	if (wmd__active) {
+		wmd__active = 0;
+		siglongjmp(wmd__jump, signal);
+	}
+
+	/* The fault is not the node's. */
+	for (i = 0; i < WMD__SIGNAL_COUNT; i++) {
+		if (wmd__signals[i] == signal) {
+			previous = &wmd__previous[i];
+		}
+	}
+	if (previous->sa_flags & SA_SIGINFO) {
+		previous->sa_sigaction(signal, info, context);
+	} else if (previous->sa_handler != SIG_DFL && previous->sa_handler != SIG_IGN) {
+		previous->sa_handler(signal);
+	} else {
+		/* The faulting instruction runs again once this returns, raising it with the
+		 * default action. */
+		sigaction(signal, previous, NULL);
+	}
+}
+
+static void wmd__install(stack_t *previous_stack) {
+	stack_t stack;
+	struct sigaction action;
+	size_t i;
+
+	memset(&stack, 0, sizeof stack);
+	stack.ss_sp = wmd__signal_stack;
+	stack.ss_size = sizeof wmd__signal_stack;
+	sigaltstack(&stack, previous_stack);
+
+	memset(&action, 0, sizeof action);
+	action.sa_sigaction = wmd__on_fault;
+	action.sa_flags = SA_ONSTACK | SA_SIGINFO;
+	sigemptyset(&action.sa_mask);
+	for (i = 0; i < WMD__SIGNAL_COUNT; i++) {
+		sigaction(wmd__signals[i], &action, &wmd__previous[i]);
+	}
+}
+
+static void wmd__restore(const stack_t *previous_stack) {
+	size_t i;
+
+	for (i = 0; i < WMD__SIGNAL_COUNT; i++) {
+		sigaction(wmd__signals[i], &wmd__previous[i], NULL);
+	}
+	sigaltstack(previous_stack, NULL);
+}
+
+#define WMD__GUARD(result, call)                                                           \
+	do {                                                                                   \
+		stack_t previous_stack;                                                            \
+		wmd__install(&previous_stack);                                                     \
+		if (sigsetjmp(wmd__jump, 1) == 0) {                                                \
+			wmd__active = 1;                                                               \
+			call;                                                                          \
+		} else {                                                                           \
+			result = WMD_CRASHED;                                                          \
+		}                                                                                  \
+		wmd__active = 0;                                                                   \
+		wmd__restore(&previous_stack);                                                     \
+	} while (0)
+
+#else
+
+/* No crash guard on Windows yet: a fault inside of a node takes the app down. */
+#define WMD__GUARD(result, call) call
+
+#endif
+
+uint32_t wmd__abi_version(void) {
+	return WMD_ABI_VERSION;
+}
+
+int wmd__guarded_init(const wmd_api *api, void **state) {
+	int result = WMD_OK;
+	WMD__GUARD(result, *state = wmd_init(api));
+	return result;
+}
+
+int wmd__guarded_step(void *state, const wmd_api *api, float dt) {
+	int result = WMD_OK;
+	WMD__GUARD(result, result = wmd_step(state, api, dt));
+	return result;
+}
+
+int wmd__guarded_free(void *state) {
+	int result = WMD_OK;
+	WMD__GUARD(result, wmd_free(state));
+	return result;
+}
@@ -1,11 +1,15 @@
 # print("Loaded wavemod pylib")
 
-import wavemod_rs as rust # type: ignore
+import wavemod_rs as rust
 
 from . import utils
-from .gpu import (Renderer, Pipeline, Buffer)
-from .node import Node
+from .events import PointerEvent
+from .gpu import (Renderer, Pipeline, Buffer, Texture, ShaderError)
+from .node import Node, register_node_type
 from .window import Window
 from . import tests
 
-this: Node = None
\ No newline at end of file
+this: Node = None
+CURRENT_NODE: str = None
+# Properties of the node whose script is loading.
+CURRENT_PROPERTIES: dict = {}
\ No newline at end of file
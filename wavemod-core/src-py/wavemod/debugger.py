@@ -0,0 +1,120 @@
+"""
+Step-through debugging of node scripts, driven by the app.
+
+Node `build()` and event handlers are called through `DEBUGGER.call`, which traces them
+while breakpoints are set. When execution stops on a line of a node, the app is told where
+and the handler waits for the command resuming it.
+"""
+
+import bdb
+import linecache
+import sys
+
+import wavemod_rs as rust
+
+REPR_LIMIT = 200
+"""Length variable values are shortened to."""
+
+
+def _repr(value) -> str:
+    try:
+        text = repr(value)
+    except Exception as error:
+        text = f"<repr failed: {error!r}>"
+    if len(text) > REPR_LIMIT:
+        text = text[:REPR_LIMIT] + "..."
+    return text
+
+
+class NodeDebugger(bdb.Bdb):
+    def __init__(self):
+        super().__init__()
+        self.nodes = set()
+        self.ignored = []
+        """Breakpoints set while paused that are not on a line of code."""
+
+    def canonic(self, filename):
+        # Node scripts are named after their node rather than a file, keep them as is.
+        return filename
+
+    def add_node(self, name: str, code: str):
+        """Registers the code executed for a node, so its lines can hold breakpoints."""
+        self.nodes.add(name)
+        linecache.cache[name] = (len(code), None, code.splitlines(True), name)
+
+    def set_breakpoints(self, breakpoints: dict) -> list:
+        """Replaces the breakpoints, lines of the executed code per node. Returns the errors."""
+        self.clear_all_breaks()
+        errors = []
+        for node, lines in breakpoints.items():
+            for line in lines:
+                # bdb accepts blank lines, which execution never stops on.
+                if not linecache.getline(node, line).strip():
+                    errors.append((node, line, "Line %s:%d has no code" % (node, line)))
+                    continue
+                error = self.set_break(node, line)
+                if error:
+                    errors.append((node, line, error))
+        return errors
+
+    def take_ignored(self) -> list:
+        ignored, self.ignored = self.ignored, []
+        return ignored
+
+    def call(self, function, *args):
+        """Calls `function`, stopping on the breakpoints it runs into."""
+        if not self.breaks:
+            return function(*args)
+        self.reset()
+        # Stop on breakpoints only, until a step command is given.
+        self.botframe = sys._getframe()
+        self._set_stopinfo(self.botframe, None, -1)
+        sys.settrace(self.trace_dispatch)
+        try:
+            return function(*args)
+        finally:
+            sys.settrace(None)
+
+    def user_line(self, frame):
+        if frame is self.botframe:
+            self.set_continue()
+            return
+        node = frame.f_code.co_filename
+        if node not in self.nodes:
+            # Stepping only stops in node scripts.
+            self.set_step()
+            return
+
+        frames = []
+        current = frame
+        while current is not None and current is not self.botframe:
+            file = current.f_code.co_filename
+            frames.append((
+                file,
+                current.f_code.co_name,
+                current.f_lineno,
+                linecache.getline(file, current.f_lineno).rstrip(),
+                file in self.nodes,
+                {
+                    name: _repr(value)
+                    for name, value in current.f_locals.items()
+                    if not name.startswith("__")
+                },
+            ))
+            current = current.f_back
+        frames.reverse()
+
+        command, breakpoints = rust._debug_pause(node, frames)
+        if breakpoints is not None:
+            self.ignored.extend(self.set_breakpoints(breakpoints))
+        if command == "step_into":
+            self.set_step()
+        elif command == "step_over":
+            self.set_next(frame)
+        elif command == "step_out":
+            self.set_return(frame)
+        else:
+            self.set_continue()
+
+
+DEBUGGER = NodeDebugger()
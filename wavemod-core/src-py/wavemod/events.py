@@ -0,0 +1,19 @@
+from dataclasses import dataclass
+
+@dataclass
+class PointerEvent:
+    """
+    Pointer input received by `on_pointer(event)` handlers.
+
+    `x` and `y` are in physical pixels, relative to the top-left corner of the node area
+    (or of the board when the node has no area).
+    """
+    kind: str
+    """One of "move", "down", "up" or "wheel"."""
+    x: float
+    y: float
+    button: int = None
+    """Button of "down" and "up" events: 0 left, 1 right, 2 middle."""
+    delta_x: float = 0.0
+    delta_y: float = 0.0
+    """Scroll amount of "wheel" events, in pixels."""
@@ -1,19 +1,119 @@
-from typing_extensions import Self
+try:
+    from typing import Self
+except ImportError:  # Python < 3.11
+    from typing_extensions import Self
+
+import wavemod_rs as rust
+
+ShaderError = rust.ShaderError
 
 class Buffer:
-    pass
+    def __init__(self, pipeline: int, buffer: int):
+        self._pipeline = pipeline
+        self._buffer = buffer
+
+    def write(self, data, offset: int = 0):
+        """
+        Writes `data` at `offset` bytes into the buffer.
+
+        Any object supporting the buffer protocol (bytes, NumPy arrays...) is read in place,
+        as uint8, int32, uint32 or float32 elements.
+        """
+        rust.write_buffer(self._pipeline, self._buffer, data, offset)
+
+class Texture:
+    def __init__(self, pipeline: int, texture: int):
+        self._pipeline = pipeline
+        self._texture = texture
+
+    def write(self, data):
+        """
+        Replaces the texture content with a `(height, width[, channels])` array.
+
+        Any object supporting the buffer protocol is read in place, without copies.
+        """
+        rust.write_texture(self._pipeline, self._texture, data)
 
 class Pipeline:
-    def create_buffer(self, *args) -> Buffer:
-        self._create_buffer(self._pipeline, *args)
+    def __init__(self, pipeline: int):
+        self._pipeline = pipeline
+
+    def create_buffer(self, binding: int, data, storage: bool = False) -> Buffer:
+        """
+        Creates a buffer bound at `@group(1) @binding(binding)` of the pipeline.
+
+        :param data: Initial content, its length is the size of the buffer.
+        :param storage: Bind as a read-only storage buffer instead of a uniform buffer.
+        """
+        return Buffer(self._pipeline, rust.create_buffer(self._pipeline, binding, data, storage))
+
+    def create_texture(self, binding: int, width: int, height: int, format: str = "rgba8unorm") -> Texture:
+        """
+        Creates a 2D texture bound at `@group(1) @binding(binding)`, read with `textureLoad`.
+
+        :param format: One of `r8unorm`, `rgba8unorm`, `r32float`, `rg32float` or `rgba32float`.
+        """
+        return Texture(self._pipeline, rust.create_texture(self._pipeline, binding, width, height, format))
 
-    def _create_buffer(self, pipeline, *args):
-        pass
+    def dispatch(self, x: int, y: int = 1, z: int = 1, invocations: bool = False) -> Self:
+        """
+        Sets the workgroups a compute pipeline dispatches every frame.
+
+        :param invocations: Count invocations instead, rounded up to whole workgroups.
+        """
+        rust.set_dispatch(self._pipeline, x, y, z, invocations)
+        return self
+
+    def dispatch_per_element(self, buffer: str) -> Self:
+        """
+        Dispatches a compute pipeline with one invocation per element of the array of the
+        storage buffer variable `buffer`.
+        """
+        rust.set_dispatch_buffer(self._pipeline, buffer)
+        return self
+
+class StorageBuffer:
+    def __init__(self, name: str):
+        self._name = name
+
+    def write(self, data, offset: int = 0):
+        """Writes `data` at `offset` bytes into the buffer."""
+        rust.write_storage_buffer(self._name, data, offset)
 
 class Renderer:
+    def __init__(self, node: str = None):
+        if node is None:
+            from . import CURRENT_NODE as node
+        self._node = node
+
+    def set_area(self, x: float, y: float, width: float, height: float) -> Self:
+        rust.set_node_area(self._node, x, y, width, height)
+        return self
+
+    def create_storage_buffer(self, name: str, data) -> StorageBuffer:
+        """
+        Creates the storage buffer bound to the shader variables named `name`, in every node.
+
+        Compute shaders declare it `read_write` to fill it, before the other shaders read it.
+        """
+        rust.create_storage_buffer(name, data)
+        return StorageBuffer(name)
+
+    def create_storage_texture(self, name: str, width: int, height: int, format: str = "rgba8unorm"):
+        """
+        Creates the 2D texture bound to the shader variables named `name`, in every node.
+
+        Compute shaders write it as a storage texture, the other shaders sample it.
+
+        :param format: `rgba8unorm` or `rgba16float`.
+        """
+        rust.create_storage_texture(name, width, height, format)
+
     def create_pipeline(self, source: str) -> Pipeline:
-        return self._create_pipeline(self._texture, source)
-    
-    def _create_pipeline(self, texture, source):
-        pass
+        """
+        Compiles a WGSL fragment shader (`fs_main`) drawn in the node area, or a compute
+        shader (`@compute`) dispatched every frame before the fragment shaders.
 
+        Raises `ShaderError` when the shader does not compile.
+        """
+        return Pipeline(rust.create_pipeline(self._node, source))
@@ -1,7 +1,10 @@
 from dataclasses import dataclass
-import wavemod_rs as wmd_rs # type: ignore
+import wavemod_rs as wmd_rs
 
-from typing_extensions import Self
+try:
+    from typing import Self
+except ImportError:  # Python < 3.11
+    from typing_extensions import Self
 from typing import Tuple
 
 Coordinate = Tuple[float, ...]
@@ -45,7 +48,25 @@ class CoordinateMapping:
 
 DEFAULT_MAPPING = CoordinateMapping.default()
 
+NODE_TYPES = []
+"""Classes registered with `register_node_type`, in registration order."""
+
+HANDLERS = ("on_frame", "on_resize", "on_pointer")
+"""Event handlers a node type can define, besides `on_property_changed`."""
+
+
 class Node():
+    type_name: str = None
+    """Name of the node type, the class name when `None`."""
+    properties: dict = {}
+    """Properties of the node type, with their default value."""
+    inputs: list = []
+    """Names of the input ports."""
+    outputs: list = []
+    """Names of the output ports."""
+    default_size: tuple[float, float] = (25., 25.)
+    """Size of new nodes of the type."""
+
     def __init__(
         self,
         pronode: Self=None,
@@ -53,7 +74,7 @@ class Node():
         size: tuple[float, ...]=(25., 25.),
         mapping: CoordinateMapping=CoordinateMapping.default(),
     ):
-        self.id = wmd_rs.create_node(pronode.id if pronode else 0)
+        self.id = wmd_rs.create_node(pronode.id if pronode else 0) # type: ignore[attr-defined]  # not exposed yet
         self.pronode = pronode
         self.mapping = mapping
         self.resize(size)
@@ -85,3 +106,74 @@ class Node():
     def mapping(self) -> CoordinateMapping:
         return self.mapping
 
+    def build(self):
+        """Called once when the node is loaded."""
+        pass
+
+
+def register_node_type(cls: type) -> type:
+    """Registers a subclass of `Node` as a node type, which boards can instantiate.
+
+    The app finds it once the module defining it is loaded as node types::
+
+        @wmd.register_node_type
+        class Blur(wmd.Node):
+            properties = {"radius": 4.0}
+            inputs = ["image"]
+            outputs = ["image"]
+
+            def on_frame(self, dt):
+                ...
+    """
+    if not (isinstance(cls, type) and issubclass(cls, Node)):
+        raise TypeError(f"{cls!r} is not a subclass of wavemod.Node")
+    NODE_TYPES.append(cls)
+    return cls
+
+
+def describe_node_types(module: str) -> list:
+    """Imports `module` and describes the node types it registers."""
+    import importlib
+    importlib.import_module(module)
+    return [
+        (
+            cls.type_name or cls.__name__,
+            cls.__qualname__,
+            cls.__doc__,
+            dict(cls.properties),
+            list(cls.inputs),
+            list(cls.outputs),
+            tuple(cls.default_size),
+        )
+        for cls in NODE_TYPES
+        if cls.__module__ == module
+    ]
+
+
+def bind_node_type(namespace: dict, module: str, qualname: str):
+    """Creates the node of a node script as an instance of a node type, exposing its
+    methods as the functions of the script."""
+    import importlib
+    import wavemod
+
+    cls = importlib.import_module(module)
+    for name in qualname.split("."):
+        cls = getattr(cls, name)
+    # Node types are created by the app, without the arguments of `Node.__init__`.
+    node = cls.__new__(cls)
+    node.name = wavemod.CURRENT_NODE
+    # Saved values replace the defaults of the type.
+    node.properties = {**cls.properties, **wavemod.CURRENT_PROPERTIES}
+
+    namespace["build"] = node.build
+    for handler in HANDLERS:
+        if hasattr(node, handler):
+            namespace[handler] = getattr(node, handler)
+    custom = getattr(node, "on_property_changed", None)
+
+    def on_property_changed(key, value):
+        node.properties[key] = value
+        if custom is not None:
+            custom(key, value)
+
+    namespace["on_property_changed"] = on_property_changed
@@ -0,0 +1,72 @@
+"""
+Execution time of node scripts, measured by the app around `build()` and event handlers.
+
+When profiling is enabled, the time of every call stack is collected too, to be shown as a
+flamegraph.
+"""
+
+import os
+import sys
+from time import perf_counter
+
+from .debugger import DEBUGGER
+
+
+class NodeProfiler:
+    def __init__(self):
+        self.enabled = False
+        self.line_offset = 0
+        """Lines prepended to node scripts, removed from the lines of their functions."""
+        self.reports = {}
+        """Calls, seconds and seconds per call stack of each node, since the last `take()`."""
+
+    def call(self, node: str, function, *args):
+        """Calls `function` of `node`, timing it."""
+        report = self.reports.setdefault(node, [0, 0.0, {}])
+        start = perf_counter()
+        try:
+            if not self.enabled:
+                return function(*args)
+            return self._profile(report[2], function, args)
+        finally:
+            report[0] += 1
+            report[1] += perf_counter() - start
+
+    def take(self) -> list:
+        """Returns the reports as `(node, calls, seconds, stacks)`, and starts new ones."""
+        reports, self.reports = self.reports, {}
+        return [(node, *report) for node, report in reports.items()]
+
+    def _name(self, frame, event, arg) -> str:
+        if event == "c_call":
+            return getattr(arg, "__qualname__", repr(arg))
+        code = frame.f_code
+        if code.co_filename in DEBUGGER.nodes:
+            line = code.co_firstlineno - self.line_offset
+            return f"{code.co_name} ({code.co_filename}:{line})"
+        return f"{code.co_name} ({os.path.basename(code.co_filename)}:{code.co_firstlineno})"
+
+    def _profile(self, stacks: dict, function, args):
+        # Running calls, outermost first: name, start time and time spent in callees.
+        running = []
+
+        def profile(frame, event, arg):
+            now = perf_counter()
+            if event in ("call", "c_call"):
+                running.append([self._name(frame, event, arg), now, 0.0])
+            elif running and event in ("return", "c_return", "c_exception"):
+                stack = ";".join(entry[0] for entry in running)
+                _, start, callees = running.pop()
+                elapsed = now - start
+                stacks[stack] = stacks.get(stack, 0.0) + elapsed - callees
+                if running:
+                    running[-1][2] += elapsed
+
+        sys.setprofile(profile)
+        try:
+            return function(*args)
+        finally:
+            sys.setprofile(None)
+
+
+PROFILER = NodeProfiler()
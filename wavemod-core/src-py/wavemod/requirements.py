@@ -0,0 +1,85 @@
+"""
+Python package requirements of boards, checked against the interpreter running them.
+
+Requirements are written as in `requirements.txt`: a distribution name followed by
+comma-separated version specifiers, like `numpy>=1.24,<2`. Environment markers after `;`
+are ignored, and versions are compared by their release numbers only.
+"""
+
+import re
+from importlib import metadata
+
+_REQUIREMENT = re.compile(r"^([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[[^\]]*\])?\s*(.*)$")
+_SPECIFIER = re.compile(r"^(===|==|!=|~=|>=|<=|>|<)\s*(\S+)$")
+_RELEASE = re.compile(r"^v?(\d+(?:\.\d+)*)")
+
+
+def _release(version: str) -> tuple:
+    match = _RELEASE.match(version.strip())
+    if not match:
+        raise ValueError(f"Invalid version {version!r}")
+    return tuple(int(part) for part in match.group(1).split("."))
+
+
+def _padded(a: tuple, b: tuple) -> tuple:
+    length = max(len(a), len(b))
+    return a + (0,) * (length - len(a)), b + (0,) * (length - len(b))
+
+
+def _matches(installed: str, operator: str, version: str) -> bool:
+    if operator == "===":
+        return installed == version
+    if operator in ("==", "!=") and version.endswith(".*"):
+        prefix = _release(version[:-2])
+        equal = _release(installed)[:len(prefix)] == prefix
+        return equal if operator == "==" else not equal
+
+    current, wanted = _padded(_release(installed), _release(version))
+    if operator == "==":
+        return current == wanted
+    if operator == "!=":
+        return current != wanted
+    if operator == ">=":
+        return current >= wanted
+    if operator == "<=":
+        return current <= wanted
+    if operator == ">":
+        return current > wanted
+    if operator == "<":
+        return current < wanted
+    # ~=X.Y is >=X.Y with the same X.
+    release = _release(version)
+    if len(release) < 2:
+        raise ValueError(f"~= needs at least two release numbers, got {version!r}")
+    return current >= wanted and _release(installed)[:len(release) - 1] == release[:-1]
+
+
+def parse(requirement: str) -> tuple:
+    """Splits a requirement into its distribution name and `(operator, version)` specifiers."""
+    text = requirement.split(";", 1)[0].strip()
+    match = _REQUIREMENT.match(text)
+    if not match:
+        raise ValueError(f"Invalid requirement {requirement!r}")
+    specifiers = []
+    for specifier in filter(None, (part.strip() for part in match.group(2).split(","))):
+        spec = _SPECIFIER.match(specifier)
+        if not spec:
+            raise ValueError(f"Invalid version specifier {specifier!r} in {requirement!r}")
+        specifiers.append((spec.group(1), spec.group(2)))
+    return match.group(1), specifiers
+
+
+def check(requirements: list) -> list:
+    """Returns the requirements the interpreter does not satisfy, with their installed
+    version, `None` when the distribution is missing."""
+    unmet = []
+    for requirement in requirements:
+        name, specifiers = parse(requirement)
+        try:
+            installed = metadata.version(name)
+        except metadata.PackageNotFoundError:
+            unmet.append((requirement, None))
+            continue
+        if not all(_matches(installed, operator, version) for operator, version in specifiers):
+            unmet.append((requirement, installed))
+    return unmet
@@ -1,4 +1,7 @@
-from typing_extensions import Self
+try:
+    from typing import Self
+except ImportError:  # Python < 3.11
+    from typing_extensions import Self
 from .gpu import Renderer
 
 class Window:
@@ -0,0 +1,43 @@
+# Generated from the wavemod_rs bindings, run the tests with WAVEMOD_UPDATE_STUBS=1 to update.
+
+from typing_extensions import Buffer
+
+class ShaderError(Exception):
+    """Raised when a node shader fails to compile or validate."""
+
+def create_buffer(pipeline: int, binding: int, data: Buffer, storage: bool = False) -> int:
+    """Creates a uniform or read-only storage buffer from `data`, returning the buffer id."""
+
+def create_pipeline(node: str, source: str) -> int:
+    """Compiles a WGSL fragment shader drawn in the node area, or a compute shader dispatched
+    every frame, returning the pipeline id."""
+
+def create_storage_buffer(name: str, data: Buffer) -> None:
+    """Creates the storage buffer bound to the shader variables named `name`."""
+
+def create_storage_texture(name: str, width: int, height: int, format: str = "rgba8unorm") -> None:
+    """Creates the 2D storage texture bound to the shader variables named `name`."""
+
+def create_texture(pipeline: int, binding: int, width: int, height: int, format: str = "rgba8unorm") -> int:
+    """Creates a 2D texture read with `textureLoad`, returning the texture id."""
+
+def pytest() -> None:
+    """Prints a message, to check the module is reachable from Python."""
+
+def set_dispatch(pipeline: int, x: int, y: int = 1, z: int = 1, invocations: bool = False) -> None:
+    """Sets the workgroups a compute pipeline dispatches every frame, or its invocations."""
+
+def set_dispatch_buffer(pipeline: int, buffer: str) -> None:
+    """Dispatches a compute pipeline with one invocation per element of a storage buffer array."""
+
+def set_node_area(node: str, x: float, y: float, width: float, height: float) -> None:
+    """Sets the area of the board the node pipelines are drawn into, in physical pixels."""
+
+def write_buffer(pipeline: int, buffer: int, data: Buffer, offset: int = 0) -> None:
+    """Writes `data` at `offset` bytes into a buffer of the pipeline."""
+
+def write_storage_buffer(name: str, data: Buffer, offset: int = 0) -> None:
+    """Writes `data` at `offset` bytes into a storage buffer."""
+
+def write_texture(pipeline: int, texture: int, data: Buffer) -> None:
+    """Uploads a `(height, width[, channels])` array, or raw bytes for 8-bit formats."""
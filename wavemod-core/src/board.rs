@@ -1,15 +1,180 @@
 #![allow(unused)]
 
+use serde::{Deserialize, Serialize};
+
 use crate::schema::{SchemaObject, SchemaValue};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Script {
 	Python(String),
 	JavaScript(String),
-	C,
-	WGSL,
+	/// Compiled into a shared library implementing the ABI of `src-c/wavemod.h`.
+	C(String),
+	/// Fragment shader drawn in the node area, with an `fs_main` entry point.
+	WGSL(String),
+}
+
+/// A call frame of a script error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptFrame {
+	pub file: String,
+	pub function: String,
+	/// 1-based line, relative to the node source when `file` is the node itself.
+	pub line: Option<u32>,
+	pub text: Option<String>,
+}
+
+/// Span of the node source an error points at, with its explanation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptLabel {
+	pub line: u32,
+	/// 1-based, end-exclusive column range on `line`.
+	pub columns: (u32, u32),
+	pub message: String,
+}
+
+/// Error raised while running a node script.
+///
+/// Locations are relative to the node's own source so the code editor can underline them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptError {
+	/// Exception type, e.g. `ZeroDivisionError`.
+	pub kind: String,
+	pub message: String,
+	/// Innermost line of the node source involved in the error.
+	pub line: Option<u32>,
+	/// 1-based, end-exclusive column range on `line`, when the interpreter reports it.
+	pub columns: Option<(u32, u32)>,
+	/// Source text of `line`.
+	pub text: Option<String>,
+	/// Call stack, outermost call first.
+	pub frames: Vec<ScriptFrame>,
+	/// Spans of the source involved in the error, for compilers reporting several of them.
+	pub labels: Vec<ScriptLabel>,
+}
+
+impl ScriptError {
+	/// Error without location, for failures outside of the node source.
+	pub fn new(kind: &str, message: impl Into<String>) -> Self {
+		ScriptError {
+			kind: kind.to_string(),
+			message: message.into(),
+			line: None,
+			columns: None,
+			text: None,
+			frames: Vec::new(),
+			labels: Vec::new(),
+		}
+	}
 }
 
+impl std::fmt::Display for ScriptError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.kind, self.message)?;
+		if let Some(line) = self.line {
+			write!(f, " (line {})", line)?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Text of the 1-based `line` of a script source.
+pub fn source_text(source: &str, line: u32) -> Option<String> {
+	source
+		.lines()
+		.nth((line as usize).checked_sub(1)?)
+		.map(|text| text.trim_end().to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PointerKind {
+	Move,
+	Down,
+	Up,
+	Wheel,
+}
+
+impl PointerKind {
+	/// Name of the kind in node scripts.
+	pub fn name(&self) -> &'static str {
+		match self {
+			PointerKind::Move => "move",
+			PointerKind::Down => "down",
+			PointerKind::Up => "up",
+			PointerKind::Wheel => "wheel",
+		}
+	}
+}
+
+/// Pointer input, in physical pixels relative to the top-left corner of the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointerEvent {
+	pub kind: PointerKind,
+	pub x: f32,
+	pub y: f32,
+	/// Button of `Down` and `Up` events: 0 left, 1 right, 2 middle, then extra buttons.
+	pub button: Option<u32>,
+	/// Scroll amount of `Wheel` events, in pixels.
+	pub delta: (f32, f32),
+}
+
+impl PointerEvent {
+	/// Position relative to `area` (`[x, y, width, height]`), `None` when outside of it.
+	pub fn local_position(&self, area: Option<[f32; 4]>) -> Option<(f32, f32)> {
+		match area {
+			Some([x, y, width, height]) => {
+				let (x, y) = (self.x - x, self.y - y);
+				((0.0..width).contains(&x) && (0.0..height).contains(&y)).then_some((x, y))
+			}
+			None => Some((self.x, self.y)),
+		}
+	}
+}
+
+/// Event dispatched by the event loop to node scripts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NodeEvent {
+	/// A new frame is drawn, `dt` seconds after the previous one.
+	Frame {
+		dt: f32,
+	},
+	Resize {
+		width: u32,
+		height: u32,
+	},
+	Pointer(PointerEvent),
+	PropertyChanged {
+		key: String,
+		value: SchemaValue,
+	},
+}
+
+/// Output a node produced, kept with the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NodeOutput {
+	Text(String),
+	/// PNG encoded image.
+	Png(Vec<u8>),
+}
+
+/// Connectors and size of a node created from a node type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeLayout {
+	pub inputs: Vec<String>,
+	pub outputs: Vec<String>,
+	/// Size of the node on the board, `(width, height)`.
+	pub size: (f32, f32),
+}
+
+/// Type of the nodes holding a markdown note in their `text` property.
+pub const NOTE_TYPE: &str = "Note";
+
+/// Type of the Python nodes imported from notebook code cells, which share the namespace
+/// of the notebook and have no `build()`.
+pub const CELL_TYPE: &str = "Cell";
+
 pub struct Node {
 	pronode: Option<Box<Self>>,
 	subnodes: Vec<Node>,
@@ -17,6 +182,10 @@ pub struct Node {
 	hash: usize,
 	schema: SchemaObject,
 	script: Option<Script>,
+	/// Node type the node was created from, registered, [`NOTE_TYPE`] or [`CELL_TYPE`].
+	node_type: Option<Box<str>>,
+	layout: Option<NodeLayout>,
+	outputs: Vec<NodeOutput>,
 }
 
 impl Node {
@@ -28,13 +197,36 @@ impl Node {
 			hash: 0,
 			schema: SchemaObject::new(),
 			script: None,
+			node_type: None,
+			layout: None,
+			outputs: Vec::new(),
 		}
 	}
 
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
 	pub fn properties(&self) -> &SchemaObject {
 		&self.schema
 	}
 
+	pub fn script(&self) -> Option<&Script> {
+		self.script.as_ref()
+	}
+
+	pub fn node_type(&self) -> Option<&str> {
+		self.node_type.as_deref()
+	}
+
+	pub fn layout(&self) -> Option<&NodeLayout> {
+		self.layout.as_ref()
+	}
+
+	pub fn outputs(&self) -> &[NodeOutput] {
+		&self.outputs
+	}
+
 	pub fn with_schematic(mut self, props: &SchemaObject) -> Self {
 		self.schema.update(props);
 		self
@@ -64,6 +256,19 @@ impl Node {
 		None
 	}
 
+	pub fn find_node_mut(&mut self, name: &str) -> Option<&mut Node> {
+		if self.name == name.into() {
+			return Some(self);
+		}
+		self.subnodes
+			.iter_mut()
+			.find_map(|node| node.find_node_mut(name))
+	}
+
+	pub fn set_property(&mut self, key: &str, value: SchemaValue) {
+		self.schema.entries.insert(key.to_string(), value);
+	}
+
 	pub fn find_subnodes(&self, name: &str) -> Vec<&Node> {
 		let mut nodes = Vec::new();
 		for node in &self.subnodes {
@@ -79,10 +284,29 @@ impl Node {
 		self.script = Some(source);
 		self
 	}
+
+	pub fn with_type(mut self, node_type: &str) -> Self {
+		self.node_type = Some(node_type.into());
+		self
+	}
+
+	pub fn with_layout(mut self, layout: NodeLayout) -> Self {
+		self.layout = Some(layout);
+		self
+	}
+
+	pub fn with_outputs(mut self, outputs: Vec<NodeOutput>) -> Self {
+		self.outputs = outputs;
+		self
+	}
 }
 
 pub struct BoardState {
 	root_node: Node,
+	/// Events waiting to be dispatched to node scripts by the event loop.
+	events: Vec<(String, NodeEvent)>,
+	/// Edges between nodes, from the first node to the second.
+	edges: Vec<(String, String)>,
 }
 
 pub type BoardStateMutex = std::sync::Mutex<BoardState>;
@@ -96,17 +320,79 @@ impl BoardState {
 		&self.root_node.properties()
 	}
 
+	pub fn root_node(&self) -> &Node {
+		&self.root_node
+	}
+
+	pub fn edges(&self) -> &[(String, String)] {
+		&self.edges
+	}
+
+	/// Adds an edge between two nodes of the board, `false` when one is missing.
+	pub fn add_edge(&mut self, from: &str, to: &str) -> bool {
+		if self.root_node.find_node(from).is_none() || self.root_node.find_node(to).is_none() {
+			return false;
+		}
+		self.edges.push((from.to_string(), to.to_string()));
+		true
+	}
+
+	/// Updates a node property and queues the matching `PropertyChanged` event.
+	pub fn set_property(&mut self, node: &str, key: &str, value: SchemaValue) -> bool {
+		let Some(target) = self.root_node.find_node_mut(node) else {
+			return false;
+		};
+		if target.properties().get(key) == Some(&value) {
+			return true;
+		}
+		target.set_property(key, value.clone());
+		self.events.push((
+			node.to_string(),
+			NodeEvent::PropertyChanged {
+				key: key.to_string(),
+				value,
+			},
+		));
+		true
+	}
+
+	/// Replaces the script of a node.
+	pub fn set_script(&mut self, node: &str, script: Script) -> bool {
+		let Some(target) = self.root_node.find_node_mut(node) else {
+			return false;
+		};
+		target.script = Some(script);
+		true
+	}
+
+	/// Takes the queued node events, with the name of the node each one targets.
+	pub fn take_events(&mut self) -> Vec<(String, NodeEvent)> {
+		std::mem::take(&mut self.events)
+	}
+
 	pub fn as_mutex(self) -> std::sync::Mutex<Self> {
 		std::sync::Mutex::new(self)
 	}
 }
 
+/// Board of the app, shared by the event loop and the commands.
+pub fn board_state() -> &'static BoardStateMutex {
+	static BOARD_STATE: std::sync::OnceLock<BoardStateMutex> = std::sync::OnceLock::new();
+	BOARD_STATE.get_or_init(|| create_board().as_mutex())
+}
+
 pub fn create_board() -> BoardState {
 	let root_node = Node::new("root").with_schematic(&schema!
 	({
 		name: "MyBoard",
 		PYTHON_PATH: "",
 		cwd: "",
+		VIRTUAL_ENV: "",
+		PY_REQUIREMENTS: "",
 	}));
-	BoardState { root_node }
+	BoardState {
+		root_node,
+		events: Vec::new(),
+		edges: Vec::new(),
+	}
 }
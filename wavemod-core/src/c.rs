@@ -0,0 +1,778 @@
+//! C node scripts, compiled with the system C compiler into shared libraries loaded at runtime.
+//!
+//! The compiler runs on a thread of its own, a node keeps its previous library until the new
+//! one is built and swapped in on a later frame.
+//!
+//! Nodes are built with gcc or clang, `$CC` when set and `cc` otherwise; MSVC style drivers
+//! like `cl` are reported as a `CompileError`. A fault inside of a node stops the node instead
+//! of the app, except on Windows: `wavemod_guard.c` has no crash guard there yet, and a faulting
+//! node takes the app down.
+//!
+//! The ABI nodes implement is documented in `src-c/wavemod.h`.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::board::{source_text, BoardState, Node, NodeEvent, Script, ScriptError, ScriptLabel};
+use crate::schema::{SchemaObject, SchemaValue};
+
+const WAVEMOD_H: &str = include_str!("../src-c/wavemod.h");
+const WAVEMOD_GUARD_C: &str = include_str!("../src-c/wavemod_guard.c");
+
+/// `WMD_ABI_VERSION` of `wavemod.h`.
+const ABI_VERSION: u32 = 1;
+const WMD_OK: c_int = 0;
+const WMD_NOT_FOUND: c_int = 2;
+const WMD_CRASHED: c_int = -1;
+
+/// `wmd_api` of `wavemod.h`.
+#[repr(C)]
+struct WmdApi {
+	abi_version: u32,
+	node: *const c_char,
+	host: *mut HostState,
+	get_number: unsafe extern "C" fn(*mut HostState, *const c_char, *mut f64) -> c_int,
+	get_string: unsafe extern "C" fn(
+		*mut HostState,
+		*const c_char,
+		*mut c_char,
+		usize,
+		*mut usize,
+	) -> c_int,
+	set_number: unsafe extern "C" fn(*mut HostState, *const c_char, f64),
+	set_string: unsafe extern "C" fn(*mut HostState, *const c_char, *const c_char),
+	log: unsafe extern "C" fn(*mut HostState, *const c_char),
+}
+
+/// Host side of `wmd_host`: the node properties, and the writes made during a call.
+struct HostState {
+	node: String,
+	properties: SchemaObject,
+	writes: Vec<(String, SchemaValue)>,
+}
+
+/// Runs a host callback, a panic must not unwind into the node.
+fn callback<T>(
+	host: *mut HostState,
+	key: *const c_char,
+	fallback: T,
+	f: impl FnOnce(&mut HostState, String) -> T,
+) -> T {
+	if host.is_null() || key.is_null() {
+		return fallback;
+	}
+	let (host, key) = unsafe {
+		(
+			&mut *host,
+			CStr::from_ptr(key).to_string_lossy().into_owned(),
+		)
+	};
+	std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(host, key))).unwrap_or(fallback)
+}
+
+unsafe extern "C" fn get_number(
+	host: *mut HostState,
+	key: *const c_char,
+	value: *mut f64,
+) -> c_int {
+	callback(host, key, WMD_NOT_FOUND, |host, key| {
+		let number = match host.properties.get(&key) {
+			Some(SchemaValue::U64(n)) => *n as f64,
+			Some(SchemaValue::I64(n)) => *n as f64,
+			Some(SchemaValue::F64(n)) => *n,
+			Some(SchemaValue::Bool(b)) => *b as u8 as f64,
+			_ => return WMD_NOT_FOUND,
+		};
+		if !value.is_null() {
+			unsafe { *value = number };
+		}
+		WMD_OK
+	})
+}
+
+unsafe extern "C" fn get_string(
+	host: *mut HostState,
+	key: *const c_char,
+	buffer: *mut c_char,
+	size: usize,
+	length: *mut usize,
+) -> c_int {
+	callback(host, key, WMD_NOT_FOUND, |host, key| {
+		let text = match host.properties.get(&key) {
+			Some(SchemaValue::String(s) | SchemaValue::SchemaLink(s)) => s.as_bytes(),
+			_ => return WMD_NOT_FOUND,
+		};
+		if !length.is_null() {
+			unsafe { *length = text.len() };
+		}
+		if !buffer.is_null() && size > 0 {
+			let copied = text.len().min(size - 1);
+			unsafe {
+				std::ptr::copy_nonoverlapping(text.as_ptr(), buffer as *mut u8, copied);
+				*buffer.add(copied) = 0;
+			}
+		}
+		WMD_OK
+	})
+}
+
+unsafe extern "C" fn set_number(host: *mut HostState, key: *const c_char, value: f64) {
+	callback(host, key, (), |host, key| {
+		host.writes.push((key, SchemaValue::F64(value)));
+	})
+}
+
+unsafe extern "C" fn set_string(host: *mut HostState, key: *const c_char, value: *const c_char) {
+	if value.is_null() {
+		return;
+	}
+	let value = unsafe { CStr::from_ptr(value) }
+		.to_string_lossy()
+		.into_owned();
+	callback(host, key, (), |host, key| {
+		host.writes.push((key, SchemaValue::String(value)));
+	})
+}
+
+unsafe extern "C" fn log_message(host: *mut HostState, message: *const c_char) {
+	callback(host, message, (), |host, message| {
+		log::info!("{}: {}", host.node, message);
+	})
+}
+
+/// Folder node libraries are built in, next to the bundled `wavemod.h`, set up once per process.
+fn build_dir() -> Result<PathBuf, Box<ScriptError>> {
+	static BUILD_DIR: std::sync::OnceLock<Result<PathBuf, String>> = std::sync::OnceLock::new();
+	BUILD_DIR
+		.get_or_init(|| {
+			let root =
+				std::env::temp_dir().join(format!("wavemod-c-{}", env!("CARGO_PKG_VERSION")));
+			std::fs::create_dir_all(&root).map_err(|e| e.to_string())?;
+			for (file, source) in [
+				("wavemod.h", WAVEMOD_H),
+				("wavemod_guard.c", WAVEMOD_GUARD_C),
+			] {
+				let path = root.join(file);
+				if !std::fs::read_to_string(&path).is_ok_and(|current| current == source) {
+					std::fs::write(&path, source).map_err(|e| e.to_string())?;
+				}
+			}
+			Ok(root)
+		})
+		.clone()
+		.map_err(|e| ScriptError::new("CompileError", e).into())
+}
+
+/// File name of the node source, so compiler diagnostics can be traced back to the node.
+///
+/// Names differing only by their other characters, like `a-b` and `a_b`, are told apart by
+/// a hash of the node name.
+fn source_file_name(node: &str) -> String {
+	let name: String = node
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	node.hash(&mut hasher);
+	format!("{}_{:08x}.c", name, hasher.finish() as u32)
+}
+
+/// Parses the `file:line:column: severity: message` diagnostics of gcc and clang on `path`.
+fn diagnostics(output: &str, path: &Path, source: &str) -> Vec<ScriptLabel> {
+	let prefix = format!("{}:", path.display());
+	output
+		.lines()
+		.filter_map(|line| {
+			let mut parts = line.strip_prefix(&prefix)?.splitn(4, ':');
+			let line = parts.next()?.trim().parse().ok()?;
+			let column: u32 = parts.next()?.trim().parse().ok()?;
+			let severity = parts.next()?.trim();
+			let message = parts.next()?.trim();
+			Some(ScriptLabel {
+				line,
+				columns: (column, column + 1),
+				message: format!("{}: {}", severity, message),
+			})
+		})
+		.filter(|label| source_text(source, label.line).is_some())
+		.collect()
+}
+
+/// Whether `compiler` takes the flags and prints the diagnostics of gcc and clang, which MSVC
+/// style drivers do not.
+fn gcc_like(compiler: &str) -> bool {
+	// Either separator, `CC` may name a Windows path on any host.
+	let name = compiler.rsplit(['/', '\\']).next().unwrap_or(compiler);
+	let name = name.to_ascii_lowercase();
+	!matches!(
+		name.strip_suffix(".exe").unwrap_or(&name),
+		"cl" | "clang-cl"
+	)
+}
+
+/// Libraries of the nodes that crashed, which stay loaded, see [`CLibrary::poison`].
+static POISONED: std::sync::Mutex<Vec<PathBuf>> = std::sync::Mutex::new(Vec::new());
+
+/// Compiles a node into a shared library, named after its source so each version gets
+/// its own file: loaders keep returning the first library opened at a given path.
+///
+/// A source whose library crashed is built again into another file, so it starts afresh.
+fn compile(node: &str, source: &str) -> Result<PathBuf, Box<ScriptError>> {
+	let io_error = |e: std::io::Error| ScriptError::new("CompileError", e.to_string());
+	let root = build_dir()?;
+
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	(source, WAVEMOD_H, WAVEMOD_GUARD_C).hash(&mut hasher);
+	let hash = hasher.finish();
+	let source_path = root.join(source_file_name(node));
+	let poisoned = POISONED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+	let library_path = (0..)
+		.map(|rebuild| {
+			source_path.with_extension(match rebuild {
+				0 => format!("{:016x}.{}", hash, std::env::consts::DLL_EXTENSION),
+				_ => format!(
+					"{:016x}-{}.{}",
+					hash,
+					rebuild,
+					std::env::consts::DLL_EXTENSION
+				),
+			})
+		})
+		.find(|path| !poisoned.contains(path))
+		.unwrap();
+	if library_path.exists() {
+		return Ok(library_path);
+	}
+	std::fs::write(&source_path, source).map_err(io_error)?;
+
+	let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+	if !gcc_like(&compiler) {
+		return Err(ScriptError::new(
+			"CompileError",
+			format!(
+				"`{}` is not supported, set CC to a gcc or clang compiler to build C nodes",
+				compiler
+			),
+		)
+		.into());
+	}
+	let output = std::process::Command::new(&compiler)
+		.args(["-shared", "-fPIC", "-O2", "-std=c11", "-Wall"])
+		.arg("-fdiagnostics-color=never")
+		.arg("-I")
+		.arg(&root)
+		.arg("-o")
+		.arg(&library_path)
+		.arg(&source_path)
+		.arg(root.join("wavemod_guard.c"))
+		.output()
+		.map_err(|e| {
+			ScriptError::new("CompileError", format!("Cannot run `{}`: {}", compiler, e))
+		})?;
+
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	let labels = diagnostics(&stderr, &source_path, source);
+	if output.status.success() {
+		for label in &labels {
+			log::warn!("{}: line {}: {}", node, label.line, label.message);
+		}
+		return Ok(library_path);
+	}
+
+	let first_error = labels.iter().find(|label| label.message.contains("error"));
+	let mut error = ScriptError::new(
+		"CompileError",
+		match first_error {
+			Some(label) => label.message.clone(),
+			// Linker errors and the like are not located in the source.
+			None => stderr.trim().to_string(),
+		},
+	);
+	if let Some(label) = first_error {
+		error.line = Some(label.line);
+		error.columns = Some(label.columns);
+		error.text = source_text(source, label.line);
+	}
+	error.labels = labels;
+	Err(error.into())
+}
+
+/// Runs an entry point of a node library. The fault handlers of `wavemod_guard.c` are
+/// process-wide while one runs, so calls are made one at a time.
+fn guarded<T>(call: impl FnOnce() -> T) -> T {
+	static GUARD: std::sync::Mutex<()> = std::sync::Mutex::new(());
+	let _guard = GUARD.lock().unwrap_or_else(|e| e.into_inner());
+	call()
+}
+
+/// Node library, with the guarded entry points of `wavemod_guard.c`.
+struct CLibrary {
+	init: unsafe extern "C" fn(*const WmdApi, *mut *mut c_void) -> c_int,
+	step: unsafe extern "C" fn(*mut c_void, *const WmdApi, f32) -> c_int,
+	free: unsafe extern "C" fn(*mut c_void) -> c_int,
+	path: PathBuf,
+	// Declared last so the entry points above never outlive it.
+	_library: libloading::Library,
+}
+
+impl CLibrary {
+	fn load(path: &Path) -> Result<Self, Box<ScriptError>> {
+		let load_error = |e: libloading::Error| ScriptError::new("LoadError", e.to_string());
+		unsafe {
+			let library = libloading::Library::new(path).map_err(load_error)?;
+			let abi_version = library
+				.get::<unsafe extern "C" fn() -> u32>(b"wmd__abi_version\0")
+				.map_err(load_error)?();
+			if abi_version != ABI_VERSION {
+				return Err(ScriptError::new(
+					"LoadError",
+					format!(
+						"Node built for ABI {}, expected {}",
+						abi_version, ABI_VERSION
+					),
+				)
+				.into());
+			}
+			Ok(CLibrary {
+				init: *library.get(b"wmd__guarded_init\0").map_err(load_error)?,
+				step: *library.get(b"wmd__guarded_step\0").map_err(load_error)?,
+				free: *library.get(b"wmd__guarded_free\0").map_err(load_error)?,
+				path: path.to_path_buf(),
+				_library: library,
+			})
+		}
+	}
+
+	/// Leaves the library of a crashed node loaded, it may still be referenced by signal
+	/// handlers or threads, with its statics in any state. Its file is removed, and its path
+	/// never loaded again: the loader would hand back the crashed library.
+	fn poison(self) {
+		let _ = std::fs::remove_file(&self.path);
+		POISONED
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.push(self.path.clone());
+		std::mem::forget(self);
+	}
+}
+
+/// Build of a node source, running on its own thread.
+struct Compile {
+	source: String,
+	thread: std::thread::JoinHandle<Result<PathBuf, Box<ScriptError>>>,
+}
+
+/// A C node, with the library built from its current source.
+struct CNode {
+	name: CString,
+	/// Last source compiled, successfully or not, so a broken source is not rebuilt every frame.
+	source: String,
+	compile: Option<Compile>,
+	library: Option<CLibrary>,
+	state: *mut c_void,
+	host: Box<HostState>,
+	/// Set once a call failed, the node is not called again until it is rebuilt.
+	stopped: bool,
+}
+
+impl CNode {
+	fn api(&mut self) -> WmdApi {
+		WmdApi {
+			abi_version: ABI_VERSION,
+			node: self.name.as_ptr(),
+			host: &mut *self.host,
+			get_number,
+			get_string,
+			set_number,
+			set_string,
+			log: log_message,
+		}
+	}
+
+	/// Starts building `source`, see [`CNode::swap`].
+	fn rebuild(&mut self, source: &str) -> Result<(), Box<ScriptError>> {
+		self.source = source.to_string();
+		let (node, source) = (self.host.node.clone(), source.to_string());
+		let thread = std::thread::Builder::new()
+			.name("wavemod-cc".to_string())
+			.spawn({
+				let source = source.clone();
+				move || compile(&node, &source)
+			})
+			.map_err(|e| ScriptError::new("CompileError", e.to_string()))?;
+		self.compile = Some(Compile { source, thread });
+		Ok(())
+	}
+
+	/// Swaps in the library of a finished build once it initialized, the previous library
+	/// keeps running when anything fails. Builds of sources replaced since are dropped.
+	fn swap(&mut self, source: &str, wait: bool) -> Result<(), Box<ScriptError>> {
+		if !self
+			.compile
+			.as_ref()
+			.is_some_and(|compile| wait || compile.thread.is_finished())
+		{
+			return Ok(());
+		}
+		let compile = self.compile.take().unwrap();
+		let built = compile.thread.join().unwrap_or_else(|_| {
+			Err(ScriptError::new("CompileError", "The compiler thread panicked").into())
+		});
+		if compile.source != source {
+			return Ok(());
+		}
+		let library = CLibrary::load(&built?)?;
+
+		let api = self.api();
+		let mut state = std::ptr::null_mut();
+		match guarded(|| unsafe { (library.init)(&api, &mut state) }) {
+			WMD_OK => {}
+			code => {
+				library.poison();
+				return Err(call_error("wmd_init", code).into());
+			}
+		}
+
+		self.release();
+		self.library = Some(library);
+		self.state = state;
+		self.stopped = false;
+		Ok(())
+	}
+
+	fn step(&mut self, dt: f32) -> Result<(), Box<ScriptError>> {
+		let Some(library) = self.library.as_ref().filter(|_| !self.stopped) else {
+			return Ok(());
+		};
+		let step = library.step;
+		let api = self.api();
+		match guarded(|| unsafe { step(self.state, &api, dt) }) {
+			WMD_OK => Ok(()),
+			code => {
+				self.stopped = true;
+				if code == WMD_CRASHED {
+					self.library.take().unwrap().poison();
+					self.state = std::ptr::null_mut();
+				}
+				Err(call_error("wmd_step", code).into())
+			}
+		}
+	}
+
+	/// Frees the node state and unloads its library.
+	fn release(&mut self) {
+		let Some(library) = self.library.take() else {
+			return;
+		};
+		guarded(|| unsafe { (library.free)(self.state) });
+		self.state = std::ptr::null_mut();
+		let path = library.path.clone();
+		drop(library);
+		let _ = std::fs::remove_file(path);
+	}
+}
+
+impl Drop for CNode {
+	fn drop(&mut self) {
+		self.release();
+	}
+}
+
+fn call_error(function: &str, code: c_int) -> ScriptError {
+	match code {
+		WMD_CRASHED => ScriptError::new(
+			"Crash",
+			format!("The node crashed in {} and was stopped", function),
+		),
+		code => ScriptError::new("NodeError", format!("{} returned {}", function, code)),
+	}
+}
+
+/// C node scripts of a board, rebuilt whenever their source changes.
+#[derive(Default)]
+pub struct CHost {
+	nodes: Vec<CNode>,
+}
+
+impl CHost {
+	/// Starts building the C nodes of the board, which run from the frame they are built on.
+	pub fn load_board(board: &BoardState) -> Self {
+		let mut host = CHost::default();
+		for (node, error) in host.sync(board) {
+			log::error!("C node '{}' failed: {}", node, error);
+		}
+		host
+	}
+
+	/// Starts rebuilding the nodes whose source changed since the last call, and swaps in the
+	/// libraries built since, returning build errors.
+	///
+	/// Nodes removed from the board, or no longer scripted in C, are freed and unloaded.
+	pub fn sync(&mut self, board: &BoardState) -> Vec<(String, ScriptError)> {
+		self.sync_nodes(board, false)
+	}
+
+	/// [`CHost::sync`], waiting for the builds it starts.
+	#[cfg(test)]
+	fn sync_now(&mut self, board: &BoardState) -> Vec<(String, ScriptError)> {
+		self.sync_nodes(board, true)
+	}
+
+	fn sync_nodes(&mut self, board: &BoardState, wait: bool) -> Vec<(String, ScriptError)> {
+		fn sync_node(
+			host: &mut CHost,
+			node: &Node,
+			wait: bool,
+			names: &mut Vec<String>,
+			errors: &mut Vec<(String, ScriptError)>,
+		) {
+			if let Some(Script::C(source)) = node.script() {
+				names.push(node.name().to_string());
+				let index = match host.nodes.iter().position(|c| c.host.node == node.name()) {
+					Some(index) => index,
+					None => {
+						host.nodes.push(CNode {
+							name: CString::new(node.name()).unwrap_or_default(),
+							source: String::new(),
+							compile: None,
+							library: None,
+							state: std::ptr::null_mut(),
+							host: Box::new(HostState {
+								node: node.name().to_string(),
+								properties: node.properties().clone(),
+								writes: Vec::new(),
+							}),
+							stopped: false,
+						});
+						host.nodes.len() - 1
+					}
+				};
+				let c_node = &mut host.nodes[index];
+				// One build at a time, a source changed meanwhile is built after it.
+				let mut rebuilt = Ok(());
+				if c_node.compile.is_none() && c_node.source != *source {
+					rebuilt = c_node.rebuild(source);
+				}
+				if let Err(error) = rebuilt.and_then(|_| c_node.swap(source, wait)) {
+					errors.push((node.name().to_string(), *error));
+				}
+			}
+			for subnode in node.subnodes() {
+				sync_node(host, subnode, wait, names, errors);
+			}
+		}
+
+		let (mut names, mut errors) = (Vec::new(), Vec::new());
+		sync_node(self, board.root_node(), wait, &mut names, &mut errors);
+		// Dropping a node releases it.
+		self.nodes
+			.retain(|c_node| names.contains(&c_node.host.node));
+		errors
+	}
+
+	/// Steps the nodes on `Frame` events and keeps their properties up to date.
+	///
+	/// Returns the errors of the nodes, which are stopped until their source changes.
+	pub fn dispatch(
+		&mut self,
+		node: Option<&str>,
+		event: &NodeEvent,
+	) -> Vec<(String, ScriptError)> {
+		let mut errors = Vec::new();
+		for c_node in &mut self.nodes {
+			if node.is_some_and(|node| node != c_node.host.node) {
+				continue;
+			}
+			match event {
+				NodeEvent::Frame { dt } => {
+					if let Err(error) = c_node.step(*dt) {
+						errors.push((c_node.host.node.clone(), *error));
+					}
+				}
+				NodeEvent::PropertyChanged { key, value } => {
+					c_node
+						.host
+						.properties
+						.entries
+						.insert(key.clone(), value.clone());
+				}
+				_ => {}
+			}
+		}
+		errors
+	}
+
+	/// Takes the property writes of the nodes: node, key and value.
+	pub fn take_writes(&mut self) -> Vec<(String, String, SchemaValue)> {
+		self.nodes
+			.iter_mut()
+			.flat_map(|c_node| {
+				let node = c_node.host.node.clone();
+				std::mem::take(&mut c_node.host.writes)
+					.into_iter()
+					.map(move |(key, value)| (node.clone(), key, value))
+			})
+			.collect()
+	}
+}
+
+#[test]
+fn test_c_node() {
+	let mut board = crate::board::create_board();
+	let node = Node::new("c_plug").with_schematic(&schema!({ speed: 2u64, label: "wave" }));
+	board.add_node(
+		node.set_script(Script::C(
+			r#"
+#include <stdlib.h>
+#include <string.h>
+#include <wavemod.h>
+
+typedef struct { double time; } State;
+
+void *wmd_init(const wmd_api *api) {
+    return calloc(1, sizeof(State));
+}
+
+int wmd_step(void *state, const wmd_api *api, float dt) {
+    State *s = state;
+    double speed = 0;
+    char label[16];
+    if (api->get_number(api->host, "speed", &speed) != WMD_OK) return WMD_ERROR;
+    if (api->get_string(api->host, "label", label, sizeof label, NULL) != WMD_OK) return WMD_ERROR;
+    s->time += dt * speed;
+    api->set_number(api->host, "time", s->time);
+    api->set_string(api->host, "echo", label);
+    return WMD_OK;
+}
+
+void wmd_free(void *state) {
+    free(state);
+}
+"#
+			.to_string(),
+		)),
+	);
+
+	let mut host = CHost::default();
+	assert!(host.sync_now(&board).is_empty());
+	assert!(host
+		.dispatch(None, &NodeEvent::Frame { dt: 0.5 })
+		.is_empty());
+	assert!(host
+		.dispatch(None, &NodeEvent::Frame { dt: 0.25 })
+		.is_empty());
+	let writes = host.take_writes();
+	assert_eq!(
+		writes.last(),
+		Some(&("c_plug".to_string(), "echo".to_string(), "wave".into()))
+	);
+	assert!(writes.contains(&("c_plug".to_string(), "time".to_string(), 1.5.into())));
+}
+
+#[test]
+fn test_c_node_removed() {
+	let source = r#"
+#include <stddef.h>
+#include <wavemod.h>
+
+void *wmd_init(const wmd_api *api) { return NULL; }
+
+int wmd_step(void *state, const wmd_api *api, float dt) {
+    api->set_number(api->host, "stepped", dt);
+    return WMD_OK;
+}
+
+void wmd_free(void *state) {}
+"#;
+	let mut board = crate::board::create_board();
+	board.add_node(Node::new("c_removed").set_script(Script::C(source.to_string())));
+	let mut host = CHost::default();
+	assert!(host.sync_now(&board).is_empty());
+	host.dispatch(None, &NodeEvent::Frame { dt: 0.1 });
+	assert_eq!(host.take_writes().len(), 1);
+
+	// Scripted in another language.
+	assert!(board.set_script("c_removed", Script::JavaScript(String::new())));
+	assert!(host.sync_now(&board).is_empty());
+	host.dispatch(None, &NodeEvent::Frame { dt: 0.1 });
+	assert!(host.take_writes().is_empty());
+
+	// Deleted from the board.
+	assert!(board.set_script("c_removed", Script::C(source.to_string())));
+	assert!(host.sync_now(&board).is_empty());
+	assert!(host.sync_now(&crate::board::create_board()).is_empty());
+	host.dispatch(None, &NodeEvent::Frame { dt: 0.1 });
+	assert!(host.take_writes().is_empty());
+	assert!(host.nodes.is_empty());
+}
+
+#[test]
+fn test_gcc_like() {
+	assert!(gcc_like("cc"));
+	assert!(gcc_like("/usr/bin/clang"));
+	assert!(gcc_like("x86_64-w64-mingw32-gcc.exe"));
+	assert!(!gcc_like("cl"));
+	assert!(!gcc_like("C:\\VS\\bin\\CL.EXE"));
+	assert!(!gcc_like("clang-cl.exe"));
+}
+
+#[test]
+fn test_source_file_name() {
+	assert!(source_file_name("c-plug").starts_with("c_plug_"));
+	assert_ne!(source_file_name("c-plug"), source_file_name("c_plug"));
+	assert_eq!(source_file_name("c-plug"), source_file_name("c-plug"));
+}
+
+#[test]
+fn test_c_node_errors() {
+	let mut board = crate::board::create_board();
+	let source = "#include <wavemod.h>\n\nvoid *wmd_init(const wmd_api *api) {\n    return undefined_name;\n}\n";
+	board.add_node(Node::new("c_broken").set_script(Script::C(source.to_string())));
+
+	let mut host = CHost::default();
+	let errors = host.sync_now(&board);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].1.kind, "CompileError");
+	assert_eq!(errors[0].1.line, Some(4));
+	assert_eq!(
+		errors[0].1.text.as_deref(),
+		Some("    return undefined_name;")
+	);
+	// The same source is not rebuilt.
+	assert!(host.sync_now(&board).is_empty());
+
+	// A crash is reported and stops the node instead of the process.
+	let source = r#"
+#include <stddef.h>
+#include <wavemod.h>
+
+void *wmd_init(const wmd_api *api) { return NULL; }
+
+int wmd_step(void *state, const wmd_api *api, float dt) {
+    volatile int *pointer = NULL;
+    return *pointer;
+}
+
+void wmd_free(void *state) {}
+"#;
+	assert!(board.set_script("c_broken", Script::C(source.to_string())));
+	assert!(host.sync_now(&board).is_empty());
+	let crashed = host.nodes[0].library.as_ref().unwrap().path.clone();
+	let errors = host.dispatch(None, &NodeEvent::Frame { dt: 0.1 });
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].1.kind, "Crash");
+	assert!(host
+		.dispatch(None, &NodeEvent::Frame { dt: 0.1 })
+		.is_empty());
+	assert!(!crashed.exists());
+
+	// Going back to the crashed source builds a library of its own.
+	assert!(board.set_script("c_broken", Script::C(source.replace("NULL", "0"))));
+	assert!(host.sync_now(&board).is_empty());
+	assert!(board.set_script("c_broken", Script::C(source.to_string())));
+	assert!(host.sync_now(&board).is_empty());
+	assert_ne!(host.nodes[0].library.as_ref().unwrap().path, crashed);
+	let errors = host.dispatch(None, &NodeEvent::Frame { dt: 0.1 });
+	assert_eq!(errors[0].1.kind, "Crash");
+}
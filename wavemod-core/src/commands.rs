@@ -6,21 +6,28 @@ pub fn quit() {
 }
 
 pub fn get_board_props(
-	boardstate: crate::board::BoardStateMutex,
+	boardstate: &crate::board::BoardStateMutex,
 ) -> Result<crate::schema::SchemaObject, ()> {
 	let schema = boardstate.lock().unwrap().properties().clone();
 	Ok(schema)
 }
 
-pub fn gx_init(window: SharedPtr<winit::window::Window>) {
+/// Starts drawing into the window on a render thread.
+///
+/// The caller must keep the returned loop alive, dropping it stops the drawing, and forward
+/// the window events to [`crate::render_loop::RenderLoop::window_event`] so the loop pauses
+/// while the window is hidden.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn gx_init(window: SharedPtr<winit::window::Window>) -> crate::render_loop::RenderLoop {
 	let gx = crate::graphics::create_graphics(window.clone())
 		.with_shader(include_str!("./example/cosmos.wgsl"))
 		.apply_shader(0)
-		.spawn_render_loop();
+		.spawn_render_loop(crate::render_loop::FramePacing::VSync);
 	// window.manage(Mutex::new(gx.surface));
 	// window.manage(Mutex::new(gx.device));
 	// window.manage(Mutex::new(gx.queue));
 	log::info!("WGPU renderer attached.");
+	gx
 }
 
 pub fn create_board() {
@@ -28,11 +35,201 @@ pub fn create_board() {
 	log::error!("create_board not implemented!")
 }
 
-pub fn create_node(boardstate: crate::board::BoardStateMutex, name: String) {
+pub fn create_node(boardstate: &crate::board::BoardStateMutex, name: String) {
 	let mut board = boardstate.lock().unwrap();
 	board.add_node(crate::board::Node::new(name.as_str()));
 }
 
+pub fn set_node_property(
+	boardstate: &crate::board::BoardStateMutex,
+	node: String,
+	key: String,
+	value: crate::schema::SchemaValue,
+) -> Result<(), ()> {
+	let mut board = boardstate.lock().unwrap();
+	match board.set_property(&node, &key, value) {
+		true => Ok(()),
+		false => Err(()),
+	}
+}
+
+/// Loads a Python module of the board environment and registers the node types it defines.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_python_node_types(
+	boardstate: &crate::board::BoardStateMutex,
+	module: String,
+) -> Result<Vec<crate::python::NodeType>, Box<crate::board::ScriptError>> {
+	let env = crate::python::PyEnv::from_props(boardstate.lock().unwrap().properties());
+	crate::python::load_node_types(&env, &module)
+}
+
+/// Python requirements of the board its interpreter does not satisfy.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check_python_requirements(
+	boardstate: &crate::board::BoardStateMutex,
+) -> Result<Vec<crate::python::UnmetRequirement>, Box<crate::board::ScriptError>> {
+	let env = crate::python::PyEnv::from_props(boardstate.lock().unwrap().properties());
+	crate::python::check_requirements(&env)
+}
+
+/// Installs the Python requirements of the board into its virtualenv, from a folder of wheels.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_python_requirements(
+	boardstate: &crate::board::BoardStateMutex,
+	wheels: String,
+) -> Result<(), Box<crate::board::ScriptError>> {
+	let env = crate::python::PyEnv::from_props(boardstate.lock().unwrap().properties());
+	crate::python::install_requirements(&env, std::path::Path::new(&wheels))
+}
+
+/// Registered Python node types, sorted by name.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_node_types() -> Vec<crate::python::NodeType> {
+	crate::python::node_types().lock().unwrap().list()
+}
+
+/// Adds a node of a registered node type, with its default properties.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn create_typed_node(
+	boardstate: &crate::board::BoardStateMutex,
+	node_type: String,
+	name: String,
+) -> Result<(), ()> {
+	let node = match crate::python::node_types().lock().unwrap().get(&node_type) {
+		Some(node_type) => node_type.instantiate(&name),
+		None => return Err(()),
+	};
+	boardstate.lock().unwrap().add_node(node);
+	Ok(())
+}
+
+/// Replaces the board with a Jupyter notebook, its cells becoming nodes.
+pub fn import_notebook(
+	boardstate: &crate::board::BoardStateMutex,
+	json: String,
+) -> Result<(), String> {
+	let board = crate::notebook::import_notebook(&json).map_err(|e| e.to_string())?;
+	*boardstate.lock().unwrap() = board;
+	Ok(())
+}
+
+/// Exports the Python nodes and notes of the board as a Jupyter notebook.
+pub fn export_notebook(boardstate: &crate::board::BoardStateMutex) -> Result<String, String> {
+	crate::notebook::export_notebook(&boardstate.lock().unwrap()).map_err(|e| e.to_string())
+}
+
+/// Replaces the shader of a WGSL node, which keeps its previous one if `source` is invalid.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_node_shader(
+	boardstate: &crate::board::BoardStateMutex,
+	node: String,
+	source: String,
+) -> Result<(), Box<crate::board::ScriptError>> {
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.set_node_shader(&node, &source)?;
+	let mut board = boardstate.lock().unwrap();
+	board.set_script(&node, crate::board::Script::WGSL(source));
+	Ok(())
+}
+
+/// Feeds the drawing of `source` to the `iChannel{channel}` texture of the shader of a node,
+/// `None` unbinds the channel.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_shader_channel(
+	node: String,
+	channel: usize,
+	source: Option<String>,
+) -> Result<(), String> {
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.set_channel(&node, channel, source.as_deref())
+}
+
+/// Sets the buffer passes drawn before the shader of a node, with their render targets.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_shader_passes(
+	node: String,
+	passes: crate::graphics::ShaderPasses,
+) -> Result<(), String> {
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.set_passes(&node, passes)
+}
+
+/// Pans and zooms the board the node shaders are drawn on.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_board_camera(camera: crate::graphics::BoardCamera) -> Result<(), String> {
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.set_camera(camera)
+}
+
+/// Sets the workgroups the compute shader of a WGSL node dispatches every frame.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_node_dispatch(node: String, size: crate::graphics::DispatchSize) -> Result<(), String> {
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.set_node_dispatch(&node, size)
+}
+
+/// Sets the lines of a Python node the debugger pauses on, an empty list clears them.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_python_breakpoints(node: String, lines: Vec<u32>) {
+	crate::python::py_debugger()
+		.lock()
+		.unwrap()
+		.set_breakpoints(&node, &lines);
+}
+
+/// Python nodes paused by the debugger, with their call stack and variables.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_python_paused() -> Vec<crate::python::DebugPause> {
+	crate::python::py_debugger().lock().unwrap().paused()
+}
+
+/// Resumes a paused Python node, continuing or stepping over, into or out of its line.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn resume_python_node(node: String, command: crate::python::DebugCommand) -> Result<(), ()> {
+	match crate::python::py_debugger()
+		.lock()
+		.unwrap()
+		.resume(&node, command)
+	{
+		true => Ok(()),
+		false => Err(()),
+	}
+}
+
+/// Turns the collection of Python node call stacks on or off.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_python_profiling(enabled: bool) {
+	crate::python::py_profiler()
+		.lock()
+		.unwrap()
+		.set_enabled(enabled);
+}
+
+/// Execution time of the Python nodes, slowest first, with their call stacks when profiled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_python_profiles() -> Vec<crate::python::NodeProfile> {
+	crate::python::py_profiler().lock().unwrap().profiles()
+}
+
+/// Call stacks of a Python node in the folded format of flamegraph tools.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_python_flamegraph(node: String) -> Result<String, ()> {
+	match crate::python::py_profiler().lock().unwrap().profile(&node) {
+		Some(profile) => Ok(profile.folded()),
+		None => Err(()),
+	}
+}
+
 //
 // pub fn draw_shader(
 //     graphics: tauri::State<'_, crate::graphics::GraphicsHandleMutex>,
@@ -71,3 +268,31 @@ pub async fn show_window(window: winit::window::Window) {
 	log::info!("Showing window");
 	window.focus_window();
 }
+
+#[test]
+fn test_node_property_events() {
+	let board = crate::board::board_state();
+	create_node(board, "command-node".to_string());
+	set_node_property(
+		board,
+		"command-node".to_string(),
+		"speed".to_string(),
+		2u64.into(),
+	)
+	.unwrap();
+	assert!(set_node_property(
+		board,
+		"missing".to_string(),
+		"speed".to_string(),
+		2u64.into()
+	)
+	.is_err());
+	// The event loop drains the events of the same board.
+	let events = board.lock().unwrap().take_events();
+	assert!(events.iter().any(|(node, event)| node == "command-node"
+		&& *event
+			== crate::board::NodeEvent::PropertyChanged {
+				key: "speed".to_string(),
+				value: 2u64.into(),
+			}));
+}
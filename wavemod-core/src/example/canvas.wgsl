@@ -1,12 +1,22 @@
 
 // @group(0) @binding(0) var<uniform> elapsed_time: f32;
 
+struct CanvasOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position in the quad drawn, from 0 to 1 and downwards: the UV of a node in its area
+    @location(0) uv: vec2<f32>,
+};
+
 @vertex
 fn vs_canvas_bounds(
+    @builtin(vertex_index) index: u32,
     @location(0) pos: vec3<f32>
-) -> @builtin(position) vec4<f32> {
+) -> CanvasOutput {
     // return vec4<f32>(pos + vec3<f32>(sin(elapsed_time), cos(elapsed_time), 0.0), 1.0);
-    return vec4<f32>(pos, 1.0);
+    // Quads are triangle strips of four vertices, from the bottom-left corner
+    let corner = index % 4u;
+    let uv = vec2<f32>(f32(corner & 1u), 1.0 - f32(corner >> 1u));
+    return CanvasOutput(vec4<f32>(pos, 1.0), uv);
 }
 
 /* REMOVE SURFACE_SIZE UNIFORM
@@ -2,15 +2,12 @@
 const PI: f32 = 3.141592653589793;
 const TAU: f32 = 2.0 * PI;
 
-@group(0) @binding(0) var<uniform> elapsed_time: f32;
-@group(0) @binding(1) var<uniform> surface_size: vec2<f32>;
-
 @fragment
 fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
-    let uv_raw =  vec4<f32>(position.xy / surface_size, 0., 1.)-.5;
+    let uv_raw =  vec4<f32>(position.xy / iResolution.xy, 0., 1.)-.5;
 	let len = length(uv_raw.xy);
 	
-    let t = 0.05*elapsed_time;
+    let t = 0.05*iTime;
 	let time = t  +  (5.+sin(t))*.11 / (len+.07); // spiraling
 	let si = sin(time);
     let co = cos(time);
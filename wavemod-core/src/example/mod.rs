@@ -7,6 +7,8 @@ use winit::{
     keyboard::{Key, NamedKey},
 };
 
+pub mod splatting;
+
 const MAX_BUNNIES: usize = 1 << 20;
 const BUNNY_SIZE: f32 = 0.15 * 256.0;
 const GRAVITY: f32 = -9.8 * 100.0;
@@ -64,6 +66,8 @@ pub struct BunnyRenderer {
     local_buffer: wgpu::Buffer,
     extent: [u32; 2],
     rng: WyRand,
+    /// Seconds the bunnies move by each frame.
+    timestep: f32,
 }
 
 impl BunnyRenderer {
@@ -92,7 +96,7 @@ impl BunnyRenderer {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        let delta = 0.01;
+        let delta = self.timestep;
         for bunny in self.bunnies.iter_mut() {
             bunny.update_data(delta, &self.extent);
         }
@@ -354,6 +358,7 @@ impl crate::Renderer for BunnyRenderer {
             local_buffer,
             extent: [config.width, config.height],
             rng,
+            timestep: 0.01,
         };
 
         ex.spawn_bunnies();
@@ -376,6 +381,10 @@ impl crate::Renderer for BunnyRenderer {
         }
     }
 
+    fn set_timestep(&mut self, dt: f32) {
+        self.timestep = dt;
+    }
+
     fn resize(
         &mut self,
         sc_desc: &wgpu::SurfaceConfiguration,
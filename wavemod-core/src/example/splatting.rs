@@ -0,0 +1,744 @@
+//! Gaussian splatting: a compute pass sums Gaussian kernels into a 3D grid, which is drawn
+//! either as a slice or raymarched, cut by a movable slice plane.
+//!
+//! Kernels are JSON lists of `{"position": [x, y, z], "color": [r, g, b], "sigma": s}`, in
+//! grid coordinates from 0 to 1. They come from the `kernels` property of a board node, from
+//! the file of its `kernels_file` property, or from the file named by [`KERNELS_VAR`]. The
+//! renderer follows changes of the node properties.
+//!
+//! Keys: `M` switches modes, `X`, `Y` and `Z` pick the axis of the slice plane, and the up and
+//! down arrows move it.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{Key, NamedKey};
+
+use crate::board::{BoardState, Node};
+use crate::schema::SchemaValue;
+
+/// Environment variable naming a kernel file loaded by [`SplattingRenderer::init`].
+pub const KERNELS_VAR: &str = "WAVEMOD_SPLATTING_KERNELS";
+
+/// Cells of the grid along each axis.
+const GRID_SIZE: u32 = 64;
+/// Bytes of an rgba16float cell.
+const CELL_SIZE: u32 = 8;
+/// Must match the `@workgroup_size` of `cs_main`.
+const WORKGROUP_SIZE: u32 = 4;
+/// Distance the arrows move the slice plane by.
+const SLICE_STEP: f32 = 1.0 / 32.0;
+/// Radians per second the raymarching camera turns by.
+const ROTATION_SPEED: f32 = 0.5;
+/// Opacity of a density of 1 over the width of the grid.
+const DENSITY: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gaussian {
+	pub position: [f32; 3],
+	pub color: [f32; 3],
+	pub sigma: f32,
+}
+
+/// [`Gaussian`] with the layout of the WGSL struct, where `vec3` is aligned on 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuGaussian {
+	position: [f32; 3],
+	_pad: f32,
+	color: [f32; 3],
+	sigma: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GridUniform {
+	size: [u32; 3],
+	kernel_count: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewUniform {
+	resolution: [f32; 2],
+	angle: f32,
+	density: f32,
+	slice_axis: u32,
+	slice_position: f32,
+	mode: u32,
+	steps: u32,
+}
+
+/// Red and blue kernels on the diagonal of the grid.
+pub fn default_kernels() -> Vec<Gaussian> {
+	vec![
+		Gaussian {
+			position: [0.3; 3],
+			color: [1.0, 0.0, 0.0],
+			sigma: 0.05,
+		},
+		Gaussian {
+			position: [0.7; 3],
+			color: [0.0, 0.0, 1.0],
+			sigma: 0.05,
+		},
+	]
+}
+
+pub fn parse_kernels(json: &str) -> Result<Vec<Gaussian>, String> {
+	let kernels: Vec<Gaussian> =
+		serde_json::from_str(json).map_err(|e| format!("Invalid kernels: {}", e))?;
+	if let Some(i) = kernels.iter().position(|kernel| kernel.sigma <= 0.0) {
+		return Err(format!("Kernel {} has a non-positive sigma", i));
+	}
+	Ok(kernels)
+}
+
+pub fn load_kernels(path: impl AsRef<Path>) -> Result<Vec<Gaussian>, String> {
+	let path = path.as_ref();
+	let json = std::fs::read_to_string(path)
+		.map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+	parse_kernels(&json)
+}
+
+/// Kernels of the `kernels` or `kernels_file` property of a node, if it has one.
+pub fn node_kernels(node: &Node) -> Result<Option<Vec<Gaussian>>, String> {
+	let properties = node.properties();
+	match (properties.get("kernels"), properties.get("kernels_file")) {
+		(Some(SchemaValue::String(json)), _) => parse_kernels(json).map(Some),
+		(None, Some(SchemaValue::String(path))) => load_kernels(path).map(Some),
+		(None, None) => Ok(None),
+		_ => Err(format!("Kernels of {} are not a string", node.name())),
+	}
+}
+
+/// First node with a `kernels` or `kernels_file` property.
+fn find_kernel_node(node: &Node) -> Option<&Node> {
+	let properties = node.properties();
+	if properties.get("kernels").is_some() || properties.get("kernels_file").is_some() {
+		return Some(node);
+	}
+	node.subnodes().iter().find_map(find_kernel_node)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplatMode {
+	#[default]
+	Slice,
+	Raymarch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SliceAxis {
+	X,
+	Y,
+	#[default]
+	Z,
+}
+
+pub struct SplattingRenderer {
+	kernels: Vec<Gaussian>,
+	/// `kernels` and `kernels_file` properties of the board node the kernels were read from.
+	kernel_properties: (Option<SchemaValue>, Option<SchemaValue>),
+	/// Whether the grid misses changes of the kernels.
+	dirty: bool,
+	kernel_buffer: wgpu::Buffer,
+	grid_buffer: wgpu::Buffer,
+	compute_layout: wgpu::BindGroupLayout,
+	compute_group: wgpu::BindGroup,
+	compute_pipeline: wgpu::ComputePipeline,
+	/// Cells written by the compute pass, then copied to `grid_texture`. Writing the 3D
+	/// texture directly only writes its first layer on GL.
+	cells: wgpu::Buffer,
+	grid_texture: wgpu::Texture,
+	view_buffer: wgpu::Buffer,
+	render_group: wgpu::BindGroup,
+	render_pipeline: wgpu::RenderPipeline,
+	resolution: [f32; 2],
+	mode: SplatMode,
+	slice_axis: SliceAxis,
+	slice_position: f32,
+	angle: f32,
+	/// Seconds the camera turns by each frame.
+	timestep: f32,
+}
+
+impl SplattingRenderer {
+	pub fn kernels(&self) -> &[Gaussian] {
+		&self.kernels
+	}
+
+	/// Replaces the kernels, the grid is computed again before the next frame.
+	pub fn set_kernels(&mut self, kernels: Vec<Gaussian>) {
+		self.kernels = kernels;
+		self.dirty = true;
+	}
+
+	/// Reads the kernels of the board node with a `kernels` or `kernels_file` property again
+	/// when the property changed. Invalid kernels are logged and the previous ones kept.
+	pub fn sync_kernels(&mut self, board: &BoardState) {
+		let Some(node) = find_kernel_node(board.root_node()) else {
+			return;
+		};
+		let properties = node.properties();
+		let kernel_properties = (
+			properties.get("kernels").cloned(),
+			properties.get("kernels_file").cloned(),
+		);
+		if kernel_properties == self.kernel_properties {
+			return;
+		}
+		self.kernel_properties = kernel_properties;
+		match node_kernels(node) {
+			Ok(Some(kernels)) => self.set_kernels(kernels),
+			Ok(None) => {}
+			Err(error) => log::error!("{}", error),
+		}
+	}
+
+	pub fn mode(&self) -> SplatMode {
+		self.mode
+	}
+
+	pub fn set_mode(&mut self, mode: SplatMode) {
+		self.mode = mode;
+	}
+
+	pub fn slice(&self) -> (SliceAxis, f32) {
+		(self.slice_axis, self.slice_position)
+	}
+
+	/// Places the slice plane at `position`, from 0 to 1 along `axis`.
+	pub fn set_slice(&mut self, axis: SliceAxis, position: f32) {
+		self.slice_axis = axis;
+		self.slice_position = position.clamp(0.0, 1.0);
+	}
+
+	pub fn move_slice(&mut self, delta: f32) {
+		self.set_slice(self.slice_axis, self.slice_position + delta);
+	}
+
+	fn create_kernel_buffer(device: &wgpu::Device, kernels: &[Gaussian]) -> wgpu::Buffer {
+		let mut data: Vec<GpuGaussian> = kernels
+			.iter()
+			.map(|kernel| GpuGaussian {
+				position: kernel.position,
+				_pad: 0.0,
+				color: kernel.color,
+				sigma: kernel.sigma,
+			})
+			.collect();
+		// Storage bindings cannot be empty.
+		if data.is_empty() {
+			data.push(bytemuck::Zeroable::zeroed());
+		}
+		device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Splatting Kernels"),
+			contents: bytemuck::cast_slice(&data),
+			usage: wgpu::BufferUsages::STORAGE,
+		})
+	}
+
+	fn create_compute_group(
+		device: &wgpu::Device,
+		layout: &wgpu::BindGroupLayout,
+		grid_buffer: &wgpu::Buffer,
+		kernel_buffer: &wgpu::Buffer,
+		cells: &wgpu::Buffer,
+	) -> wgpu::BindGroup {
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Splatting Compute"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: grid_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: kernel_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: cells.as_entire_binding(),
+				},
+			],
+		})
+	}
+
+	/// Sums the kernels into the grid texture.
+	fn compute_grid(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+		self.kernel_buffer = Self::create_kernel_buffer(device, &self.kernels);
+		self.compute_group = Self::create_compute_group(
+			device,
+			&self.compute_layout,
+			&self.grid_buffer,
+			&self.kernel_buffer,
+			&self.cells,
+		);
+		let grid = GridUniform {
+			size: [GRID_SIZE; 3],
+			kernel_count: self.kernels.len() as u32,
+		};
+		queue.write_buffer(&self.grid_buffer, 0, bytemuck::bytes_of(&grid));
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Splatting Grid"),
+		});
+		{
+			let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+			cpass.set_pipeline(&self.compute_pipeline);
+			cpass.set_bind_group(0, &self.compute_group, &[]);
+			let workgroups = GRID_SIZE.div_ceil(WORKGROUP_SIZE);
+			cpass.dispatch_workgroups(workgroups, workgroups, workgroups);
+		}
+		encoder.copy_buffer_to_texture(
+			wgpu::TexelCopyBufferInfo {
+				buffer: &self.cells,
+				layout: wgpu::TexelCopyBufferLayout {
+					offset: 0,
+					bytes_per_row: Some(GRID_SIZE * CELL_SIZE),
+					rows_per_image: Some(GRID_SIZE),
+				},
+			},
+			self.grid_texture.as_image_copy(),
+			self.grid_texture.size(),
+		);
+		queue.submit(std::iter::once(encoder.finish()));
+		self.dirty = false;
+	}
+}
+
+impl crate::Renderer for SplattingRenderer {
+	fn required_downlevel_capabilities() -> wgpu::DownlevelCapabilities {
+		wgpu::DownlevelCapabilities {
+			flags: wgpu::DownlevelFlags::COMPUTE_SHADERS,
+			..Default::default()
+		}
+	}
+
+	fn init(
+		config: &wgpu::SurfaceConfiguration,
+		_adapter: &wgpu::Adapter,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+	) -> Self {
+		let kernels = match std::env::var(KERNELS_VAR) {
+			Ok(path) => load_kernels(&path).unwrap_or_else(|error| {
+				log::error!("{}", error);
+				default_kernels()
+			}),
+			Err(_) => default_kernels(),
+		};
+
+		let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("Splatting"),
+			source: wgpu::ShaderSource::Wgsl(include_str!("splatting.wgsl").into()),
+		});
+
+		let grid_texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Splatting Grid"),
+			size: wgpu::Extent3d {
+				width: GRID_SIZE,
+				height: GRID_SIZE,
+				depth_or_array_layers: GRID_SIZE,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D3,
+			format: wgpu::TextureFormat::Rgba16Float,
+			usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+			view_formats: &[],
+		});
+		let grid_view = grid_texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let cells = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Splatting Cells"),
+			size: (GRID_SIZE * GRID_SIZE * GRID_SIZE * CELL_SIZE) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+		let grid_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Splatting Grid"),
+			size: std::mem::size_of::<GridUniform>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let kernel_buffer = Self::create_kernel_buffer(device, &kernels);
+
+		let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Splatting Compute"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: true },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: false },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
+		});
+		let compute_group = Self::create_compute_group(
+			device,
+			&compute_layout,
+			&grid_buffer,
+			&kernel_buffer,
+			&cells,
+		);
+		let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+			label: Some("Splatting Grid"),
+			layout: Some(
+				&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+					label: None,
+					bind_group_layouts: &[&compute_layout],
+					push_constant_ranges: &[],
+				}),
+			),
+			module: &shader,
+			entry_point: Some("cs_main"),
+			compilation_options: Default::default(),
+			cache: None,
+		});
+
+		let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Splatting View"),
+			size: std::mem::size_of::<ViewUniform>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Splatting Grid"),
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+		let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Splatting Render"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 3,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 4,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D3,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 5,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+			],
+		});
+		let render_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Splatting Render"),
+			layout: &render_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 3,
+					resource: view_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 4,
+					resource: wgpu::BindingResource::TextureView(&grid_view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 5,
+					resource: wgpu::BindingResource::Sampler(&sampler),
+				},
+			],
+		});
+		let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Splatting Render"),
+			layout: Some(
+				&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+					label: None,
+					bind_group_layouts: &[&render_layout],
+					push_constant_ranges: &[],
+				}),
+			),
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: Some("vs_main"),
+				compilation_options: Default::default(),
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point: Some("fs_main"),
+				compilation_options: Default::default(),
+				targets: &[Some(config.view_formats[0].into())],
+			}),
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			multiview: None,
+			cache: None,
+		});
+
+		let mut renderer = SplattingRenderer {
+			kernels,
+			kernel_properties: (None, None),
+			dirty: true,
+			kernel_buffer,
+			grid_buffer,
+			compute_layout,
+			compute_group,
+			compute_pipeline,
+			cells,
+			grid_texture,
+			view_buffer,
+			render_group,
+			render_pipeline,
+			resolution: [config.width as f32, config.height as f32],
+			mode: SplatMode::default(),
+			slice_axis: SliceAxis::default(),
+			slice_position: 0.5,
+			angle: 0.0,
+			timestep: 0.01,
+		};
+		renderer.compute_grid(device, queue);
+		renderer
+	}
+
+	fn resize(
+		&mut self,
+		config: &wgpu::SurfaceConfiguration,
+		_device: &wgpu::Device,
+		_queue: &wgpu::Queue,
+	) {
+		self.resolution = [config.width as f32, config.height as f32];
+	}
+
+	fn update(&mut self, event: winit::event::WindowEvent) {
+		let winit::event::WindowEvent::KeyboardInput {
+			event: KeyEvent {
+				logical_key,
+				state: ElementState::Pressed,
+				..
+			},
+			..
+		} = event
+		else {
+			return;
+		};
+		match logical_key {
+			Key::Named(NamedKey::ArrowUp) => self.move_slice(SLICE_STEP),
+			Key::Named(NamedKey::ArrowDown) => self.move_slice(-SLICE_STEP),
+			Key::Character(key) => match key.to_lowercase().as_str() {
+				"m" => {
+					self.mode = match self.mode {
+						SplatMode::Slice => SplatMode::Raymarch,
+						SplatMode::Raymarch => SplatMode::Slice,
+					}
+				}
+				"x" => self.slice_axis = SliceAxis::X,
+				"y" => self.slice_axis = SliceAxis::Y,
+				"z" => self.slice_axis = SliceAxis::Z,
+				_ => {}
+			},
+			_ => {}
+		}
+	}
+
+	fn set_timestep(&mut self, dt: f32) {
+		self.timestep = dt;
+	}
+
+	fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+		self.sync_kernels(&crate::board::board_state().lock().unwrap());
+		if self.dirty {
+			self.compute_grid(device, queue);
+		}
+		let uniform = ViewUniform {
+			resolution: self.resolution,
+			angle: self.angle,
+			density: DENSITY,
+			slice_axis: self.slice_axis as u32,
+			slice_position: self.slice_position,
+			mode: self.mode as u32,
+			steps: 2 * GRID_SIZE,
+		};
+		queue.write_buffer(&self.view_buffer, 0, bytemuck::bytes_of(&uniform));
+		if self.mode == SplatMode::Raymarch {
+			self.angle += ROTATION_SPEED * self.timestep;
+		}
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Splatting Render"),
+		});
+		{
+			let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Splatting Render"),
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			rpass.set_pipeline(&self.render_pipeline);
+			rpass.set_bind_group(0, &self.render_group, &[]);
+			rpass.draw(0..3, 0..1);
+		}
+		queue.submit(std::iter::once(encoder.finish()));
+	}
+}
+
+#[test]
+fn test_splatting_kernels() {
+	let kernels =
+		parse_kernels(r#"[{"position": [0.5, 0.5, 0.5], "color": [0, 1, 0], "sigma": 0.1}]"#)
+			.unwrap();
+	assert_eq!(kernels[0].color, [0.0, 1.0, 0.0]);
+	assert!(
+		parse_kernels(r#"[{"position": [0, 0, 0], "color": [1, 1, 1], "sigma": 0}]"#)
+			.unwrap_err()
+			.contains("sigma")
+	);
+	assert!(parse_kernels("{}").is_err());
+	assert_eq!(std::mem::size_of::<GpuGaussian>(), 32);
+
+	let mut node = Node::new("splats");
+	assert_eq!(node_kernels(&node), Ok(None));
+	let path = std::env::temp_dir().join(format!("wavemod_splats_{}.json", std::process::id()));
+	std::fs::write(&path, serde_json::to_string(&default_kernels()).unwrap()).unwrap();
+	node.set_property("kernels_file", path.to_string_lossy().as_ref().into());
+	assert_eq!(node_kernels(&node), Ok(Some(default_kernels())));
+	let _ = std::fs::remove_file(path);
+	node.set_property("kernels", "[]".into());
+	assert_eq!(node_kernels(&node), Ok(Some(Vec::new())));
+	node.set_property("kernels", SchemaValue::U64(1));
+	assert!(node_kernels(&node).is_err());
+}
+
+#[test]
+fn test_splatting_renderer() {
+	use crate::Renderer;
+
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<SplattingRenderer>())
+	{
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("splatting test", &error);
+			return;
+		}
+	};
+	let flags = context.adapter.get_downlevel_capabilities().flags;
+	if !flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+		log::warn!("Skipping splatting test: no compute shaders");
+		return;
+	}
+	let (device, queue) = (&context.device, &context.queue);
+	let size = 64;
+	let target = crate::OffscreenTarget::new(device, size, size, SplattingRenderer::SRGB);
+	let mut renderer = target.render_new::<SplattingRenderer>(&context);
+	let render = |renderer: &mut SplattingRenderer| {
+		target.render(renderer, device, queue);
+		target.read_rgba(device, queue)
+	};
+	// Pixel at grid coordinates, the grid filling the target upwards.
+	let pixel = |pixels: &[u8], x: f32, y: f32| -> [u8; 4] {
+		let (x, y) = ((x * size as f32) as u32, ((1.0 - y) * size as f32) as u32);
+		pixels[((y * size + x) * 4) as usize..][..4]
+			.try_into()
+			.unwrap()
+	};
+
+	// Slices through the centers of the red and blue kernels.
+	renderer.set_slice(SliceAxis::Z, 0.3);
+	let pixels = render(&mut renderer);
+	let red = pixel(&pixels, 0.3, 0.3);
+	assert!(red[0] > 200 && red[2] < 20, "{:?}", red);
+	assert!(pixel(&pixels, 0.5, 0.5)[..3].iter().all(|&c| c < 20));
+	renderer.set_slice(SliceAxis::Z, 0.7);
+	let pixels = render(&mut renderer);
+	let blue = pixel(&pixels, 0.7, 0.7);
+	assert!(blue[2] > 200 && blue[0] < 20, "{:?}", blue);
+
+	// Raymarching shows both kernels until the plane cuts the blue one away.
+	let count = |pixels: &[u8], channel: usize| {
+		pixels
+			.chunks(4)
+			.filter(|pixel| pixel[channel] > 100)
+			.count()
+	};
+	renderer.set_mode(SplatMode::Raymarch);
+	renderer.set_slice(SliceAxis::Z, 1.0);
+	let pixels = render(&mut renderer);
+	assert!(count(&pixels, 0) > 0 && count(&pixels, 2) > 0);
+	renderer.set_slice(SliceAxis::Z, 0.5);
+	let pixels = render(&mut renderer);
+	assert!(count(&pixels, 0) > 0 && count(&pixels, 2) == 0);
+
+	// New kernels replace the grid.
+	renderer.set_kernels(Vec::new());
+	renderer.set_mode(SplatMode::Slice);
+	renderer.set_slice(SliceAxis::Z, 0.3);
+	let pixels = render(&mut renderer);
+	assert!(pixels.chunks(4).all(|pixel| pixel[..3] == [0, 0, 0]));
+
+	// So do the kernels of a board node, when its property changes.
+	let mut board = crate::board::create_board();
+	let mut node = Node::new("splats");
+	node.set_property(
+		"kernels",
+		serde_json::to_string(&default_kernels()).unwrap().into(),
+	);
+	board.add_node(node);
+	renderer.sync_kernels(&board);
+	assert_eq!(renderer.kernels(), default_kernels());
+	let pixels = render(&mut renderer);
+	let red = pixel(&pixels, 0.3, 0.3);
+	assert!(red[0] > 200 && red[2] < 20, "{:?}", red);
+	assert!(board.set_property("splats", "kernels", "[]".into()));
+	renderer.sync_kernels(&board);
+	assert!(renderer.kernels().is_empty());
+	// Unchanged properties are not read again, and invalid ones keep the kernels.
+	renderer.set_kernels(default_kernels());
+	renderer.sync_kernels(&board);
+	assert_eq!(renderer.kernels(), default_kernels());
+	assert!(board.set_property("splats", "kernels", "{}".into()));
+	renderer.sync_kernels(&board);
+	assert_eq!(renderer.kernels(), default_kernels());
+}
@@ -1,98 +1,128 @@
 // --------------------- Compute Shader: Generate 3D Grid ---------------------
 struct Gaussian {
-    position: vec3<f32>,  // Center of the Gaussian kernel
+    position: vec3<f32>,  // Center of the Gaussian kernel, in grid coordinates from 0 to 1
     color: vec3<f32>,     // RGB color of the Gaussian
-    sigma: f32           // Standard deviation (spread of the Gaussian)
+    sigma: f32            // Standard deviation (spread of the Gaussian)
 };
 
-struct Uniforms {
-    gridSize: vec3<u32>,  // Dimensions of the 3D grid (e.g., 1000x1000x1000)
-    numKernels: u32      // Number of Gaussian kernels
+struct Grid {
+    size: vec3<u32>,      // Dimensions of the 3D grid (e.g., 64x64x64)
+    kernel_count: u32     // Number of Gaussian kernels
 };
 
-@group(0) @binding(0) var<uniform> uniforms: Uniforms;          // Uniform for grid size and kernel count
-@group(0) @binding(1) var<storage, read> kernels: array<Gaussian>; // Gaussian kernels data
-@group(0) @binding(2) var<storage, read_write> grid: array<vec4<f32>>; // 3D grid as flat array
+@group(0) @binding(0) var<uniform> grid: Grid;
+@group(0) @binding(1) var<storage, read> kernels: array<Gaussian>;
+@group(0) @binding(2) var<storage, read_write> cells: array<vec2<u32>>; // rgba16float texels, copied to the grid texture
 
-// Compute Gaussian contribution for a single grid point
-fn gaussian_contribution(position: vec3<f32>, kernel: Gaussian) -> vec3<f32> {
-    let dist = distance(position, kernel.position); // Distance to Gaussian center
-    let weight = exp(-0.5 * (dist / kernel.sigma) * (dist / kernel.sigma)); // Gaussian decay
-    return kernel.color * weight; // Weighted color
+// Compute Gaussian weight of a kernel at a grid point
+fn gaussian_weight(position: vec3<f32>, kernel: Gaussian) -> f32 {
+    let dist = distance(position, kernel.position) / kernel.sigma;
+    return exp(-0.5 * dist * dist);
 }
 
-@compute @workgroup_size(8, 8, 8) // Optimized for GPU parallelism
-fn compute_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    // Calculate 1D index for the flat grid array
-    let idx = global_id.x + global_id.y * uniforms.gridSize.x + global_id.z * uniforms.gridSize.x * uniforms.gridSize.y;
-    if (idx >= arrayLength(&grid) ) { return; } // Bounds check
+@compute @workgroup_size(4, 4, 4) // 64 invocations, within the downlevel limits
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (any(global_id >= grid.size)) { return; } // Bounds check
 
     // Map global ID to normalized grid coordinates
-    let position = vec3<f32>(
-        f32(global_id.x) / f32(uniforms.gridSize.x - 1),
-        f32(global_id.y) / f32(uniforms.gridSize.y - 1),
-        f32(global_id.z) / f32(uniforms.gridSize.z - 1)
-    );
-
-    // Compute accumulated color for this grid point
-    var color: vec3<f32> = vec3(0.0);
-    for (var i = 0u; i < uniforms.numKernels; i++) {
-        color += gaussian_contribution(position, kernels[i]);
+    let position = vec3<f32>(global_id) / vec3<f32>(max(grid.size - 1u, vec3<u32>(1u)));
+
+    // Accumulated color, and total weight as the density of the point
+    var color = vec3<f32>(0.0);
+    var density = 0.0;
+    for (var i = 0u; i < grid.kernel_count; i++) {
+        let weight = gaussian_weight(position, kernels[i]);
+        color += kernels[i].color * weight;
+        density += weight;
     }
+    let idx = global_id.x + grid.size.x * (global_id.y + grid.size.y * global_id.z);
+    cells[idx] = vec2<u32>(pack2x16float(color.rg), pack2x16float(vec2<f32>(color.b, density)));
+}
+
+// --------------------- Render Shaders ---------------------
+struct View {
+    resolution: vec2<f32>, // Size of the target, in pixels
+    angle: f32,            // Rotation of the raymarching camera around the Y axis
+    density: f32,          // Opacity of a unit of density over the width of the grid
+    slice_axis: u32,       // 0, 1 or 2 for the X, Y or Z axis
+    slice_position: f32,   // Position of the slice plane along its axis, from 0 to 1
+    mode: u32,             // 0 for the slice, 1 for raymarching
+    steps: u32             // Raymarching samples over the width of the grid
+};
 
-    // Write color to the grid (adding alpha = 1.0 for full opacity)
-    grid[idx] = vec4<f32>(color, 1.0);
+@group(0) @binding(3) var<uniform> view: View;
+@group(0) @binding(4) var grid_texture: texture_3d<f32>;
+@group(0) @binding(5) var grid_sampler: sampler;
+
+// Full-screen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
 }
 
-// --------------------- Render Shader: Slicing ---------------------
-@fragment
-fn slice_render_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
-    let uv = fragCoord.xy / resolution.xy; // Map screen to normalized coordinates
-    let sliceZ = 0.5; // Middle slice (z = 0.5)
-    let texCoord = vec3<f32>(uv, sliceZ); // Map to 3D grid texture
-    return textureSample(gridTexture, texCoord);
+// Position in the largest centered square, from 0 to 1 and upwards
+fn square_uv(frag_coord: vec2<f32>) -> vec2<f32> {
+    let side = min(view.resolution.x, view.resolution.y);
+    let uv = (frag_coord - 0.5 * (view.resolution - side)) / side;
+    return vec2<f32>(uv.x, 1.0 - uv.y);
 }
 
-// --------------------- Render Shader: Raymarching ---------------------
-@fragment
-fn raymarch_render_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
-    var rayOrigin = vec3<f32>(fragCoord.xy / resolution.xy, 0.0); // Start at near plane
-    let rayDir = vec3<f32>(0.0, 0.0, 1.0); // March along the z-axis
-    var color = vec3<f32>(0.0);
-    var alpha = 0.0;
-
-    // Raymarching loop
-    for (var t = 0.0; t < 1.0; t += 0.01) {
-        let samplePos = rayOrigin + t * rayDir; // Move along the ray
-        if (samplePos.x >= 0.0 && samplePos.x <= 1.0 &&
-            samplePos.y >= 0.0 && samplePos.y <= 1.0 &&
-            samplePos.z >= 0.0 && samplePos.z <= 1.0) {
-            let value = textureSample(gridTexture, samplePos); // Sample from 3D texture
-            let sampleColor = value.rgb;
-            let sampleAlpha = value.a;
-            color += (1.0 - alpha) * sampleAlpha * sampleColor; // Composite color
-            alpha += (1.0 - alpha) * sampleAlpha;
-            if (alpha >= 1.0) { break; } // Early ray termination
-        }
-    }
-    return vec4<f32>(color, alpha);
+fn sample_grid(position: vec3<f32>) -> vec4<f32> {
+    return textureSampleLevel(grid_texture, grid_sampler, position, 0.0);
 }
 
-// --------------------- Render Shader: Point Cloud ---------------------
-@vertex
-fn pointcloud_vertex_main(@location(0) position: vec3<f32>, @location(1) color: vec3<f32>) -> @builtin(position) vec4<f32> {
-    return vec4<f32>(position, 1.0); // Pass position to clip space
+// Slice of the grid along the slice plane
+fn render_slice(uv: vec2<f32>) -> vec4<f32> {
+    var position: vec3<f32>;
+    if (view.slice_axis == 0u) {
+        position = vec3<f32>(view.slice_position, uv.y, uv.x); // X slice
+    } else if (view.slice_axis == 1u) {
+        position = vec3<f32>(uv.x, view.slice_position, uv.y); // Y slice
+    } else {
+        position = vec3<f32>(uv.x, uv.y, view.slice_position); // Z slice
+    }
+    return vec4<f32>(min(sample_grid(position).rgb, vec3<f32>(1.0)), 1.0);
 }
 
-@fragment
-fn pointcloud_fragment_main(@location(1) color: vec3<f32>) -> @location(0) vec4<f32> {
-    return vec4<f32>(color, 1.0); // Pass color as output
+// Orthographic raymarching of the grid, cut by the slice plane
+fn render_volume(uv: vec2<f32>) -> vec4<f32> {
+    let forward = vec3<f32>(sin(view.angle), 0.0, cos(view.angle));
+    let right = vec3<f32>(cos(view.angle), 0.0, -sin(view.angle));
+    let screen = (uv * 2.0 - 1.0) * 0.87; // Half the diagonal of the grid, so it fits
+    let origin = vec3<f32>(0.5) + right * screen.x + vec3<f32>(0.0, screen.y, 0.0) - forward * 2.0;
+
+    // Intersection with the grid cube
+    let direction = select(forward, vec3<f32>(1e-6), abs(forward) < vec3<f32>(1e-6));
+    let t0 = (vec3<f32>(0.0) - origin) / direction;
+    let t1 = (vec3<f32>(1.0) - origin) / direction;
+    let near = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), min(t0.z, t1.z));
+    let far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), max(t0.z, t1.z));
+    if (near >= far) { return vec4<f32>(0.0, 0.0, 0.0, 1.0); }
+
+    let step = 1.0 / f32(view.steps);
+    var color = vec3<f32>(0.0);
+    var transmittance = 1.0;
+    for (var t = near + 0.5 * step; t < far; t += step) {
+        let position = origin + forward * t;
+        if (position[view.slice_axis] > view.slice_position) { continue; } // Cut away
+        let value = sample_grid(position);
+        let alpha = 1.0 - exp(-value.a * view.density * step);
+        color += transmittance * alpha * value.rgb / max(value.a, 1e-4); // Normalized kernel color
+        transmittance *= 1.0 - alpha;
+        if (transmittance < 0.01) { break; } // Early ray termination
+    }
+    return vec4<f32>(color, 1.0);
 }
 
-// --------------------- Render Shader: Cube Mapping ---------------------
 @fragment
-fn cubemap_render_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
-    let uv = fragCoord.xy / resolution.xy; // Screen-space coordinates
-    let texCoord = vec3<f32>(uv, 0.5); // Map to 3D grid texture
-    return textureSample(gridTexture, texCoord); // Sample from the 3D texture
+fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = square_uv(frag_coord.xy);
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    if (view.mode == 0u) {
+        return render_slice(uv);
+    }
+    return render_volume(uv);
 }
@@ -0,0 +1,336 @@
+//! Golden-image tests: headless renders compared against reference PNG files.
+//!
+//! A [`GoldenTest`] renders a [`Renderer`] or a shader pipeline for a number of frames with
+//! a fixed timestep, then compares the last frame with its reference. When they differ, a
+//! diff image is written next to the reference. Setting `WAVEMOD_UPDATE_GOLDENS=1` writes
+//! the renders as the new references instead.
+//!
+//! Tests rendering on the GPU fail when no adapter is found, unless `WAVEMOD_SKIP_GPU_TESTS=1`
+//! skips them, for machines that cannot render at all.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{IADQContext, OffscreenTarget, Renderer};
+
+/// Environment variable switching golden tests to updating their references.
+pub const UPDATE_GOLDENS_VAR: &str = "WAVEMOD_UPDATE_GOLDENS";
+/// Environment variable letting GPU tests pass without an adapter.
+pub const SKIP_GPU_TESTS_VAR: &str = "WAVEMOD_SKIP_GPU_TESTS";
+
+/// Fails a test that found no adapter, unless [`SKIP_GPU_TESTS_VAR`] is `1`.
+#[cfg(test)]
+pub(crate) fn skip_without_adapter(test: &str, error: &str) {
+	if std::env::var(SKIP_GPU_TESTS_VAR).is_ok_and(|value| value == "1") {
+		log::warn!("Skipping {}: {}", test, error);
+	} else {
+		panic!(
+			"The {} needs a GPU adapter, set {}=1 to skip it: {}",
+			test, SKIP_GPU_TESTS_VAR, error
+		);
+	}
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+	/// No adapter can render headlessly, tests should be skipped.
+	NoAdapter(String),
+	/// The reference is missing or unreadable.
+	Reference(PathBuf, String),
+	/// The reference does not have the size of the render.
+	Size {
+		expected: (u32, u32),
+		actual: (u32, u32),
+	},
+	/// Too many pixels differ from the reference, their diff is in `diff`.
+	Mismatch {
+		failing: usize,
+		allowed: usize,
+		diff: PathBuf,
+	},
+	Shader(Box<crate::board::ScriptError>),
+}
+
+impl fmt::Display for GoldenError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			GoldenError::NoAdapter(error) => write!(f, "Cannot render headlessly: {}", error),
+			GoldenError::Reference(path, error) => write!(
+				f,
+				"Cannot read reference {}: {} (run with {}=1 to create it)",
+				path.display(),
+				error,
+				UPDATE_GOLDENS_VAR
+			),
+			GoldenError::Size { expected, actual } => write!(
+				f,
+				"Reference is {}x{} but the render is {}x{}",
+				expected.0, expected.1, actual.0, actual.1
+			),
+			GoldenError::Mismatch {
+				failing,
+				allowed,
+				diff,
+			} => write!(
+				f,
+				"{} pixels differ from the reference, {} allowed, see {}",
+				failing,
+				allowed,
+				diff.display()
+			),
+			GoldenError::Shader(error) => write!(f, "Cannot create the shader: {}", error),
+		}
+	}
+}
+
+impl std::error::Error for GoldenError {}
+
+/// Render compared against the reference image at `image_path`.
+#[derive(Debug, Clone)]
+pub struct GoldenTest {
+	pub image_path: PathBuf,
+	pub width: u32,
+	pub height: u32,
+	/// Frames rendered before the comparison.
+	pub frames: u32,
+	/// Seconds between two frames.
+	pub timestep: f32,
+	/// Largest difference allowed on a channel of a pixel.
+	pub tolerance: u8,
+	/// Fraction of the pixels allowed to exceed `tolerance`.
+	pub max_failing: f32,
+	/// Writes the render as the reference instead of comparing them.
+	pub update: bool,
+}
+
+impl GoldenTest {
+	/// Test of a single frame, with exact comparison. Updates the reference when
+	/// [`UPDATE_GOLDENS_VAR`] is set.
+	pub fn new(image_path: impl Into<PathBuf>, width: u32, height: u32) -> Self {
+		GoldenTest {
+			image_path: image_path.into(),
+			width,
+			height,
+			frames: 1,
+			timestep: 1.0 / 60.0,
+			tolerance: 0,
+			max_failing: 0.0,
+			update: std::env::var(UPDATE_GOLDENS_VAR).is_ok_and(|value| value == "1"),
+		}
+	}
+
+	pub fn with_frames(mut self, frames: u32, timestep: f32) -> Self {
+		self.frames = frames;
+		self.timestep = timestep;
+		self
+	}
+
+	pub fn with_tolerance(mut self, tolerance: u8, max_failing: f32) -> Self {
+		self.tolerance = tolerance;
+		self.max_failing = max_failing;
+		self
+	}
+
+	/// Renders `R` and compares its last frame with the reference.
+	pub fn run_renderer<R: Renderer>(&self) -> Result<(), GoldenError> {
+		let context = pollster::block_on(IADQContext::init_headless::<R>())
+			.map_err(GoldenError::NoAdapter)?;
+		let target = OffscreenTarget::new(&context.device, self.width, self.height, R::SRGB);
+		let mut renderer = R::init(
+			target.config(),
+			&context.adapter,
+			&context.device,
+			&context.queue,
+		);
+		renderer.set_timestep(self.timestep);
+		for _ in 0..self.frames {
+			target.render(&mut renderer, &context.device, &context.queue);
+		}
+		self.check(&target.read_rgba(&context.device, &context.queue))
+	}
+
+	/// Renders a WGSL fragment shader and compares its last frame with the reference.
+	pub fn run_shader(&self, source: &str) -> Result<(), GoldenError> {
+		let context =
+			pollster::block_on(IADQContext::init_headless::<crate::offscreen::ShaderRenderer>())
+				.map_err(GoldenError::NoAdapter)?;
+		let (device, queue) = (&context.device, &context.queue);
+		let target = OffscreenTarget::new(device, self.width, self.height, false);
+		let shader = crate::graphics::create_shader_pipeline(device, target.format(), source)
+			.map_err(GoldenError::Shader)?;
+		let size = (self.width as f32, self.height as f32);
+		shader.set_surface_size(queue, size);
+		for frame in 0..self.frames {
+			// Without date so renders stay reproducible.
+			crate::shadertoy::ShaderToyInputs {
+				resolution: size,
+				time: frame as f32 * self.timestep,
+				time_delta: self.timestep,
+				frame: frame as i32,
+				..Default::default()
+			}
+			.write(&shader, queue);
+			shader.render_at(&target.view(), device, queue, frame as f32 * self.timestep);
+		}
+		self.check(&target.read_rgba(device, queue))
+	}
+
+	/// Compares an RGBA render with the reference, or replaces the reference in update mode.
+	pub fn check(&self, pixels: &[u8]) -> Result<(), GoldenError> {
+		let size = (self.width as usize, self.height as usize);
+		if self.update {
+			if let Some(folder) = self.image_path.parent() {
+				let _ = std::fs::create_dir_all(folder);
+			}
+			crate::utils::output_image_native(
+				pixels.to_vec(),
+				size,
+				self.image_path.to_string_lossy().into_owned(),
+			);
+			return Ok(());
+		}
+
+		let (expected, reference) = read_png(&self.image_path)
+			.map_err(|error| GoldenError::Reference(self.image_path.clone(), error))?;
+		if expected != (self.width, self.height) {
+			return Err(GoldenError::Size {
+				expected,
+				actual: (self.width, self.height),
+			});
+		}
+
+		let (failing, diff) = diff_images(pixels, &reference, self.tolerance);
+		let allowed = (self.max_failing * (self.width * self.height) as f32) as usize;
+		if failing <= allowed {
+			return Ok(());
+		}
+		let diff_path = self.image_path.with_extension("diff.png");
+		crate::utils::output_image_native(diff, size, diff_path.to_string_lossy().into_owned());
+		Err(GoldenError::Mismatch {
+			failing,
+			allowed,
+			diff: diff_path,
+		})
+	}
+}
+
+/// Reads a PNG file as RGBA pixels, with its size.
+fn read_png(path: &Path) -> Result<((u32, u32), Vec<u8>), String> {
+	let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+	let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+	decoder.set_transformations(png::Transformations::normalize_to_color8());
+	let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+	let mut data = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+	data.truncate(info.buffer_size());
+
+	let pixels = match info.color_type {
+		png::ColorType::Rgba => data,
+		png::ColorType::Rgb => data
+			.chunks(3)
+			.flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+			.collect(),
+		png::ColorType::GrayscaleAlpha => data
+			.chunks(2)
+			.flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+			.collect(),
+		png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+		png::ColorType::Indexed => return Err("Unexpanded indexed colors".to_string()),
+	};
+	Ok(((info.width, info.height), pixels))
+}
+
+/// Counts the pixels with a channel differing by more than `tolerance`, and draws them in
+/// red over a faded copy of the reference.
+fn diff_images(actual: &[u8], reference: &[u8], tolerance: u8) -> (usize, Vec<u8>) {
+	let mut failing = 0;
+	let diff = actual
+		.chunks(4)
+		.zip(reference.chunks(4))
+		.flat_map(|(a, r)| {
+			if a.iter().zip(r).any(|(a, r)| a.abs_diff(*r) > tolerance) {
+				failing += 1;
+				[255, 0, 0, 255]
+			} else {
+				let luma = (r[0] as u32 * 3 + r[1] as u32 * 6 + r[2] as u32) / 10;
+				let faded = (luma / 4) as u8;
+				[faded, faded, faded, 255]
+			}
+		})
+		.collect();
+	(failing, diff)
+}
+
+#[test]
+fn test_golden_images() {
+	let folder = std::env::temp_dir().join(format!("wavemod_golden_{}", std::process::id()));
+	let red = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0, 0.0, 0.0, 1.0);\n}\n";
+	let golden = GoldenTest {
+		update: false,
+		..GoldenTest::new(folder.join("red.png"), 32, 32)
+	};
+
+	match golden.run_shader(red) {
+		Err(GoldenError::NoAdapter(error)) => {
+			skip_without_adapter("golden image test", &error);
+			return;
+		}
+		Err(GoldenError::Reference(..)) => {}
+		result => panic!("{:?}", result),
+	}
+
+	let update = GoldenTest {
+		update: true,
+		..golden.clone()
+	};
+	update.run_shader(red).unwrap();
+	golden.run_shader(red).unwrap();
+
+	// The triangle is drawn in a darker red, past the tolerance.
+	let dark = red.replace("1.0, 0.0", "0.5, 0.0");
+	let error = golden.run_shader(&dark).unwrap_err();
+	let GoldenError::Mismatch { failing, diff, .. } = &error else {
+		panic!("{:?}", error);
+	};
+	assert!(*failing > 0 && *failing < 32 * 32);
+	assert!(diff.exists());
+	golden
+		.clone()
+		.with_tolerance(128, 0.0)
+		.run_shader(&dark)
+		.unwrap();
+	golden
+		.clone()
+		.with_tolerance(0, 0.5)
+		.run_shader(&dark)
+		.unwrap();
+
+	// Frames advance the time uniform by the timestep.
+	let pulse = "@group(0) @binding(0) var<uniform> elapsed_time: f32;\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(elapsed_time, 0.0, 0.0, 1.0);\n}\n";
+	golden
+		.clone()
+		.with_frames(3, 0.5)
+		.run_shader(pulse)
+		.unwrap();
+	assert!(golden
+		.clone()
+		.with_frames(2, 0.5)
+		.run_shader(pulse)
+		.is_err());
+
+	let _ = std::fs::remove_dir_all(folder);
+}
+
+#[test]
+fn test_bunny_screenshot() {
+	let golden = GoldenTest::new(
+		concat!(env!("CARGO_MANIFEST_DIR"), "/src/example/screenshot.png"),
+		1024,
+		768,
+	)
+	.with_tolerance(8, 0.01);
+	match golden.run_renderer::<crate::example::BunnyRenderer>() {
+		Err(GoldenError::NoAdapter(error)) => skip_without_adapter("bunny screenshot test", &error),
+		result => result.unwrap(),
+	}
+}
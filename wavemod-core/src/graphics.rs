@@ -1,10 +1,31 @@
+//! Shader drawing, along two paths that share the [`RenderGraph`] and its texture pools.
+//!
+//! [`GraphicsHandle`] draws the shaders of `gx_init` into the window it was created for, and
+//! owns the surface of that window, or none when headless. Each shader covers the whole frame:
+//! it is drawn into a layer of the frame size, and the [`Compositor`] blends the layers.
+//!
+//! [`NodeGraphics`] draws the node scripts over the board of the app window. The event loop of
+//! `setup.rs` owns that surface and hands over its frame, drawn over the renderer of the app, so
+//! [`NodeGraphics`] never touches a surface. Nodes only cover their board area under the camera,
+//! so they are drawn straight into the frame, cut to their area, rather than into layers of the
+//! frame size, which would cost a full frame texture and blend for each node. Their compute
+//! passes, buffer passes and channel textures are ordered by a [`RenderGraph`] of their own.
+//!
+//! The two stay apart as they draw into different windows, and their pipelines are kept
+//! differently: the shaders of a [`GraphicsHandle`] by the index render commands set them at,
+//! node pipelines by node, rebuilt or dropped as the board changes.
+
 #![allow(unused)]
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::{Duration, Instant};
 use wgpu::util::DeviceExt;
 use wgpu::*;
 
+use crate::board::{source_text, BoardState, Node, PointerEvent, Script, ScriptError, ScriptLabel};
+use crate::render_graph::{Compositor, RenderGraph, TextureDesc, TexturePool, OUTPUT};
+use crate::shadertoy::{ShaderMouse, ShaderToyInputs, CHANNEL_COUNT};
 use crate::SharedPtr;
 
 const VERTICES: &[[f32; 3]; 4] = &[
@@ -23,6 +44,9 @@ pub struct BufferGroup {
 	id: u32,
 	binding_type: BufferBindingType,
 	bind_group: Option<BindGroup>,
+	/// Bind groups used instead of `bind_group` on the other frames of a ping-pong, binding the
+	/// other texture of each render target.
+	variants: Vec<BindGroup>,
 	buffers: HashMap<String, Buffer>,
 }
 
@@ -35,6 +59,7 @@ impl BufferGroup {
 				None => BufferBindingType::Vertex,
 			},
 			bind_group,
+			variants: Vec::new(),
 			buffers: HashMap::new(),
 		}
 	}
@@ -43,38 +68,249 @@ impl BufferGroup {
 		self.buffers.insert(label, buffer);
 		self
 	}
+
+	/// Bind group of `variant`, the default one when the group has no such variant.
+	fn bind_group(&self, variant: usize) -> Option<&BindGroup> {
+		variant
+			.checked_sub(1)
+			.and_then(|v| self.variants.get(v))
+			.or(self.bind_group.as_ref())
+	}
+}
+
+/// Uniform receiving the seconds elapsed since the shader creation, as an `f32`.
+///
+/// Only a variable of this exact name is set, in any group and binding, e.g.
+/// `@group(0) @binding(0) var<uniform> elapsed_time: f32;`. Shaders may read the Shadertoy
+/// `iTime` instead, see [`crate::shadertoy`].
+pub const TIME_UNIFORM: &str = "elapsed_time";
+/// Uniform receiving the size of the area drawn by the shader, in pixels.
+pub const SURFACE_SIZE_UNIFORM: &str = "surface_size";
+
+/// Pipeline of a [`Shader`].
+enum ShaderPipeline {
+	/// Fragment shader drawn over the canvas quad.
+	Render(RenderPipeline),
+	/// Compute shader, with the workgroup size of its entry point.
+	Compute(ComputePipeline, [u32; 3]),
 }
 
 pub struct Shader {
-	pipeline: RenderPipeline,
+	pipeline: ShaderPipeline,
 	buffers: Vec<BufferGroup>,
+	/// Resource bindings reflected from the shader source.
+	bindings: Vec<ShaderBinding>,
 	time_start: std::time::Instant,
 }
 
 impl Shader {
-	// TODO: Return an error if we cannot find the buffer
-	fn update_buffer(&self, queue: &wgpu::Queue, label: String, data: &[u8], offset: u64) {
+	/// Writes to a buffer of the shader, named after its WGSL variable.
+	pub fn update_buffer(
+		&self,
+		queue: &wgpu::Queue,
+		label: &str,
+		data: &[u8],
+		offset: u64,
+	) -> Result<(), String> {
 		let buffer = self
 			.buffers
 			.iter()
-			.find_map(|bg| bg.buffers.get(&label))
-			.expect(format!("Unkwon buffer '{}'", label).as_str());
+			.find_map(|bg| bg.buffers.get(label))
+			.ok_or_else(|| format!("Unknown buffer '{}'", label))?;
+		if offset + data.len() as u64 > buffer.size() {
+			return Err(format!(
+				"Writing {} bytes at offset {} overflows the {} bytes buffer '{}'",
+				data.len(),
+				offset,
+				buffer.size(),
+				label
+			));
+		}
 		queue.write_buffer(buffer, offset, data);
+		Ok(())
+	}
+
+	pub fn bindings(&self) -> &[ShaderBinding] {
+		&self.bindings
+	}
+
+	/// Whether the shader declares a binding named `name`.
+	pub fn has_binding(&self, name: &str) -> bool {
+		self.bindings.iter().any(|b| b.name == name)
+	}
+
+	pub fn has_buffer(&self, label: &str) -> bool {
+		self.buffers.iter().any(|bg| bg.buffers.contains_key(label))
+	}
+
+	/// Buffer of the shader, named after its WGSL variable.
+	pub fn buffer(&self, label: &str) -> Option<&Buffer> {
+		self.buffers.iter().find_map(|bg| bg.buffers.get(label))
+	}
+
+	/// Workgroup size of compute shaders, `None` for fragment shaders.
+	pub fn workgroup_size(&self) -> Option<[u32; 3]> {
+		match self.pipeline {
+			ShaderPipeline::Compute(_, size) => Some(size),
+			ShaderPipeline::Render(_) => None,
+		}
+	}
+
+	/// Sets the [`SURFACE_SIZE_UNIFORM`] of shaders declaring it.
+	pub fn set_surface_size(&self, queue: &wgpu::Queue, size: (f32, f32)) {
+		if self.has_buffer(SURFACE_SIZE_UNIFORM) {
+			let _ = self.update_buffer(
+				queue,
+				SURFACE_SIZE_UNIFORM,
+				bytemuck::cast_slice(&[size.0, size.1]),
+				0,
+			);
+		}
+	}
+
+	/// Sets the [`TIME_UNIFORM`] of shaders declaring it.
+	pub fn set_time(&self, queue: &wgpu::Queue, time: f32) {
+		if self.has_buffer(TIME_UNIFORM) {
+			let _ = self.update_buffer(queue, TIME_UNIFORM, bytemuck::cast_slice(&[time]), 0);
+		}
 	}
 
-	fn ellapsed(&self) -> f32 {
+	pub(crate) fn ellapsed(&self) -> f32 {
 		(std::time::Instant::now() - self.time_start).as_secs_f32()
 	}
+
+	/// Clears `view` and draws the shader over it, with the time elapsed since its creation.
+	pub fn render(&self, view: &TextureView, device: &Device, queue: &Queue) {
+		self.render_at(view, device, queue, self.ellapsed());
+	}
+
+	/// Same as [`Shader::render`], at `time` seconds instead of the current time.
+	pub fn render_at(&self, view: &TextureView, device: &Device, queue: &Queue, time: f32) {
+		self.set_time(queue, time);
+
+		let mut encoder =
+			device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: None,
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color {
+							r: 0.0,
+							g: 0.0,
+							b: 0.0,
+							a: 0.0,
+						}),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				depth_stencil_attachment: None,
+				..Default::default()
+			});
+			self.draw(&mut render_pass, 0);
+		}
+		queue.submit(std::iter::once(encoder.finish()));
+	}
+
+	/// Dispatches `workgroups` of a compute shader.
+	pub fn compute(&self, device: &Device, queue: &Queue, workgroups: [u32; 3]) {
+		self.set_time(queue, self.ellapsed());
+
+		let mut encoder =
+			device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+		{
+			let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+			self.dispatch(&mut compute_pass, workgroups, 0);
+		}
+		queue.submit(std::iter::once(encoder.finish()));
+	}
+
+	/// Records the dispatch of `workgroups` with this compute shader, binding the bind groups
+	/// of `variant`. Does nothing for fragment shaders.
+	fn dispatch(&self, compute_pass: &mut ComputePass<'_>, workgroups: [u32; 3], variant: usize) {
+		let ShaderPipeline::Compute(pipeline, _) = &self.pipeline else {
+			return;
+		};
+		compute_pass.set_pipeline(pipeline);
+		for buffer_group in &self.buffers {
+			if let BufferBindingType::BindGroup = buffer_group.binding_type {
+				compute_pass.set_bind_group(buffer_group.id, buffer_group.bind_group(variant), &[]);
+			}
+		}
+		let [x, y, z] = workgroups;
+		compute_pass.dispatch_workgroups(x, y, z);
+	}
+
+	/// Replaces the vertices of the canvas, quads of four vertices drawn by
+	/// [`Shader::draw_vertices`].
+	fn set_canvas(&mut self, device: &Device, vertices: &[[f32; 3]]) {
+		let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Canvas Vertex Buffer"),
+			contents: bytemuck::cast_slice(vertices),
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		});
+		if let Some(canvas) = self
+			.buffers
+			.iter_mut()
+			.find_map(|bg| bg.buffers.get_mut("Canvas Vertex Buffer"))
+		{
+			*canvas = buffer;
+		}
+	}
+
+	/// Records the draw of the canvas quad with this shader's pipeline and buffers, binding
+	/// the bind groups of `variant` (see [`create_pipeline_layout`]). Does nothing for compute
+	/// shaders.
+	fn draw(&self, render_pass: &mut RenderPass<'_>, variant: usize) {
+		self.draw_vertices(render_pass, variant, 0..4);
+	}
+
+	/// Same as [`Shader::draw`], drawing the quad of the canvas `vertices`.
+	fn draw_vertices(
+		&self,
+		render_pass: &mut RenderPass<'_>,
+		variant: usize,
+		vertices: std::ops::Range<u32>,
+	) {
+		let ShaderPipeline::Render(pipeline) = &self.pipeline else {
+			return;
+		};
+		render_pass.set_pipeline(pipeline);
+
+		for buffer_group in &self.buffers {
+			let _ = match buffer_group.binding_type {
+				BufferBindingType::BindGroup => render_pass.set_bind_group(
+					buffer_group.id.clone(),
+					buffer_group.bind_group(variant),
+					&[],
+				),
+				BufferBindingType::Vertex => {
+					for (i, (_, vertex_buffer)) in buffer_group.buffers.iter().enumerate() {
+						render_pass.set_vertex_buffer(i as u32, vertex_buffer.slice(..));
+					}
+				}
+			};
+		}
+
+		render_pass.draw(vertices, 0..1);
+	}
 }
 
 pub struct GraphicsHandle {
-	pub surface: Surface<'static>,
+	/// `None` for headless graphics, which draw into views given by the caller.
+	pub surface: Option<Surface<'static>>,
 	pub instance: Instance,
 	pub adapter: Adapter,
 	pub device: Device,
 	pub queue: Queue,
 	pub swapchain_info: SurfaceCapabilities,
 	pub shaders: Vec<Shader>,
+	/// Layers the shaders are drawn into before being composited.
+	layers: TexturePool,
+	compositor: Compositor,
+	config: SurfaceConfiguration,
 }
 pub type GraphicsHandleMutex = std::sync::Mutex<GraphicsHandle>;
 
@@ -122,23 +358,105 @@ pub fn create_graphics(window: SharedPtr<winit::window::Window>) -> GraphicsHand
 	};
 
 	surface.configure(&device, &config);
+	let compositor = Compositor::new(&device, swapchain_format);
 
 	GraphicsHandle {
-		surface,
+		surface: Some(surface),
 		instance,
 		adapter,
 		device,
 		queue,
 		swapchain_info,
 		shaders: Vec::new(),
+		layers: TexturePool::default(),
+		compositor,
+		config,
+	}
+}
+
+/// Format headless graphics draw in, that of [`crate::OffscreenTarget::new`] with sRGB.
+pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Creates graphics without a window, e.g. for CI, from a context made by
+/// [`crate::IADQContext::init_headless`]. They draw with [`GraphicsHandle::render_all_into`]
+/// and [`GraphicsHandle::screenshot`], in [`HEADLESS_FORMAT`].
+pub fn create_headless_graphics(
+	context: &crate::IADQContext,
+	width: u32,
+	height: u32,
+) -> GraphicsHandle {
+	let config = wgpu::SurfaceConfiguration {
+		usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+		format: HEADLESS_FORMAT,
+		width: width.max(1),
+		height: height.max(1),
+		present_mode: wgpu::PresentMode::Fifo,
+		desired_maximum_frame_latency: 2,
+		alpha_mode: wgpu::CompositeAlphaMode::PostMultiplied,
+		view_formats: vec![],
+	};
+	GraphicsHandle {
+		surface: None,
+		instance: context.instance.clone(),
+		adapter: context.adapter.clone(),
+		device: context.device.clone(),
+		queue: context.queue.clone(),
+		swapchain_info: SurfaceCapabilities {
+			formats: vec![HEADLESS_FORMAT],
+			present_modes: vec![config.present_mode],
+			alpha_modes: vec![config.alpha_mode],
+			usages: config.usage,
+		},
+		shaders: Vec::new(),
+		layers: TexturePool::default(),
+		compositor: Compositor::new(&context.device, HEADLESS_FORMAT),
+		config,
 	}
 }
 
+/// Creates the pipeline of a WGSL fragment shader, which is validated first so a broken
+/// shader is reported instead of making wgpu panic.
 pub fn create_shader_pipeline(
 	device: &Device,
 	swapchain_format: TextureFormat,
 	source: &str,
-) -> Shader {
+) -> Result<Shader, Box<ScriptError>> {
+	create_shader_pipeline_with_groups(
+		device,
+		swapchain_format,
+		source,
+		Vec::new(),
+		&NamedResources::default(),
+	)
+}
+
+/// Resources bound by name to the reflected bindings of a shader, in place of defaults.
+#[derive(Clone, Default)]
+pub struct NamedResources {
+	/// Views of the texture and storage texture variables, one map per variant of the bind
+	/// groups.
+	pub textures: Vec<HashMap<String, TextureView>>,
+	/// Buffers of the storage buffer variables.
+	pub buffers: HashMap<String, Buffer>,
+}
+
+/// Same as [`create_shader_pipeline`], with bind groups provided for some groups of the
+/// shader, at the id of their [`BufferGroup`]. The other groups are reflected from the shader,
+/// with `resources` bound to the variables of the same name.
+pub fn create_shader_pipeline_with_groups(
+	device: &Device,
+	swapchain_format: TextureFormat,
+	source: &str,
+	extra_groups: Vec<(BindGroupLayout, BufferGroup)>,
+	resources: &NamedResources,
+) -> Result<Shader, Box<ScriptError>> {
+	let source = &*crate::shadertoy::declare_inputs(source)?;
+	let module = validate_wgsl(source)?;
+	let bindings = reflect_bindings(&module, source)?;
+	warn_unknown_time_uniform(&module);
+
+	// Capture the errors naga can't see (bindings, targets) instead of letting wgpu panic on them.
+	device.push_error_scope(wgpu::ErrorFilter::Validation);
 	let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
 		label: None,
 		source: wgpu::ShaderSource::Wgsl(source.into()),
@@ -154,7 +472,16 @@ pub fn create_shader_pipeline(
 		conservative: false,
 	};
 
-	let (pipeline_layout, mut shader_buffers) = create_pipeline_layout(device);
+	let extra_layouts: Vec<(u32, &BindGroupLayout)> =
+		extra_groups.iter().map(|(l, g)| (g.id, l)).collect();
+	let (pipeline_layout, mut shader_buffers) = create_pipeline_layout(
+		device,
+		&bindings,
+		&extra_layouts,
+		resources,
+		ShaderStages::VERTEX_FRAGMENT,
+	);
+	shader_buffers.extend(extra_groups.into_iter().map(|(_, group)| group));
 
 	let canvas_vs = device.create_shader_module(wgpu::ShaderModuleDescriptor {
 		label: Some("Canvas Vertex Shader"),
@@ -207,84 +534,513 @@ pub fn create_shader_pipeline(
 		cache: None,
 	});
 
-	Shader {
-		pipeline: render_pipeline,
+	if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+		return Err(ScriptError::new("PipelineError", error.to_string()).into());
+	}
+	Ok(Shader {
+		pipeline: ShaderPipeline::Render(render_pipeline),
 		buffers: shader_buffers,
+		bindings,
 		time_start: std::time::Instant::now(),
-	}
+	})
 }
 
-pub fn create_pipeline_layout(device: &Device) -> (wgpu::PipelineLayout, Vec<BufferGroup>) {
-	let mut bind_groups: Vec<BufferGroup> = Vec::new();
-	// Create the time buffer
-	let time_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-		label: Some("Time Buffer"),
-		contents: bytemuck::cast_slice(&[0.0f32]),
-		usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+/// Whether a validated module is a compute shader: it has a `@compute` entry point and no
+/// `fs_main`.
+pub fn is_compute_shader(module: &naga::Module) -> bool {
+	let has_compute = module
+		.entry_points
+		.iter()
+		.any(|ep| ep.stage == naga::ShaderStage::Compute);
+	let has_fs_main = module
+		.entry_points
+		.iter()
+		.any(|ep| ep.name == "fs_main" && ep.stage == naga::ShaderStage::Fragment);
+	has_compute && !has_fs_main
+}
+
+/// Creates the pipeline of the first `@compute` entry point of a WGSL shader, with bind
+/// groups provided or reflected as in [`create_shader_pipeline_with_groups`].
+pub fn create_compute_pipeline(
+	device: &Device,
+	source: &str,
+	extra_groups: Vec<(BindGroupLayout, BufferGroup)>,
+	resources: &NamedResources,
+) -> Result<Shader, Box<ScriptError>> {
+	let source = &*crate::shadertoy::declare_inputs(source)?;
+	let module = validate_wgsl(source)?;
+	let entry_point = module
+		.entry_points
+		.iter()
+		.find(|ep| ep.stage == naga::ShaderStage::Compute)
+		.ok_or_else(|| ScriptError::new("ValidationError", "Missing `@compute` entry point"))?;
+	let bindings = reflect_bindings(&module, source)?;
+
+	device.push_error_scope(wgpu::ErrorFilter::Validation);
+	let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+		label: None,
+		source: wgpu::ShaderSource::Wgsl(source.into()),
+	});
+	let extra_layouts: Vec<(u32, &BindGroupLayout)> =
+		extra_groups.iter().map(|(l, g)| (g.id, l)).collect();
+	let (pipeline_layout, mut shader_buffers) = create_pipeline_layout(
+		device,
+		&bindings,
+		&extra_layouts,
+		resources,
+		ShaderStages::COMPUTE,
+	);
+	shader_buffers.extend(extra_groups.into_iter().map(|(_, group)| group));
+	let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+		label: Some("Compute Pipeline"),
+		layout: Some(&pipeline_layout),
+		module: &shader,
+		entry_point: Some(&entry_point.name),
+		compilation_options: Default::default(),
+		cache: None,
 	});
 
-	// Define screen size
-	let surface_size = [800.0f32, 628.0f32];
+	if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+		return Err(ScriptError::new("PipelineError", error.to_string()).into());
+	}
+	Ok(Shader {
+		pipeline: ShaderPipeline::Compute(compute_pipeline, entry_point.workgroup_size),
+		buffers: shader_buffers,
+		bindings,
+		time_start: std::time::Instant::now(),
+	})
+}
+
+/// Resource binding declared by a WGSL global variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderBinding {
+	pub group: u32,
+	pub binding: u32,
+	/// Name of the WGSL variable.
+	pub name: String,
+	pub ty: BindingType,
+	/// Size of buffers in bytes, 0 for other resources.
+	pub size: u64,
+	/// Size of the elements of runtime-sized array buffers, 0 for other resources.
+	pub stride: u64,
+}
+
+impl ShaderBinding {
+	/// Whether the shader can write to the resource.
+	pub fn writable(&self) -> bool {
+		match self.ty {
+			BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Storage { read_only },
+				..
+			} => !read_only,
+			BindingType::StorageTexture { access, .. } => access != StorageTextureAccess::ReadOnly,
+			_ => false,
+		}
+	}
+}
 
-	// Create uniform buffer
-	let surface_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-		label: Some("Surface Size Buffer"),
-		contents: bytemuck::cast_slice(&surface_size),
-		usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+/// Warns about an `f32` uniform at `@group(0) @binding(0)` that is not the
+/// [`TIME_UNIFORM`], likely meant to receive the time but never set.
+fn warn_unknown_time_uniform(module: &naga::Module) {
+	let unknown = module.global_variables.iter().find(|(_, var)| {
+		var.space == naga::AddressSpace::Uniform
+			&& var
+				.binding
+				.as_ref()
+				.is_some_and(|b| (b.group, b.binding) == (0, 0))
+			&& module.types[var.ty].inner == naga::TypeInner::Scalar(naga::Scalar::F32)
+			&& var.name.as_deref() != Some(TIME_UNIFORM)
 	});
+	if let Some((_, var)) = unknown {
+		log::warn!(
+			"Uniform '{}' at @group(0) @binding(0) is never set, name it '{}' to receive the time",
+			var.name.as_deref().unwrap_or_default(),
+			TIME_UNIFORM
+		);
+	}
+}
 
-	// Create a bind group layout
-	let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-		label: Some("Bind Group Layout"),
-		entries: &[
-			wgpu::BindGroupLayoutEntry {
-				binding: 0,
-				visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-				ty: wgpu::BindingType::Buffer {
-					ty: wgpu::BufferBindingType::Uniform,
-					has_dynamic_offset: false,
-					min_binding_size: None,
-				},
-				count: None,
-			},
-			wgpu::BindGroupLayoutEntry {
-				binding: 1,
-				visibility: wgpu::ShaderStages::FRAGMENT,
-				ty: wgpu::BindingType::Buffer {
-					ty: wgpu::BufferBindingType::Uniform,
-					has_dynamic_offset: false,
-					min_binding_size: None,
-				},
-				count: None,
-			},
-		],
+/// Stages a binding is visible to: writable storage is not allowed in vertex shaders.
+fn binding_visibility(visibility: ShaderStages, writable: bool) -> ShaderStages {
+	match writable {
+		true => visibility - ShaderStages::VERTEX,
+		false => visibility,
+	}
+}
+
+/// Names of the variables bound by a module, by group and binding.
+pub fn binding_names(module: &naga::Module) -> HashMap<(u32, u32), String> {
+	module
+		.global_variables
+		.iter()
+		.filter_map(|(_, var)| {
+			let binding = var.binding.as_ref()?;
+			Some(((binding.group, binding.binding), var.name.clone()?))
+		})
+		.collect()
+}
+
+/// Lists the resource bindings of a validated module, sorted by group and binding.
+pub fn reflect_bindings(
+	module: &naga::Module,
+	source: &str,
+) -> Result<Vec<ShaderBinding>, Box<ScriptError>> {
+	let mut bindings = Vec::new();
+	for (handle, var) in module.global_variables.iter() {
+		let Some(binding) = &var.binding else {
+			continue;
+		};
+		let name = var
+			.name
+			.clone()
+			.unwrap_or_else(|| format!("binding_{}_{}", binding.group, binding.binding));
+		let (ty, size) = reflect_binding_type(module, var).map_err(|message| {
+			let span = module.global_variables.get_span(handle);
+			wgsl_error(
+				"ReflectionError",
+				format!("Cannot bind '{}': {}", name, message),
+				std::iter::once((span, message.clone())),
+				source,
+			)
+		})?;
+		let stride = match module.types[var.ty].inner {
+			naga::TypeInner::Array {
+				size: naga::ArraySize::Dynamic,
+				stride,
+				..
+			} => stride as u64,
+			_ => 0,
+		};
+		bindings.push(ShaderBinding {
+			group: binding.group,
+			binding: binding.binding,
+			name,
+			ty,
+			size,
+			stride,
+		});
+	}
+	bindings.sort_by_key(|b| (b.group, b.binding));
+	Ok(bindings)
+}
+
+/// Binding type of a global variable, with the size of buffers.
+fn reflect_binding_type(
+	module: &naga::Module,
+	var: &naga::GlobalVariable,
+) -> Result<(BindingType, u64), String> {
+	use naga::{AddressSpace, ImageClass, ImageDimension, ScalarKind, StorageAccess, TypeInner};
+
+	let inner = &module.types[var.ty].inner;
+	// Uniform buffers are padded to 16 bytes, which WebGL requires.
+	let buffer_size = || {
+		(inner.size(module.to_ctx()) as u64)
+			.max(4)
+			.next_multiple_of(16)
+	};
+	let buffer = |ty| BindingType::Buffer {
+		ty,
+		has_dynamic_offset: false,
+		min_binding_size: None,
+	};
+	match var.space {
+		AddressSpace::Uniform => Ok((buffer(wgpu::BufferBindingType::Uniform), buffer_size())),
+		AddressSpace::Storage { access } => Ok((
+			buffer(wgpu::BufferBindingType::Storage {
+				read_only: !access.contains(StorageAccess::STORE),
+			}),
+			buffer_size(),
+		)),
+		AddressSpace::Handle => match *inner {
+			TypeInner::Sampler { comparison } => Ok((
+				BindingType::Sampler(if comparison {
+					SamplerBindingType::Comparison
+				} else {
+					SamplerBindingType::Filtering
+				}),
+				0,
+			)),
+			TypeInner::Image {
+				dim,
+				arrayed,
+				class,
+			} => {
+				let view_dimension = match (dim, arrayed) {
+					(ImageDimension::D1, false) => TextureViewDimension::D1,
+					(ImageDimension::D2, false) => TextureViewDimension::D2,
+					(ImageDimension::D2, true) => TextureViewDimension::D2Array,
+					(ImageDimension::D3, false) => TextureViewDimension::D3,
+					(ImageDimension::Cube, false) => TextureViewDimension::Cube,
+					(ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+					_ => return Err(format!("unsupported {:?} texture array", dim)),
+				};
+				let ty = match class {
+					ImageClass::Sampled { multi: true, .. } | ImageClass::Depth { multi: true } => {
+						return Err("multisampled textures are not supported".to_string())
+					}
+					ImageClass::Sampled { kind, .. } => BindingType::Texture {
+						sample_type: match kind {
+							ScalarKind::Sint => TextureSampleType::Sint,
+							ScalarKind::Uint => TextureSampleType::Uint,
+							_ => TextureSampleType::Float { filterable: true },
+						},
+						view_dimension,
+						multisampled: false,
+					},
+					ImageClass::Depth { .. } => BindingType::Texture {
+						sample_type: TextureSampleType::Depth,
+						view_dimension,
+						multisampled: false,
+					},
+					ImageClass::Storage { format, access } => BindingType::StorageTexture {
+						access: if access.contains(StorageAccess::LOAD | StorageAccess::STORE) {
+							StorageTextureAccess::ReadWrite
+						} else if access.contains(StorageAccess::STORE) {
+							StorageTextureAccess::WriteOnly
+						} else {
+							StorageTextureAccess::ReadOnly
+						},
+						format: storage_format(format)
+							.ok_or_else(|| format!("unsupported storage format {:?}", format))?,
+						view_dimension,
+					},
+				};
+				Ok((ty, 0))
+			}
+			_ => Err("unsupported resource type".to_string()),
+		},
+		_ => Err(format!("unsupported address space {:?}", var.space)),
+	}
+}
+
+/// Texture format of the storage textures shaders can declare.
+fn storage_format(format: naga::StorageFormat) -> Option<TextureFormat> {
+	use naga::StorageFormat as Format;
+	Some(match format {
+		Format::R32Float => TextureFormat::R32Float,
+		Format::R32Uint => TextureFormat::R32Uint,
+		Format::R32Sint => TextureFormat::R32Sint,
+		Format::Rg32Float => TextureFormat::Rg32Float,
+		Format::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+		Format::Rgba8Snorm => TextureFormat::Rgba8Snorm,
+		Format::Rgba8Uint => TextureFormat::Rgba8Uint,
+		Format::Rgba8Sint => TextureFormat::Rgba8Sint,
+		Format::Bgra8Unorm => TextureFormat::Bgra8Unorm,
+		Format::Rgba16Float => TextureFormat::Rgba16Float,
+		Format::Rgba32Float => TextureFormat::Rgba32Float,
+		Format::Rgba32Uint => TextureFormat::Rgba32Uint,
+		Format::Rgba32Sint => TextureFormat::Rgba32Sint,
+		_ => return None,
+	})
+}
+
+/// Resource bound to a reflected binding, by default a zeroed buffer, a 1x1 texture or a
+/// linear sampler.
+enum DefaultResource {
+	Buffer(Buffer),
+	Texture(TextureView),
+	Sampler(Sampler),
+}
+
+impl DefaultResource {
+	fn create(device: &Device, binding: &ShaderBinding) -> Self {
+		let label = Some(binding.name.as_str());
+		match binding.ty {
+			BindingType::Buffer { ty, .. } => {
+				DefaultResource::Buffer(device.create_buffer(&wgpu::BufferDescriptor {
+					label,
+					size: binding.size,
+					usage: match ty {
+						wgpu::BufferBindingType::Uniform => wgpu::BufferUsages::UNIFORM,
+						wgpu::BufferBindingType::Storage { .. } => wgpu::BufferUsages::STORAGE,
+					} | wgpu::BufferUsages::COPY_DST,
+					mapped_at_creation: false,
+				}))
+			}
+			BindingType::Sampler(ty) => DefaultResource::Sampler(
+				device.create_sampler(&wgpu::SamplerDescriptor {
+					label,
+					mag_filter: wgpu::FilterMode::Linear,
+					min_filter: wgpu::FilterMode::Linear,
+					compare: (ty == SamplerBindingType::Comparison)
+						.then_some(wgpu::CompareFunction::LessEqual),
+					..Default::default()
+				}),
+			),
+			BindingType::Texture {
+				sample_type,
+				view_dimension,
+				..
+			} => {
+				let format = match sample_type {
+					TextureSampleType::Float { .. } => TextureFormat::Rgba8Unorm,
+					TextureSampleType::Sint => TextureFormat::R32Sint,
+					TextureSampleType::Uint => TextureFormat::R32Uint,
+					TextureSampleType::Depth => TextureFormat::Depth32Float,
+				};
+				DefaultResource::Texture(default_texture(
+					device,
+					label,
+					format,
+					view_dimension,
+					wgpu::TextureUsages::TEXTURE_BINDING,
+				))
+			}
+			BindingType::StorageTexture {
+				format,
+				view_dimension,
+				..
+			} => DefaultResource::Texture(default_texture(
+				device,
+				label,
+				format,
+				view_dimension,
+				wgpu::TextureUsages::STORAGE_BINDING,
+			)),
+			_ => unreachable!("Binding types are reflected by reflect_binding_type"),
+		}
+	}
+
+	fn resource(&self) -> BindingResource<'_> {
+		match self {
+			DefaultResource::Buffer(buffer) => buffer.as_entire_binding(),
+			DefaultResource::Texture(view) => BindingResource::TextureView(view),
+			DefaultResource::Sampler(sampler) => BindingResource::Sampler(sampler),
+		}
+	}
+}
+
+fn default_texture(
+	device: &Device,
+	label: Option<&str>,
+	format: TextureFormat,
+	view_dimension: TextureViewDimension,
+	usage: wgpu::TextureUsages,
+) -> TextureView {
+	let (dimension, layers) = match view_dimension {
+		TextureViewDimension::D1 => (wgpu::TextureDimension::D1, 1),
+		TextureViewDimension::D3 => (wgpu::TextureDimension::D3, 1),
+		TextureViewDimension::Cube | TextureViewDimension::CubeArray => {
+			(wgpu::TextureDimension::D2, 6)
+		}
+		_ => (wgpu::TextureDimension::D2, 1),
+	};
+	let texture = device.create_texture(&wgpu::TextureDescriptor {
+		label,
+		size: Extent3d {
+			width: 1,
+			height: 1,
+			depth_or_array_layers: layers,
+		},
+		mip_level_count: 1,
+		sample_count: 1,
+		dimension,
+		format,
+		usage,
+		view_formats: &[],
 	});
+	texture.create_view(&wgpu::TextureViewDescriptor {
+		dimension: Some(view_dimension),
+		..Default::default()
+	})
+}
 
-	// Create a bind group
-	let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-		label: Some("Bind Group"),
-		layout: &bind_group_layout,
-		entries: &[
-			wgpu::BindGroupEntry {
-				binding: 0,
-				resource: time_buffer.as_entire_binding(),
-			},
-			wgpu::BindGroupEntry {
-				binding: 1,
-				resource: surface_size_buffer.as_entire_binding(),
+/// Creates the pipeline layout of reflected `bindings`, with default resources bound to
+/// them, or the views of `textures` with their name. Groups of `extra_layouts` are skipped,
+/// their bind groups are provided separately.
+///
+/// Each map of `resources.textures` gives a variant of the bind groups, sharing their
+/// buffers, so a shader can alternate between the two textures of a ping-pong without being
+/// rebuilt.
+pub fn create_pipeline_layout(
+	device: &Device,
+	bindings: &[ShaderBinding],
+	extra_layouts: &[(u32, &BindGroupLayout)],
+	resources: &NamedResources,
+	visibility: ShaderStages,
+) -> (wgpu::PipelineLayout, Vec<BufferGroup>) {
+	let (textures, buffers) = (&resources.textures, &resources.buffers);
+	let group_count = bindings
+		.iter()
+		.map(|b| b.group + 1)
+		.chain(extra_layouts.iter().map(|(group, _)| group + 1))
+		.max()
+		.unwrap_or(0);
+
+	let mut layouts = Vec::new();
+	let mut bind_groups: Vec<BufferGroup> = Vec::new();
+	for group in 0..group_count {
+		if let Some((_, layout)) = extra_layouts.iter().find(|(id, _)| *id == group) {
+			layouts.push((*layout).clone());
+			continue;
+		}
+
+		// Groups the shader skips get an empty bind group.
+		let group_bindings: Vec<_> = bindings.iter().filter(|b| b.group == group).collect();
+		let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some(&format!("Bind Group Layout {}", group)),
+			entries: &group_bindings
+				.iter()
+				.map(|b| wgpu::BindGroupLayoutEntry {
+					binding: b.binding,
+					visibility: binding_visibility(visibility, b.writable()),
+					ty: b.ty,
+					count: None,
+				})
+				.collect::<Vec<_>>(),
+		});
+		let resources: Vec<_> = group_bindings
+			.iter()
+			.map(|b| match (&b.ty, buffers.get(&b.name)) {
+				// A shared buffer smaller than the shader expects would fail when drawing.
+				(BindingType::Buffer { .. }, Some(buffer)) if buffer.size() >= b.size => {
+					DefaultResource::Buffer(buffer.clone())
+				}
+				_ => DefaultResource::create(device, b),
+			})
+			.collect();
+		let mut variants: Vec<_> = (0..textures.len().max(1))
+			.map(|variant| {
+				device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some(&format!("Bind Group {}", group)),
+					layout: &layout,
+					entries: &group_bindings
+						.iter()
+						.zip(&resources)
+						.map(|(b, resource)| wgpu::BindGroupEntry {
+							binding: b.binding,
+							resource: match (
+								&b.ty,
+								textures.get(variant).and_then(|t| t.get(&b.name)),
+							) {
+								(
+									BindingType::Texture { .. }
+									| BindingType::StorageTexture { .. },
+									Some(view),
+								) => wgpu::BindingResource::TextureView(view),
+								_ => resource.resource(),
+							},
+						})
+						.collect::<Vec<_>>(),
+				})
+			})
+			.collect();
+		let bind_group = variants.remove(0);
+		let mut buffer_group = group_bindings.iter().zip(resources).fold(
+			BufferGroup::new(group, Some(bind_group)),
+			|buffer_group, (b, resource)| match resource {
+				DefaultResource::Buffer(buffer) => buffer_group.with_buffer(b.name.clone(), buffer),
+				_ => buffer_group,
 			},
-		],
-	});
-	bind_groups.push(
-		BufferGroup::new(0, Some(bind_group))
-			.with_buffer("Time Buffer".to_string(), time_buffer)
-			.with_buffer("Surface Size Buffer".to_string(), surface_size_buffer),
-	);
+		);
+		buffer_group.variants = variants;
+		bind_groups.push(buffer_group);
+		layouts.push(layout);
+	}
 
-	// Create a pipeline layout
 	let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 		label: Some("Pipeline Layout"),
-		bind_group_layouts: &[&bind_group_layout],
+		bind_group_layouts: &layouts.iter().collect::<Vec<_>>(),
 		push_constant_ranges: &[],
 	});
 
@@ -292,67 +1048,43 @@ pub fn create_pipeline_layout(device: &Device) -> (wgpu::PipelineLayout, Vec<Buf
 }
 
 impl GraphicsHandle {
+	/// Next frame of the window, `None` when there is none to draw this time: a lost or
+	/// outdated surface is configured again for the next frame.
+	fn next_frame(&self) -> Option<SurfaceTexture> {
+		let surface = self.surface.as_ref()?;
+		match surface.get_current_texture() {
+			Ok(texture) => Some(texture),
+			Err(SurfaceError::Outdated | SurfaceError::Lost) => {
+				surface.configure(&self.device, &self.config);
+				None
+			}
+			Err(SurfaceError::Timeout) => None,
+			Err(error) => {
+				log::warn!("Cannot get the next frame: {}", error);
+				None
+			}
+		}
+	}
+
 	fn render(&self, shader: &Shader) {
 		// info!("RENDERING");
-		// Update ellapsed time:
-		shader.update_buffer(
-			&self.queue,
-			"Time Buffer".to_string(),
-			bytemuck::cast_slice(&[shader.ellapsed()]),
-			0,
-		);
-
-		let texture = self
-			.surface
-			.get_current_texture()
-			.expect("Cannot get current texture");
+		let Some(texture) = self.next_frame() else {
+			return;
+		};
 		let view = texture
 			.texture
 			.create_view(&wgpu::TextureViewDescriptor::default());
-		let mut encoder = self
-			.device
-			.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-		{
-			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-				label: None,
-				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-					view: &view,
-					resolve_target: None,
-					ops: wgpu::Operations {
-						load: wgpu::LoadOp::Clear(wgpu::Color {
-							r: 0.0,
-							g: 0.0,
-							b: 0.0,
-							a: 0.0,
-						}),
-						store: wgpu::StoreOp::Store,
-					},
-				})],
-				depth_stencil_attachment: None,
-				..Default::default()
-			});
-			render_pass.set_pipeline(&shader.pipeline);
-
-			for buffer_group in &shader.buffers {
-				let _ = match buffer_group.binding_type {
-					BufferBindingType::BindGroup => render_pass.set_bind_group(
-						buffer_group.id.clone(),
-						&buffer_group.bind_group,
-						&[],
-					),
-					BufferBindingType::Vertex => {
-						for (i, (_, vertex_buffer)) in buffer_group.buffers.iter().enumerate() {
-							render_pass.set_vertex_buffer(i as u32, vertex_buffer.slice(..));
-						}
-					}
-				};
-			}
-
-			render_pass.draw(0..4, 0..1);
+		let size = texture.texture.size();
+		let size = (size.width as f32, size.height as f32);
+		shader.set_surface_size(&self.queue, size);
+		ShaderToyInputs {
+			resolution: size,
+			time: shader.ellapsed(),
+			date: crate::shadertoy::date_now(),
+			..Default::default()
 		}
-
-		self.queue.submit(std::iter::once(encoder.finish()));
+		.write(shader, &self.queue);
+		shader.render(&view, &self.device, &self.queue);
 		texture.present();
 	}
 
@@ -364,53 +1096,2169 @@ impl GraphicsHandle {
 		self.render(shader);
 	}
 
-	pub fn render_all(&self) {
-		for shader in &self.shaders {
-			self.render(shader);
-		}
+	/// Draws every shader into a layer and composites the layers over a single frame, the
+	/// last shader on top. Headless graphics have no frame to draw.
+	pub fn render_all(&mut self) {
+		let Some(texture) = self.next_frame() else {
+			return;
+		};
+		let view = texture
+			.texture
+			.create_view(&wgpu::TextureViewDescriptor::default());
+		let size = (texture.texture.width(), texture.texture.height());
+		self.render_layers(&view, size);
+		texture.present();
 	}
 
-	pub fn with_shader(mut self, source: &str) -> Self {
-		let shader =
-			create_shader_pipeline(&self.device, self.swapchain_info.formats[0], source.into());
-		self.shaders.push(shader);
-		self
+	/// Draws the shaders as [`GraphicsHandle::render_all`] does, into `view` of `size`
+	/// pixels and of the [`GraphicsHandle::format`].
+	pub fn render_all_into(&mut self, view: &TextureView, size: (u32, u32)) {
+		self.render_layers(view, size);
 	}
 
-	pub fn apply_shader(self, shader_id: usize) -> Self {
-		self.render_shader(shader_id);
-		self
+	/// Format of the frames the shaders are composited into.
+	pub fn format(&self) -> TextureFormat {
+		self.compositor.format()
 	}
 
-	pub fn spawn_render_loop(self) {
-		loop {
-			self.render_all();
-			let shad = self.shaders.get(0).unwrap();
-			let ell = shad.ellapsed();
-			let new_vert = VERTICES
-				.iter()
-				.map(|v| [v[0] + f32::sin(ell), v[1] + f32::cos(ell), 0.0])
-				.collect::<Vec<_>>();
+	/// Draws the shader layers and composites them into `view`, of the compositor format.
+	fn render_layers(&mut self, view: &TextureView, size: (u32, u32)) {
+		let format = self.compositor.format();
+		let mut graph = RenderGraph::new();
+		let date = crate::shadertoy::date_now();
+		for (i, shader) in self.shaders.iter().enumerate() {
+			let layer = format!("shader {}", i);
+			graph.add_texture(layer.clone(), TextureDesc { size, format });
+			graph.add_pass(layer.clone(), Vec::new(), vec![layer.clone()], i);
+			graph.add_layer(layer, None);
 
-			shad.update_buffer(
-				&self.queue,
-				"Canvas Vertex Buffer".to_string(),
-				bytemuck::cast_slice(&new_vert),
-				0,
+			let resolution = (size.0 as f32, size.1 as f32);
+			shader.set_surface_size(&self.queue, resolution);
+			shader.set_time(&self.queue, shader.ellapsed());
+			ShaderToyInputs {
+				resolution,
+				time: shader.ellapsed(),
+				date,
+				..Default::default()
+			}
+			.write(shader, &self.queue);
+		}
+		let plan = graph
+			.compile()
+			.expect("Shader layers do not depend on each other");
+		let views = self.layers.views(&self.device, &plan);
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("Shader Layers"),
+			});
+		for &index in &plan.order {
+			let pass = graph.pass(index);
+			let mut render_pass = begin_color_pass(
+				&mut encoder,
+				"Shader Layer",
+				&views[&pass.name],
+				wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+			);
+			self.shaders[pass.payload].draw(&mut render_pass, 0);
+		}
+		{
+			let mut render_pass = begin_color_pass(
+				&mut encoder,
+				"Composite",
+				view,
+				wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
 			);
-			// self.render_shader(0);
-			// tokio::time::sleep(Duration::from_millis(20)).await;
+			self.compositor
+				.composite(&self.device, &mut render_pass, size, graph.layers(), &views);
 		}
+		self.queue.submit(std::iter::once(encoder.finish()));
 	}
 
-	pub fn as_mutex(self) -> GraphicsHandleMutex {
-		std::sync::Mutex::new(self)
+	/// Replaces the shader at `index`, or adds it after the last one.
+	pub fn set_shader(&mut self, index: usize, source: &str) -> Result<(), Box<ScriptError>> {
+		let shader = create_shader_pipeline(&self.device, self.config.format, source)?;
+		match self.shaders.get_mut(index) {
+			Some(current) => *current = shader,
+			None => self.shaders.push(shader),
+		}
+		Ok(())
 	}
-}
 
-// Threaded Render
-// tauri::async_runtime::spawn(async move {
-//     let frame_duration = Duration::from_millis(16); // ~60 FPS
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.config.width = width.max(1);
+		self.config.height = height.max(1);
+		if let Some(surface) = &self.surface {
+			surface.configure(&self.device, &self.config);
+		}
+	}
+
+	/// Draws a frame of the size of the surface into a PNG file.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn screenshot(&mut self, path: &std::path::Path) -> Result<(), String> {
+		// Checked first, writing the image panics on failure.
+		std::fs::File::create(path).map_err(|error| error.to_string())?;
+		let target = crate::OffscreenTarget::with_format(
+			&self.device,
+			self.config.width,
+			self.config.height,
+			self.compositor.format(),
+		);
+		self.render_layers(&target.view(), target.size());
+		target.save_png(
+			&self.device,
+			&self.queue,
+			path.to_string_lossy().into_owned(),
+		);
+		Ok(())
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn set_pacing(&mut self, pacing: crate::render_loop::FramePacing) {
+		self.config.present_mode = match pacing {
+			crate::render_loop::FramePacing::VSync => PresentMode::Fifo,
+			crate::render_loop::FramePacing::Fps(_) => PresentMode::AutoNoVsync,
+		};
+		if let Some(surface) = &self.surface {
+			surface.configure(&self.device, &self.config);
+		}
+	}
+
+	pub fn with_shader(mut self, source: &str) -> Self {
+		match create_shader_pipeline(&self.device, self.swapchain_info.formats[0], source) {
+			Ok(shader) => self.shaders.push(shader),
+			Err(error) => log::error!("Cannot create shader: {}", error),
+		}
+		self
+	}
+
+	pub fn apply_shader(self, shader_id: usize) -> Self {
+		self.render_shader(shader_id);
+		self
+	}
+
+	/// Moves the handle to a render thread drawing the shaders at the pace of `pacing`.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn spawn_render_loop(
+		mut self,
+		pacing: crate::render_loop::FramePacing,
+	) -> crate::render_loop::RenderLoop {
+		self.set_pacing(pacing);
+		crate::render_loop::RenderLoop::spawn(self, pacing)
+	}
+
+	pub fn as_mutex(self) -> GraphicsHandleMutex {
+		std::sync::Mutex::new(self)
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::render_loop::LoopTarget for GraphicsHandle {
+	fn render_frame(&mut self) {
+		self.render_all();
+	}
+
+	fn command(&mut self, command: crate::render_loop::RenderCommand) {
+		use crate::render_loop::RenderCommand;
+		match command {
+			RenderCommand::SetShader { index, source } => {
+				if let Err(error) = self.set_shader(index, &source) {
+					log::error!("Cannot create shader: {}", error);
+				}
+			}
+			RenderCommand::Resize { width, height } => self.resize(width, height),
+			RenderCommand::Screenshot { path, done } => {
+				let _ = done.send(self.screenshot(&path));
+			}
+			RenderCommand::SetPacing(pacing) => self.set_pacing(pacing),
+			RenderCommand::SetVisible(_) | RenderCommand::Stop => {}
+		}
+	}
+}
+
+/// Full-viewport quad drawn by node pipelines, as a triangle strip.
+const QUAD_VERTICES: &[[f32; 3]; 4] = &[
+	[-1.0, -1.0, 0.0],
+	[1.0, -1.0, 0.0],
+	[-1.0, 1.0, 0.0],
+	[1.0, 1.0, 0.0],
+];
+
+/// Vertices of the canvas of node pipelines holding the quad of the node area, rewritten
+/// every frame.
+const AREA_QUAD: std::ops::Range<u32> = 4..8;
+
+/// Quad covering `area` (`[x, y, width, height]`) of a target of `size` pixels, in the
+/// order of [`QUAD_VERTICES`].
+fn area_quad(area: [f32; 4], size: (u32, u32)) -> [[f32; 3]; 4] {
+	let [x, y, width, height] = area;
+	let ndc = |px: f32, py: f32| {
+		[
+			px / size.0 as f32 * 2.0 - 1.0,
+			1.0 - py / size.1 as f32 * 2.0,
+			0.0,
+		]
+	};
+	[
+		ndc(x, y + height),
+		ndc(x + width, y + height),
+		ndc(x, y),
+		ndc(x + width, y),
+	]
+}
+
+/// Converts a naga diagnostic into a [`ScriptError`] labelled with its spans in `source`.
+fn wgsl_error(
+	kind: &str,
+	message: String,
+	spans: impl Iterator<Item = (naga::Span, String)>,
+	source: &str,
+) -> ScriptError {
+	let mut error = ScriptError::new(kind, message);
+	error.labels = spans
+		.filter(|(span, _)| span.is_defined())
+		.map(|(span, message)| {
+			let location = span.location(source);
+			let start = location.line_position;
+			// Spans running over several lines are cut at the end of their first one.
+			let line_end = source_text(source, location.line_number)
+				.map_or(start, |text| text.len() as u32 + 1);
+			ScriptLabel {
+				line: location.line_number,
+				columns: (
+					start,
+					(start + location.length).min(line_end).max(start + 1),
+				),
+				message,
+			}
+		})
+		.collect();
+	if let Some(label) = error.labels.first() {
+		error.line = Some(label.line);
+		error.columns = Some(label.columns);
+		error.text = source_text(source, label.line);
+	}
+	error
+}
+
+/// Parses and validates a WGSL fragment shader, locating the errors in `source`.
+///
+/// The Shadertoy inputs the shader uses are declared, see [`crate::shadertoy`].
+pub fn validate_wgsl(source: &str) -> Result<naga::Module, Box<ScriptError>> {
+	let source = &*crate::shadertoy::declare_inputs(source)?;
+	let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+		let labels = e.labels().map(|(span, label)| (span, label.to_string()));
+		wgsl_error("ParseError", e.message().to_string(), labels, source)
+	})?;
+	naga::valid::Validator::new(
+		naga::valid::ValidationFlags::all(),
+		naga::valid::Capabilities::all(),
+	)
+	.validate(&module)
+	.map_err(|e| {
+		// Validation errors nest, e.g. the invalid function, then the invalid expression in it.
+		let mut message = e.as_inner().to_string();
+		let mut cause = std::error::Error::source(e.as_inner());
+		while let Some(error) = cause {
+			message = format!("{}: {}", message, error);
+			cause = error.source();
+		}
+		wgsl_error("ValidationError", message, e.spans().cloned(), source)
+	})?;
+
+	let has_fs_main = module
+		.entry_points
+		.iter()
+		.any(|ep| ep.name == "fs_main" && ep.stage == naga::ShaderStage::Fragment);
+	if !has_fs_main && !is_compute_shader(&module) {
+		return Err(ScriptError::new(
+			"ValidationError",
+			"Missing `@fragment fn fs_main` or `@compute` entry point",
+		)
+		.into());
+	}
+	Ok(module)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScriptBufferKind {
+	Uniform,
+	Storage,
+}
+
+/// Buffer declared by a node script, bound in group 1 of its pipeline.
+struct ScriptBuffer {
+	binding: u32,
+	kind: ScriptBufferKind,
+	size: u64,
+	/// Content written before a device was attached.
+	pending: Option<Vec<u8>>,
+	buffer: Option<Buffer>,
+}
+
+impl ScriptBuffer {
+	fn create(&mut self, device: &Device) {
+		let usage = match self.kind {
+			ScriptBufferKind::Uniform => wgpu::BufferUsages::UNIFORM,
+			ScriptBufferKind::Storage => wgpu::BufferUsages::STORAGE,
+		} | wgpu::BufferUsages::COPY_DST;
+		let contents = self.pending.take().unwrap_or_default();
+		self.buffer = Some(
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some(&format!("Script Buffer {}", self.binding)),
+				contents: &contents,
+				usage,
+			}),
+		);
+	}
+}
+
+/// Texture formats node scripts can upload to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScriptTextureFormat {
+	R8Unorm,
+	Rgba8Unorm,
+	R32Float,
+	Rg32Float,
+	Rgba32Float,
+}
+
+impl ScriptTextureFormat {
+	pub fn parse(name: &str) -> Option<Self> {
+		match name {
+			"r8unorm" => Some(Self::R8Unorm),
+			"rgba8unorm" => Some(Self::Rgba8Unorm),
+			"r32float" => Some(Self::R32Float),
+			"rg32float" => Some(Self::Rg32Float),
+			"rgba32float" => Some(Self::Rgba32Float),
+			_ => None,
+		}
+	}
+
+	pub fn channels(&self) -> usize {
+		match self {
+			Self::R8Unorm | Self::R32Float => 1,
+			Self::Rg32Float => 2,
+			Self::Rgba8Unorm | Self::Rgba32Float => 4,
+		}
+	}
+
+	/// Whether channels are `f32`, otherwise they are `u8`.
+	pub fn is_float(&self) -> bool {
+		matches!(self, Self::R32Float | Self::Rg32Float | Self::Rgba32Float)
+	}
+
+	pub fn texel_size(&self) -> usize {
+		self.channels() * if self.is_float() { 4 } else { 1 }
+	}
+
+	fn texture_format(&self) -> TextureFormat {
+		match self {
+			Self::R8Unorm => TextureFormat::R8Unorm,
+			Self::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+			Self::R32Float => TextureFormat::R32Float,
+			Self::Rg32Float => TextureFormat::Rg32Float,
+			Self::Rgba32Float => TextureFormat::Rgba32Float,
+		}
+	}
+}
+
+/// 2D texture declared by a node script, bound in group 1 of its pipeline.
+///
+/// Bound as non-filterable, so shaders read it with `textureLoad`.
+struct ScriptTexture {
+	binding: u32,
+	size: Extent3d,
+	format: ScriptTextureFormat,
+	/// Content written before a device was attached.
+	pending: Option<Vec<u8>>,
+	texture: Option<Texture>,
+}
+
+impl ScriptTexture {
+	fn create(&mut self, device: &Device, queue: &Queue) {
+		let descriptor = wgpu::TextureDescriptor {
+			label: Some(&format!("Script Texture {}", self.binding)),
+			size: self.size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: self.format.texture_format(),
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		};
+		self.texture = Some(match self.pending.take() {
+			Some(data) => device.create_texture_with_data(
+				queue,
+				&descriptor,
+				wgpu::util::TextureDataOrder::LayerMajor,
+				&data,
+			),
+			None => device.create_texture(&descriptor),
+		});
+	}
+
+	fn byte_size(&self) -> usize {
+		(self.size.width * self.size.height) as usize * self.format.texel_size()
+	}
+}
+
+/// Pipeline created by a node script, drawn in the node's area of the board.
+struct NodePipeline {
+	node: String,
+	source: String,
+	buffers: Vec<ScriptBuffer>,
+	textures: Vec<ScriptTexture>,
+	/// Workgroups dispatched every frame when the shader is a compute shader.
+	dispatch: DispatchSize,
+	/// Built on first draw, and rebuilt when the buffer layout changes.
+	shader: Option<Shader>,
+	/// Set when building failed, so a broken pipeline is not rebuilt every frame.
+	error: Option<String>,
+}
+
+impl NodePipeline {
+	fn binding_used(&self, binding: u32) -> bool {
+		self.buffers.iter().any(|b| b.binding == binding)
+			|| self.textures.iter().any(|t| t.binding == binding)
+	}
+
+	fn build(
+		&mut self,
+		device: &Device,
+		queue: &Queue,
+		format: TextureFormat,
+		resources: &NamedResources,
+	) -> Result<Shader, Box<ScriptError>> {
+		let module = validate_wgsl(&self.source)?;
+		let compute = is_compute_shader(&module);
+		let visibility = match compute {
+			true => ShaderStages::COMPUTE,
+			false => ShaderStages::VERTEX_FRAGMENT,
+		};
+		let bindings = reflect_bindings(&module, &crate::shadertoy::declare_inputs(&self.source)?)?;
+		for buffer in self.buffers.iter_mut().filter(|b| b.buffer.is_none()) {
+			buffer.create(device);
+		}
+		for texture in self.textures.iter_mut().filter(|t| t.texture.is_none()) {
+			texture.create(device, queue);
+		}
+
+		let mut extra_groups = Vec::new();
+		if !self.buffers.is_empty() || !self.textures.is_empty() {
+			let texture_views: Vec<_> = self
+				.textures
+				.iter()
+				.map(|t| {
+					let view = t.texture.as_ref().unwrap().create_view(&Default::default());
+					(t.binding, view)
+				})
+				.collect();
+
+			let mut layout_entries: Vec<_> = self
+				.buffers
+				.iter()
+				.map(|b| {
+					// Compute shaders may write to their storage buffers.
+					let writable = b.kind == ScriptBufferKind::Storage
+						&& bindings
+							.iter()
+							.any(|r| (r.group, r.binding) == (1, b.binding) && r.writable());
+					wgpu::BindGroupLayoutEntry {
+						binding: b.binding,
+						visibility: binding_visibility(visibility, writable),
+						ty: wgpu::BindingType::Buffer {
+							ty: match b.kind {
+								ScriptBufferKind::Uniform => wgpu::BufferBindingType::Uniform,
+								ScriptBufferKind::Storage => wgpu::BufferBindingType::Storage {
+									read_only: !writable,
+								},
+							},
+							has_dynamic_offset: false,
+							min_binding_size: None,
+						},
+						count: None,
+					}
+				})
+				.collect();
+			layout_entries.extend(texture_views.iter().map(|(binding, _)| {
+				wgpu::BindGroupLayoutEntry {
+					binding: *binding,
+					visibility,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: false },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				}
+			}));
+			let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Script Bind Group Layout"),
+				entries: &layout_entries,
+			});
+			let mut entries: Vec<_> = self
+				.buffers
+				.iter()
+				.map(|b| wgpu::BindGroupEntry {
+					binding: b.binding,
+					resource: b.buffer.as_ref().unwrap().as_entire_binding(),
+				})
+				.collect();
+			entries.extend(
+				texture_views
+					.iter()
+					.map(|(binding, view)| wgpu::BindGroupEntry {
+						binding: *binding,
+						resource: wgpu::BindingResource::TextureView(view),
+					}),
+			);
+			let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Script Bind Group"),
+				layout: &layout,
+				entries: &entries,
+			});
+			// Buffers are named after their WGSL variable, when the shader declares one.
+			let names = binding_names(&module);
+			let group =
+				self.buffers
+					.iter()
+					.fold(BufferGroup::new(1, Some(bind_group)), |group, b| {
+						let name = names
+							.get(&(1, b.binding))
+							.cloned()
+							.unwrap_or_else(|| format!("Script Buffer {}", b.binding));
+						group.with_buffer(name, b.buffer.clone().unwrap())
+					});
+			extra_groups.push((layout, group));
+		}
+
+		if compute {
+			let shader = create_compute_pipeline(device, &self.source, extra_groups, resources)?;
+			let limit = device.limits().max_compute_workgroups_per_dimension;
+			match self.dispatch.workgroups(&shader) {
+				Ok(workgroups) if workgroups.iter().all(|&count| count <= limit) => {}
+				Ok(workgroups) => {
+					return Err(ScriptError::new(
+						"PipelineError",
+						format!(
+							"Dispatching {:?} workgroups exceeds the limit of {}",
+							workgroups, limit
+						),
+					)
+					.into())
+				}
+				Err(error) => return Err(ScriptError::new("PipelineError", error).into()),
+			}
+			return Ok(shader);
+		}
+		let mut shader = create_shader_pipeline_with_groups(
+			device,
+			format,
+			&self.source,
+			extra_groups,
+			resources,
+		)?;
+		// The full quad for textures, then the quad of the node area on the board.
+		shader.set_canvas(device, &[*QUAD_VERTICES, *QUAD_VERTICES].concat());
+		Ok(shader)
+	}
+}
+
+/// Workgroups dispatched by a compute shader every frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DispatchSize {
+	/// Number of workgroups along each axis.
+	Workgroups([u32; 3]),
+	/// Number of invocations along each axis, rounded up to whole workgroups.
+	Invocations([u32; 3]),
+	/// One invocation along `x` per element of the runtime-sized array of a storage buffer.
+	BufferLength(String),
+}
+
+impl Default for DispatchSize {
+	fn default() -> Self {
+		DispatchSize::Workgroups([1, 1, 1])
+	}
+}
+
+impl DispatchSize {
+	/// Workgroup counts of a compute shader.
+	pub fn workgroups(&self, shader: &Shader) -> Result<[u32; 3], String> {
+		let size = shader
+			.workgroup_size()
+			.ok_or_else(|| "Only compute shaders are dispatched".to_string())?;
+		match self {
+			DispatchSize::Workgroups(count) => Ok(*count),
+			DispatchSize::Invocations(count) => Ok([0, 1, 2].map(|i| count[i].div_ceil(size[i]))),
+			DispatchSize::BufferLength(name) => {
+				let stride = shader
+					.bindings()
+					.iter()
+					.find(|b| b.name == *name)
+					.map_or(0, |b| b.stride);
+				let buffer = shader.buffer(name).filter(|_| stride > 0).ok_or_else(|| {
+					format!(
+						"'{}' is not a runtime-sized array buffer of the shader",
+						name
+					)
+				})?;
+				let length = buffer.size() / stride;
+				Ok([length.div_ceil(size[0] as u64) as u32, 1, 1])
+			}
+		}
+	}
+}
+
+/// Formats of the storage textures shared by nodes, writable by compute shaders and
+/// filterable by the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum StorageTextureFormat {
+	#[default]
+	Rgba8Unorm,
+	Rgba16Float,
+}
+
+impl StorageTextureFormat {
+	pub fn parse(name: &str) -> Option<Self> {
+		match name {
+			"rgba8unorm" => Some(Self::Rgba8Unorm),
+			"rgba16float" => Some(Self::Rgba16Float),
+			_ => None,
+		}
+	}
+
+	fn texture_format(&self) -> TextureFormat {
+		match self {
+			Self::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+			Self::Rgba16Float => TextureFormat::Rgba16Float,
+		}
+	}
+}
+
+/// Storage buffer or 2D texture shared by name: it is bound to the variables of that name in
+/// every node shader, so compute shaders write what the others read.
+enum StorageResource {
+	Buffer {
+		/// Content written before a device was attached.
+		pending: Option<Vec<u8>>,
+		size: u64,
+		buffer: Option<Buffer>,
+	},
+	Texture {
+		size: (u32, u32),
+		format: StorageTextureFormat,
+		view: Option<TextureView>,
+	},
+}
+
+impl StorageResource {
+	fn created(&self) -> bool {
+		match self {
+			StorageResource::Buffer { buffer, .. } => buffer.is_some(),
+			StorageResource::Texture { view, .. } => view.is_some(),
+		}
+	}
+
+	fn create(&mut self, device: &Device, name: &str) {
+		match self {
+			StorageResource::Buffer {
+				pending,
+				size,
+				buffer,
+			} => {
+				let contents = pending.take().unwrap_or_else(|| vec![0; *size as usize]);
+				*buffer = Some(
+					device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+						label: Some(name),
+						contents: &contents,
+						usage: wgpu::BufferUsages::STORAGE
+							| wgpu::BufferUsages::COPY_DST
+							| wgpu::BufferUsages::COPY_SRC,
+					}),
+				);
+			}
+			StorageResource::Texture { size, format, view } => {
+				let texture = device.create_texture(&wgpu::TextureDescriptor {
+					label: Some(name),
+					size: Extent3d {
+						width: size.0,
+						height: size.1,
+						depth_or_array_layers: 1,
+					},
+					mip_level_count: 1,
+					sample_count: 1,
+					dimension: wgpu::TextureDimension::D2,
+					format: format.texture_format(),
+					usage: wgpu::TextureUsages::STORAGE_BINDING
+						| wgpu::TextureUsages::TEXTURE_BINDING,
+					view_formats: &[],
+				});
+				*view = Some(texture.create_view(&Default::default()));
+			}
+		}
+	}
+}
+
+/// View of the board: a node area `[x, y, width, height]` is drawn at `[x, y] * zoom + pan`,
+/// `zoom` times larger, in physical pixels of the target.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoardCamera {
+	pub pan: (f32, f32),
+	pub zoom: f32,
+}
+
+impl Default for BoardCamera {
+	fn default() -> Self {
+		BoardCamera {
+			pan: (0.0, 0.0),
+			zoom: 1.0,
+		}
+	}
+}
+
+impl BoardCamera {
+	/// Area of the target a board area is drawn in.
+	pub fn to_target(self, area: [f32; 4]) -> [f32; 4] {
+		let [x, y, width, height] = area;
+		[
+			x * self.zoom + self.pan.0,
+			y * self.zoom + self.pan.1,
+			width * self.zoom,
+			height * self.zoom,
+		]
+	}
+
+	/// Position on the board drawn at a position of the target.
+	pub fn to_board(self, (x, y): (f32, f32)) -> (f32, f32) {
+		((x - self.pan.0) / self.zoom, (y - self.pan.1) / self.zoom)
+	}
+}
+
+/// Graphics objects created by node scripts, drawn over the board every frame.
+///
+/// Scripts may run before the graphics context exists, so GPU objects are created lazily
+/// once a device is attached.
+#[derive(Default)]
+pub struct NodeGraphics {
+	/// Pipelines of the nodes by id, in creation order, which is the drawing order.
+	pipelines: BTreeMap<usize, NodePipeline>,
+	next_pipeline: usize,
+	/// Area of each node on the board, in physical pixels at zoom 1: `[x, y, width, height]`.
+	/// Nodes without one cover the whole target, whatever the camera.
+	areas: HashMap<String, [f32; 4]>,
+	camera: BoardCamera,
+	/// Pipeline of each WGSL node.
+	node_shaders: HashMap<String, usize>,
+	/// Nodes drawn into the `iChannel` textures of each node.
+	channels: HashMap<String, [Option<String>; CHANNEL_COUNT]>,
+	/// Transient textures the nodes read by channels were drawn into on the last frame.
+	channel_targets: HashMap<String, ChannelTarget>,
+	/// Textures of the transient resources of the frame graph.
+	transients: TexturePool,
+	/// Buffer passes drawn before the shader of each node.
+	passes: HashMap<String, NodePasses>,
+	/// Storage shared between the node shaders, by variable name.
+	storage: HashMap<String, StorageResource>,
+	mouse: ShaderMouse,
+	frame: i32,
+	last_frame: Option<Instant>,
+	context: Option<(Device, Queue, TextureFormat)>,
+}
+
+/// Texture a node read by channels is drawn into, before the nodes reading it.
+struct ChannelTarget {
+	view: TextureView,
+	size: (u32, u32),
+}
+
+/// Pass of the node graphics in the frame graph.
+#[derive(Debug, Clone)]
+enum NodePass {
+	Compute(usize),
+	/// Buffer pass of a node, by index.
+	Buffer(String, usize),
+	/// Drawing of a node read by channels into its channel texture.
+	Channel(String),
+	Draw(usize),
+}
+
+/// Frame graph resource of the target of a buffer pass.
+fn pass_resource(node: &str, pass: &str) -> String {
+	format!("pass {}/{}", node, pass)
+}
+
+/// Frame graph resource of the channel texture of a node.
+fn channel_resource(node: &str) -> String {
+	format!("channel {}", node)
+}
+
+/// Formats of the render targets of buffer passes, all filterable so shaders can sample them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TargetFormat {
+	#[default]
+	Rgba8Unorm,
+	Rgba16Float,
+	Rg16Float,
+	R16Float,
+}
+
+impl TargetFormat {
+	pub fn parse(name: &str) -> Option<Self> {
+		match name {
+			"rgba8unorm" => Some(Self::Rgba8Unorm),
+			"rgba16float" => Some(Self::Rgba16Float),
+			"rg16float" => Some(Self::Rg16Float),
+			"r16float" => Some(Self::R16Float),
+			_ => None,
+		}
+	}
+
+	fn texture_format(&self) -> TextureFormat {
+		match self {
+			Self::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+			Self::Rgba16Float => TextureFormat::Rgba16Float,
+			Self::Rg16Float => TextureFormat::Rg16Float,
+			Self::R16Float => TextureFormat::R16Float,
+		}
+	}
+}
+
+/// Double-buffered render target: a pass draws into one texture while reading its previous
+/// frame from the other, and the textures swap roles every frame.
+pub struct RenderTarget {
+	textures: [Texture; 2],
+	views: [TextureView; 2],
+}
+
+impl RenderTarget {
+	pub fn new(device: &Device, label: &str, size: (u32, u32), format: TextureFormat) -> Self {
+		let textures = [0, 1].map(|i| {
+			device.create_texture(&wgpu::TextureDescriptor {
+				label: Some(&format!("{} {}", label, i)),
+				size: Extent3d {
+					width: size.0,
+					height: size.1,
+					depth_or_array_layers: 1,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: wgpu::TextureDimension::D2,
+				format,
+				usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+					| wgpu::TextureUsages::TEXTURE_BINDING
+					| wgpu::TextureUsages::COPY_SRC,
+				view_formats: &[],
+			})
+		});
+		let views = [0, 1].map(|i| textures[i].create_view(&Default::default()));
+		RenderTarget { textures, views }
+	}
+
+	pub fn size(&self) -> (u32, u32) {
+		let size = self.textures[0].size();
+		(size.width, size.height)
+	}
+
+	pub fn format(&self) -> TextureFormat {
+		self.textures[0].format()
+	}
+
+	/// View drawn on the frames of `parity`, holding the previous frame on the other ones.
+	pub fn view(&self, parity: usize) -> &TextureView {
+		&self.views[parity % 2]
+	}
+}
+
+/// Buffer pass of a node, like Shadertoy's Buffer A to D: a fragment shader drawn every frame
+/// into its own [`RenderTarget`], before the shader of the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferPassDesc {
+	pub name: String,
+	pub source: String,
+	pub format: TargetFormat,
+	/// Size of the target in pixels, the size of the node area when `None`.
+	pub size: Option<(u32, u32)>,
+	/// Passes of the node read by the `iChannel` textures. Passes drawn before this one give
+	/// their current frame, the others (this one included) their previous frame.
+	pub channels: [Option<String>; CHANNEL_COUNT],
+}
+
+/// Buffer passes of a node, and the passes its shader reads as the Image pass.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShaderPasses {
+	pub buffers: Vec<BufferPassDesc>,
+	/// Passes read by the `iChannel` textures of the node shader, in place of the nodes set
+	/// with [`NodeGraphics::set_channel`].
+	pub image_channels: [Option<String>; CHANNEL_COUNT],
+}
+
+struct BufferPass {
+	desc: BufferPassDesc,
+	/// Created on first draw, and recreated when the node area resizes it.
+	target: Option<RenderTarget>,
+	shader: Option<Shader>,
+	/// Set when building failed, so a broken pass is not rebuilt every frame.
+	error: Option<String>,
+}
+
+struct NodePasses {
+	buffers: Vec<BufferPass>,
+	image_channels: [Option<String>; CHANNEL_COUNT],
+}
+
+impl NodePasses {
+	/// Views of the passes read by `channels` of the pass `reader`, or of the node shader when
+	/// `reader` is past the buffers, for even and odd frames.
+	fn channel_views(
+		&self,
+		reader: usize,
+		channels: &[Option<String>; CHANNEL_COUNT],
+	) -> [HashMap<String, TextureView>; 2] {
+		[0, 1].map(|parity| {
+			channels
+				.iter()
+				.enumerate()
+				.filter_map(|(channel, name)| {
+					let source = self
+						.buffers
+						.iter()
+						.position(|pass| Some(&pass.desc.name) == name.as_ref())?;
+					let target = self.buffers[source].target.as_ref()?;
+					// Passes not drawn yet this frame still hold their previous frame.
+					let drawn = source < reader;
+					let view = target.view(if drawn { parity } else { parity + 1 });
+					Some((crate::shadertoy::channel_name(channel), view.clone()))
+				})
+				.collect()
+		})
+	}
+
+	/// Sizes of the targets read by `channels`.
+	fn channel_resolution(
+		&self,
+		channels: &[Option<String>; CHANNEL_COUNT],
+	) -> [Option<(f32, f32)>; CHANNEL_COUNT] {
+		channels.clone().map(|name| {
+			let pass = self
+				.buffers
+				.iter()
+				.find(|pass| Some(&pass.desc.name) == name.as_ref())?;
+			let (width, height) = pass.target.as_ref()?.size();
+			Some((width as f32, height as f32))
+		})
+	}
+}
+
+pub type NodeGraphicsMutex = std::sync::Mutex<NodeGraphics>;
+
+/// Node graphics shared between the script runtimes and the render loop.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn node_graphics() -> &'static NodeGraphicsMutex {
+	static NODE_GRAPHICS: std::sync::OnceLock<NodeGraphicsMutex> = std::sync::OnceLock::new();
+	NODE_GRAPHICS.get_or_init(Default::default)
+}
+
+impl NodeGraphics {
+	/// Makes the device available, pipelines are (re)built on the next render.
+	pub fn attach(&mut self, device: Device, queue: Queue, format: TextureFormat) {
+		for pipeline in self.pipelines.values_mut() {
+			pipeline.shader = None;
+			pipeline.error = None;
+		}
+		self.channel_targets.clear();
+		self.transients = TexturePool::default();
+		for pass in self
+			.passes
+			.values_mut()
+			.flat_map(|passes| &mut passes.buffers)
+		{
+			pass.target = None;
+			pass.shader = None;
+			pass.error = None;
+		}
+		for (name, resource) in self.storage.iter_mut() {
+			resource.create(&device, name);
+		}
+		self.context = Some((device, queue, format));
+	}
+
+	/// Validates `source` and registers its pipeline for `node`, returning the pipeline id.
+	pub fn create_pipeline(&mut self, node: &str, source: &str) -> Result<usize, String> {
+		validate_wgsl(source).map_err(|error| error.to_string())?;
+		Ok(self.add_pipeline(NodePipeline {
+			node: node.to_string(),
+			source: source.to_string(),
+			buffers: Vec::new(),
+			textures: Vec::new(),
+			dispatch: DispatchSize::default(),
+			shader: None,
+			error: None,
+		}))
+	}
+
+	fn add_pipeline(&mut self, pipeline: NodePipeline) -> usize {
+		let pipeline_id = self.next_pipeline;
+		self.next_pipeline += 1;
+		self.pipelines.insert(pipeline_id, pipeline);
+		pipeline_id
+	}
+
+	/// Registers the shaders of the WGSL nodes of the board, logging the invalid ones.
+	///
+	/// The scripts of the other nodes run again after this, so the pipelines they created are
+	/// dropped, and so is everything about the nodes the board no longer has.
+	pub fn load_board(&mut self, board: &BoardState) {
+		fn load(graphics: &mut NodeGraphics, node: &Node, wgsl: &mut HashSet<String>) {
+			if let Some(Script::WGSL(source)) = node.script() {
+				wgsl.insert(node.name().to_string());
+				if let Err(error) = graphics.set_node_shader(node.name(), source) {
+					log::error!("WGSL node '{}' failed: {}", node.name(), error);
+				}
+			}
+			for subnode in node.subnodes() {
+				load(graphics, subnode, wgsl);
+			}
+		}
+		let mut wgsl = HashSet::new();
+		load(self, board.root_node(), &mut wgsl);
+
+		let mut nodes: HashSet<String> = self.pipelines.values().map(|p| p.node.clone()).collect();
+		nodes.extend(self.areas.keys().cloned());
+		nodes.extend(self.channels.keys().cloned());
+		nodes.extend(self.passes.keys().cloned());
+		for node in nodes {
+			if board.root_node().find_node(&node).is_none() {
+				self.remove_node(&node);
+				continue;
+			}
+			if !wgsl.contains(&node) {
+				self.node_shaders.remove(&node);
+			}
+			self.remove_pipelines(&node);
+		}
+	}
+
+	/// Drops the pipelines of `node`, with their buffers and textures, before its script
+	/// runs again.
+	///
+	/// The shader of a WGSL node is kept, it is replaced once its new source is valid.
+	pub fn remove_pipelines(&mut self, node: &str) {
+		let shader = self.node_shaders.get(node).copied();
+		self.pipelines
+			.retain(|&id, pipeline| pipeline.node != node || Some(id) == shader);
+	}
+
+	/// Drops everything about a node removed from the board: its pipelines, shader, area,
+	/// channels and buffer passes.
+	pub fn remove_node(&mut self, node: &str) {
+		self.node_shaders.remove(node);
+		self.remove_pipelines(node);
+		self.areas.remove(node);
+		self.channels.remove(node);
+		self.channel_targets.remove(node);
+		self.passes.remove(node);
+	}
+
+	/// Compiles the shader of a WGSL node, returning its pipeline id.
+	///
+	/// The shader is only swapped in once it is valid, until then the node keeps its previous one.
+	pub fn set_node_shader(&mut self, node: &str, source: &str) -> Result<usize, Box<ScriptError>> {
+		let dispatch = self
+			.node_shaders
+			.get(node)
+			.map(|pipeline_id| self.pipelines[pipeline_id].dispatch.clone());
+		let mut pipeline = NodePipeline {
+			node: node.to_string(),
+			source: source.to_string(),
+			buffers: Vec::new(),
+			textures: Vec::new(),
+			dispatch: dispatch.unwrap_or_default(),
+			shader: None,
+			error: None,
+		};
+		match &self.context {
+			Some((device, queue, format)) => {
+				let resources = self.node_resources(node);
+				pipeline.shader = Some(pipeline.build(device, queue, *format, &resources)?);
+			}
+			None => {
+				validate_wgsl(source)?;
+			}
+		}
+
+		match self.node_shaders.get(node) {
+			Some(&pipeline_id) => {
+				self.pipelines.insert(pipeline_id, pipeline);
+				Ok(pipeline_id)
+			}
+			None => {
+				let pipeline_id = self.add_pipeline(pipeline);
+				self.node_shaders.insert(node.to_string(), pipeline_id);
+				Ok(pipeline_id)
+			}
+		}
+	}
+
+	/// Source of the shader a WGSL node currently draws.
+	pub fn node_shader_source(&self, node: &str) -> Option<&str> {
+		let pipeline_id = self.node_shaders.get(node)?;
+		Some(&self.pipelines[pipeline_id].source)
+	}
+
+	/// Declares a buffer at `@group(1) @binding(binding)` of a pipeline, returning the buffer id.
+	pub fn create_buffer(
+		&mut self,
+		pipeline_id: usize,
+		binding: u32,
+		kind: ScriptBufferKind,
+		data: &[u8],
+	) -> Result<usize, String> {
+		let device = self.context.as_ref().map(|(device, ..)| device.clone());
+		let pipeline = self.pipeline_mut(pipeline_id)?;
+		if pipeline.binding_used(binding) {
+			return Err(format!("Binding {} is already used", binding));
+		}
+		if data.is_empty() {
+			return Err("Cannot create an empty buffer".to_string());
+		}
+
+		let mut buffer = ScriptBuffer {
+			binding,
+			kind,
+			size: data.len() as u64,
+			pending: Some(data.to_vec()),
+			buffer: None,
+		};
+		if let Some(device) = device {
+			buffer.create(&device);
+		}
+		pipeline.buffers.push(buffer);
+		pipeline.shader = None;
+		pipeline.error = None;
+		Ok(pipeline.buffers.len() - 1)
+	}
+
+	pub fn write_buffer(
+		&mut self,
+		pipeline_id: usize,
+		buffer_id: usize,
+		offset: u64,
+		data: &[u8],
+	) -> Result<(), String> {
+		let queue = self.context.as_ref().map(|(_, queue, _)| queue.clone());
+		let buffer = self
+			.pipeline_mut(pipeline_id)?
+			.buffers
+			.get_mut(buffer_id)
+			.ok_or_else(|| format!("Unknown buffer id {}", buffer_id))?;
+		if !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+			|| !(data.len() as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+		{
+			return Err(format!(
+				"Buffer writes must be {} bytes aligned, got {} bytes at offset {}",
+				wgpu::COPY_BUFFER_ALIGNMENT,
+				data.len(),
+				offset
+			));
+		}
+		if offset + data.len() as u64 > buffer.size {
+			return Err(format!(
+				"Writing {} bytes at offset {} overflows the {} bytes buffer",
+				data.len(),
+				offset,
+				buffer.size
+			));
+		}
+
+		match (&buffer.buffer, queue) {
+			(Some(gpu_buffer), Some(queue)) => queue.write_buffer(gpu_buffer, offset, data),
+			_ => {
+				let pending = buffer.pending.as_mut().unwrap();
+				pending[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+			}
+		}
+		Ok(())
+	}
+
+	/// Declares a 2D texture at `@group(1) @binding(binding)` of a pipeline, returning the texture id.
+	pub fn create_texture(
+		&mut self,
+		pipeline_id: usize,
+		binding: u32,
+		size: (u32, u32),
+		format: ScriptTextureFormat,
+	) -> Result<usize, String> {
+		let context = self.context.clone();
+		let pipeline = self.pipeline_mut(pipeline_id)?;
+		if pipeline.binding_used(binding) {
+			return Err(format!("Binding {} is already used", binding));
+		}
+		if size.0 == 0 || size.1 == 0 {
+			return Err("Cannot create an empty texture".to_string());
+		}
+
+		let mut texture = ScriptTexture {
+			binding,
+			size: Extent3d {
+				width: size.0,
+				height: size.1,
+				depth_or_array_layers: 1,
+			},
+			format,
+			pending: None,
+			texture: None,
+		};
+		texture.pending = Some(vec![0; texture.byte_size()]);
+		if let Some((device, queue, _)) = context {
+			texture.create(&device, &queue);
+		}
+		pipeline.textures.push(texture);
+		pipeline.shader = None;
+		pipeline.error = None;
+		Ok(pipeline.textures.len() - 1)
+	}
+
+	/// Size and format of a texture created with [`Self::create_texture`].
+	pub fn texture_info(
+		&mut self,
+		pipeline_id: usize,
+		texture_id: usize,
+	) -> Result<((u32, u32), ScriptTextureFormat), String> {
+		self.pipeline_mut(pipeline_id)?
+			.textures
+			.get(texture_id)
+			.map(|t| ((t.size.width, t.size.height), t.format))
+			.ok_or_else(|| format!("Unknown texture id {}", texture_id))
+	}
+
+	/// Replaces the whole content of a texture with tightly packed rows of texels.
+	pub fn write_texture(
+		&mut self,
+		pipeline_id: usize,
+		texture_id: usize,
+		data: &[u8],
+	) -> Result<(), String> {
+		let queue = self.context.as_ref().map(|(_, queue, _)| queue.clone());
+		let texture = self
+			.pipeline_mut(pipeline_id)?
+			.textures
+			.get_mut(texture_id)
+			.ok_or_else(|| format!("Unknown texture id {}", texture_id))?;
+		if data.len() != texture.byte_size() {
+			return Err(format!(
+				"Expected {} bytes for a {}x{} texture, got {}",
+				texture.byte_size(),
+				texture.size.width,
+				texture.size.height,
+				data.len()
+			));
+		}
+
+		match (&texture.texture, queue) {
+			(Some(gpu_texture), Some(queue)) => queue.write_texture(
+				gpu_texture.as_image_copy(),
+				data,
+				wgpu::TexelCopyBufferLayout {
+					offset: 0,
+					bytes_per_row: Some(texture.size.width * texture.format.texel_size() as u32),
+					rows_per_image: None,
+				},
+				texture.size,
+			),
+			_ => texture.pending = Some(data.to_vec()),
+		}
+		Ok(())
+	}
+
+	pub fn set_area(&mut self, node: &str, area: [f32; 4]) {
+		self.areas.insert(node.to_string(), area);
+	}
+
+	/// Area `[x, y, width, height]` set by `node`, in physical pixels of the board.
+	pub fn area(&self, node: &str) -> Option<[f32; 4]> {
+		self.areas.get(node).copied()
+	}
+
+	pub fn set_camera(&mut self, camera: BoardCamera) -> Result<(), String> {
+		if !(camera.zoom.is_finite() && camera.zoom > 0.0) {
+			return Err(format!("Cannot zoom the board by {}", camera.zoom));
+		}
+		self.camera = camera;
+		Ok(())
+	}
+
+	pub fn camera(&self) -> BoardCamera {
+		self.camera
+	}
+
+	/// Position of `pointer` in the area of `node`, in pixels of the board from its top-left
+	/// corner. `None` when the pointer is outside of it.
+	pub fn node_pointer(&self, node: &str, pointer: &PointerEvent) -> Option<(f32, f32)> {
+		let area = self.area(node);
+		let (x, y) = match area {
+			Some(_) => self.camera.to_board((pointer.x, pointer.y)),
+			None => (pointer.x, pointer.y),
+		};
+		PointerEvent {
+			x,
+			y,
+			..pointer.clone()
+		}
+		.local_position(area)
+	}
+
+	/// Area of a target whose area is `full` that `node` is drawn in.
+	fn target_area(&self, node: &str, full: [f32; 4]) -> [f32; 4] {
+		self.areas
+			.get(node)
+			.map_or(full, |area| self.camera.to_target(*area))
+	}
+
+	/// Size of the textures `node` is drawn into, the size of its target area within the
+	/// limits of the device.
+	fn target_size(&self, node: &str, full: [f32; 4]) -> (u32, u32) {
+		let [_, _, width, height] = self.target_area(node, full);
+		let limit = self.context.as_ref().map_or(u32::MAX, |(device, _, _)| {
+			device.limits().max_texture_dimension_2d
+		});
+		(
+			(width.round() as u32).clamp(1, limit),
+			(height.round() as u32).clamp(1, limit),
+		)
+	}
+
+	/// Sets the workgroups a compute pipeline dispatches every frame.
+	pub fn set_dispatch(&mut self, pipeline_id: usize, size: DispatchSize) -> Result<(), String> {
+		if let DispatchSize::Workgroups(count) | DispatchSize::Invocations(count) = &size {
+			if count.contains(&0) {
+				return Err(format!("Cannot dispatch {:?} invocations", count));
+			}
+		}
+		let pipeline = self.pipeline_mut(pipeline_id)?;
+		pipeline.dispatch = size;
+		pipeline.shader = None;
+		pipeline.error = None;
+		Ok(())
+	}
+
+	/// Sets the workgroups the compute shader of a WGSL node dispatches every frame.
+	pub fn set_node_dispatch(&mut self, node: &str, size: DispatchSize) -> Result<(), String> {
+		let pipeline_id = *self
+			.node_shaders
+			.get(node)
+			.ok_or_else(|| format!("Node '{}' has no shader", node))?;
+		self.set_dispatch(pipeline_id, size)
+	}
+
+	/// Creates the storage buffer bound to the variables named `name`, replacing the previous
+	/// one, with `data` as content.
+	pub fn create_storage_buffer(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+		if data.is_empty() || !(data.len() as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+			return Err(format!(
+				"Storage buffers must have a non-zero size multiple of {} bytes, got {}",
+				wgpu::COPY_BUFFER_ALIGNMENT,
+				data.len()
+			));
+		}
+		self.set_storage(
+			name,
+			StorageResource::Buffer {
+				pending: Some(data.to_vec()),
+				size: data.len() as u64,
+				buffer: None,
+			},
+		);
+		Ok(())
+	}
+
+	pub fn write_storage_buffer(
+		&mut self,
+		name: &str,
+		offset: u64,
+		data: &[u8],
+	) -> Result<(), String> {
+		let queue = self.context.as_ref().map(|(_, queue, _)| queue.clone());
+		let Some(StorageResource::Buffer {
+			pending,
+			size,
+			buffer,
+		}) = self.storage.get_mut(name)
+		else {
+			return Err(format!("Unknown storage buffer '{}'", name));
+		};
+		if !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+			|| !(data.len() as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+			|| offset + data.len() as u64 > *size
+		{
+			return Err(format!(
+				"Cannot write {} bytes at offset {} of the {} bytes buffer '{}'",
+				data.len(),
+				offset,
+				size,
+				name
+			));
+		}
+		match (buffer, queue) {
+			(Some(buffer), Some(queue)) => queue.write_buffer(buffer, offset, data),
+			_ => {
+				let pending = pending.get_or_insert_with(|| vec![0; *size as usize]);
+				pending[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+			}
+		}
+		Ok(())
+	}
+
+	/// Creates the 2D storage texture bound to the variables named `name`, replacing the
+	/// previous one. Compute shaders write it with `textureStore`, the others sample it.
+	pub fn create_storage_texture(
+		&mut self,
+		name: &str,
+		size: (u32, u32),
+		format: StorageTextureFormat,
+	) -> Result<(), String> {
+		if size.0 == 0 || size.1 == 0 {
+			return Err("Cannot create an empty texture".to_string());
+		}
+		self.set_storage(
+			name,
+			StorageResource::Texture {
+				size,
+				format,
+				view: None,
+			},
+		);
+		Ok(())
+	}
+
+	/// Registers a shared resource and rebuilds every shader, which may bind it.
+	fn set_storage(&mut self, name: &str, mut resource: StorageResource) {
+		if let Some((device, ..)) = &self.context {
+			resource.create(device, name);
+		}
+		self.storage.insert(name.to_string(), resource);
+		for pipeline in self.pipelines.values_mut() {
+			pipeline.shader = None;
+			pipeline.error = None;
+		}
+		for pass in self
+			.passes
+			.values_mut()
+			.flat_map(|passes| &mut passes.buffers)
+		{
+			pass.shader = None;
+			pass.error = None;
+		}
+	}
+
+	/// Storage resources, by variable name.
+	fn storage_resources(&self) -> NamedResources {
+		let mut resources = NamedResources::default();
+		let mut textures = HashMap::new();
+		for (name, resource) in &self.storage {
+			match resource {
+				StorageResource::Buffer {
+					buffer: Some(buffer),
+					..
+				} => {
+					resources.buffers.insert(name.clone(), buffer.clone());
+				}
+				StorageResource::Texture {
+					view: Some(view), ..
+				} => {
+					textures.insert(name.clone(), view.clone());
+				}
+				_ => {}
+			}
+		}
+		resources.textures.push(textures);
+		resources
+	}
+
+	/// Storage read and written by `shader`.
+	fn storage_access(&self, shader: &Shader) -> (Vec<String>, Vec<String>) {
+		let (writes, reads): (Vec<&ShaderBinding>, Vec<&ShaderBinding>) = shader
+			.bindings()
+			.iter()
+			.filter(|b| self.storage.contains_key(&b.name))
+			.partition(|b| b.writable());
+		let names =
+			|bindings: Vec<&ShaderBinding>| bindings.iter().map(|b| b.name.clone()).collect();
+		(names(reads), names(writes))
+	}
+
+	fn pipeline_mut(&mut self, pipeline_id: usize) -> Result<&mut NodePipeline, String> {
+		self.pipelines
+			.get_mut(&pipeline_id)
+			.ok_or_else(|| format!("Unknown pipeline id {}", pipeline_id))
+	}
+
+	/// Feeds the drawing of `source` to the `iChannel{channel}` texture of the shaders of
+	/// `node`, `None` unbinds the channel.
+	pub fn set_channel(
+		&mut self,
+		node: &str,
+		channel: usize,
+		source: Option<&str>,
+	) -> Result<(), String> {
+		if channel >= CHANNEL_COUNT {
+			return Err(format!(
+				"Unknown channel {}, shaders have {}",
+				channel, CHANNEL_COUNT
+			));
+		}
+		if let Some(source) = source {
+			if self.reads_from(source, node) {
+				return Err(format!(
+					"Node '{}' cannot read from '{}', which reads from it",
+					node, source
+				));
+			}
+		}
+
+		let channels = self.channels.entry(node.to_string()).or_default();
+		channels[channel] = source.map(str::to_string);
+		if channels.iter().all(Option::is_none) {
+			self.channels.remove(node);
+		}
+		self.invalidate_node(node);
+		Ok(())
+	}
+
+	/// Nodes drawn into the channels of `node`.
+	pub fn channels(&self, node: &str) -> [Option<String>; CHANNEL_COUNT] {
+		self.channels.get(node).cloned().unwrap_or_default()
+	}
+
+	/// Updates the `iMouse` input of the shaders.
+	pub fn pointer(&mut self, event: &crate::board::PointerEvent) {
+		self.mouse.pointer(event);
+	}
+
+	/// Whether drawing `node` needs the drawing of `source`, directly or through channels.
+	fn reads_from(&self, node: &str, source: &str) -> bool {
+		node == source
+			|| self
+				.channels
+				.get(node)
+				.into_iter()
+				.flatten()
+				.flatten()
+				.any(|read| self.reads_from(read, source))
+	}
+
+	/// Rebuilds the pipelines of `node` on the next render.
+	fn invalidate_node(&mut self, node: &str) {
+		for pipeline in self.pipelines.values_mut().filter(|p| p.node == node) {
+			pipeline.shader = None;
+			pipeline.error = None;
+		}
+	}
+
+	/// Nodes read by channels, each one after the nodes it reads from.
+	fn channel_order(&self) -> Vec<String> {
+		fn visit(graphics: &NodeGraphics, node: &str, order: &mut Vec<String>) {
+			if order.iter().any(|n| n == node) {
+				return;
+			}
+			for source in graphics.channels.get(node).into_iter().flatten().flatten() {
+				visit(graphics, source, order);
+			}
+			order.push(node.to_string());
+		}
+
+		let mut order = Vec::new();
+		for source in self.channels.values().flatten().flatten() {
+			visit(self, source, &mut order);
+		}
+		order
+	}
+
+	/// Rebuilds the pipelines reading channel textures backed by other textures than on the
+	/// last frame, returning whether there were some.
+	fn update_channel_targets(
+		&mut self,
+		views: &HashMap<String, TextureView>,
+		full: [f32; 4],
+	) -> bool {
+		let mut changed = false;
+		for node in self.channel_order() {
+			let Some(view) = views.get(&channel_resource(&node)) else {
+				continue;
+			};
+			if self
+				.channel_targets
+				.get(&node)
+				.is_some_and(|target| target.view == *view)
+			{
+				continue;
+			}
+			let size = self.target_size(&node, full);
+			self.channel_targets.insert(
+				node.clone(),
+				ChannelTarget {
+					view: view.clone(),
+					size,
+				},
+			);
+			let readers: Vec<String> = self
+				.channels
+				.iter()
+				.filter(|(_, sources)| sources.iter().flatten().any(|s| *s == node))
+				.map(|(reader, _)| reader.clone())
+				.collect();
+			for reader in readers {
+				self.invalidate_node(&reader);
+			}
+			changed = true;
+		}
+		self.channel_targets
+			.retain(|node, _| views.contains_key(&channel_resource(node)));
+		changed
+	}
+
+	/// Channel textures of `node`, by the name of their shader variable.
+	fn channel_views(&self, node: &str) -> HashMap<String, TextureView> {
+		self.channels(node)
+			.iter()
+			.enumerate()
+			.filter_map(|(channel, source)| {
+				let target = self.channel_targets.get(source.as_ref()?)?;
+				Some((crate::shadertoy::channel_name(channel), target.view.clone()))
+			})
+			.collect()
+	}
+
+	/// Sets the buffer passes drawn before the shader of `node`, no buffers removes them.
+	///
+	/// The passes are validated first, and built on the next render.
+	pub fn set_passes(&mut self, node: &str, passes: ShaderPasses) -> Result<(), String> {
+		let names: Vec<&str> = passes.buffers.iter().map(|b| b.name.as_str()).collect();
+		let check_channels = |pass: &str, channels: &[Option<String>; CHANNEL_COUNT]| match channels
+			.iter()
+			.flatten()
+			.find(|c| !names.contains(&c.as_str()))
+		{
+			Some(channel) => Err(format!("Pass '{}' reads unknown pass '{}'", pass, channel)),
+			None => Ok(()),
+		};
+		for (i, buffer) in passes.buffers.iter().enumerate() {
+			if buffer.name.is_empty() || names[..i].contains(&buffer.name.as_str()) {
+				return Err(format!("Pass names must be unique, got '{}'", buffer.name));
+			}
+			if buffer
+				.size
+				.is_some_and(|(width, height)| width == 0 || height == 0)
+			{
+				return Err(format!("Pass '{}' has an empty target", buffer.name));
+			}
+			check_channels(&buffer.name, &buffer.channels)?;
+			let module = validate_wgsl(&buffer.source)
+				.map_err(|error| format!("Pass '{}': {}", buffer.name, error))?;
+			if is_compute_shader(&module) {
+				return Err(format!("Pass '{}' must be a fragment shader", buffer.name));
+			}
+		}
+		check_channels("Image", &passes.image_channels)?;
+
+		if passes.buffers.is_empty() {
+			self.passes.remove(node);
+		} else {
+			self.passes.insert(
+				node.to_string(),
+				NodePasses {
+					buffers: passes
+						.buffers
+						.into_iter()
+						.map(|desc| BufferPass {
+							desc,
+							target: None,
+							shader: None,
+							error: None,
+						})
+						.collect(),
+					image_channels: passes.image_channels,
+				},
+			);
+		}
+		self.invalidate_node(node);
+		Ok(())
+	}
+
+	/// Buffer passes of `node`.
+	pub fn passes(&self, node: &str) -> ShaderPasses {
+		self.passes
+			.get(node)
+			.map(|passes| ShaderPasses {
+				buffers: passes
+					.buffers
+					.iter()
+					.map(|pass| pass.desc.clone())
+					.collect(),
+				image_channels: passes.image_channels.clone(),
+			})
+			.unwrap_or_default()
+	}
+
+	/// (Re)creates the targets of the buffer passes with their size, and rebuilds the passes
+	/// and shader of the nodes whose targets were recreated.
+	fn update_pass_targets(&mut self, device: &Device, full: [f32; 4]) {
+		let mut resized = Vec::new();
+		let area_sizes: HashMap<String, (u32, u32)> = self
+			.passes
+			.keys()
+			.map(|node| (node.clone(), self.target_size(node, full)))
+			.collect();
+		for (node, passes) in self.passes.iter_mut() {
+			let area_size = area_sizes[node];
+			let mut recreated = false;
+			for pass in passes.buffers.iter_mut() {
+				let size = pass.desc.size.unwrap_or(area_size);
+				let format = pass.desc.format.texture_format();
+				if pass
+					.target
+					.as_ref()
+					.is_some_and(|target| target.size() == size && target.format() == format)
+				{
+					continue;
+				}
+				let label = format!("Pass '{}' of '{}'", pass.desc.name, node);
+				pass.target = Some(RenderTarget::new(device, &label, size, format));
+				recreated = true;
+			}
+			if recreated {
+				for pass in passes.buffers.iter_mut() {
+					pass.shader = None;
+					pass.error = None;
+				}
+				resized.push(node.clone());
+			}
+		}
+		for node in resized {
+			self.invalidate_node(&node);
+		}
+	}
+
+	/// Builds the shaders of the buffer passes missing one.
+	fn build_passes(&mut self, device: &Device, queue: &Queue) {
+		let storage = self.storage_resources();
+		for (node, passes) in self.passes.iter_mut() {
+			for i in 0..passes.buffers.len() {
+				let pass = &passes.buffers[i];
+				if pass.shader.is_some() || pass.error.is_some() {
+					continue;
+				}
+				let Some(target) = &pass.target else {
+					continue;
+				};
+				let resources = NamedResources {
+					textures: passes
+						.channel_views(i, &pass.desc.channels)
+						.into_iter()
+						.map(|views| {
+							storage.textures[0]
+								.clone()
+								.into_iter()
+								.chain(views)
+								.collect()
+						})
+						.collect(),
+					buffers: storage.buffers.clone(),
+				};
+				let result = create_shader_pipeline_with_groups(
+					device,
+					target.format(),
+					&pass.desc.source,
+					Vec::new(),
+					&resources,
+				)
+				.and_then(|shader| {
+					shader
+						.update_buffer(
+							queue,
+							"Canvas Vertex Buffer",
+							bytemuck::cast_slice(QUAD_VERTICES),
+							0,
+						)
+						.map_err(|error| ScriptError::new("PipelineError", error))?;
+					Ok(shader)
+				});
+				let pass = &mut passes.buffers[i];
+				match result {
+					Ok(shader) => pass.shader = Some(shader),
+					Err(error) => {
+						log::error!(
+							"Cannot build pass '{}' of node '{}': {}",
+							pass.desc.name,
+							node,
+							error
+						);
+						pass.error = Some(error.to_string());
+					}
+				}
+			}
+		}
+	}
+
+	/// Resources of the shaders of `node`: the storage, its channels and, for even and odd
+	/// frames, the buffer passes it reads.
+	fn node_resources(&self, node: &str) -> NamedResources {
+		let mut resources = self.storage_resources();
+		resources.textures[0].extend(self.channel_views(node));
+		if let Some(passes) = self.passes.get(node) {
+			let textures = resources.textures.pop().unwrap();
+			resources.textures = passes
+				.channel_views(passes.buffers.len(), &passes.image_channels)
+				.into_iter()
+				.map(|views| textures.clone().into_iter().chain(views).collect())
+				.collect();
+		}
+		resources
+	}
+
+	/// Builds the pipelines missing a shader.
+	fn build_pipelines(&mut self, device: &Device, queue: &Queue, format: TextureFormat) {
+		let resources: HashMap<String, NamedResources> = self
+			.pipelines
+			.values()
+			.filter(|p| p.shader.is_none() && p.error.is_none())
+			.map(|p| (p.node.clone(), self.node_resources(&p.node)))
+			.collect();
+		for pipeline in self.pipelines.values_mut() {
+			if pipeline.shader.is_none() && pipeline.error.is_none() {
+				match pipeline.build(device, queue, format, &resources[&pipeline.node]) {
+					Ok(shader) => pipeline.shader = Some(shader),
+					Err(error) => {
+						log::error!(
+							"Cannot build pipeline of node '{}': {}",
+							pipeline.node,
+							error
+						);
+						pipeline.error = Some(error.to_string());
+					}
+				}
+			}
+		}
+	}
+
+	/// Resources read by the drawing of `node` with `shader`: its channel textures, the
+	/// buffer passes of its image and the storage.
+	fn draw_reads(&self, node: &str, shader: &Shader) -> Vec<String> {
+		let channels = self.channels(node);
+		let mut reads: Vec<String> = channels
+			.iter()
+			.flatten()
+			.map(|source| channel_resource(source))
+			.collect();
+		if let Some(passes) = self.passes.get(node) {
+			reads.extend(
+				passes
+					.image_channels
+					.iter()
+					.flatten()
+					.map(|pass| pass_resource(node, pass)),
+			);
+		}
+		reads.extend(self.storage_access(shader).0);
+		reads
+	}
+
+	/// Passes of the frame, for a target whose area is `full`: compute shaders, buffer passes,
+	/// channel textures and the drawing of the nodes into the output.
+	fn frame_graph(&self, format: TextureFormat, full: [f32; 4]) -> RenderGraph<NodePass> {
+		let mut graph = RenderGraph::new();
+		let shaders = || {
+			self.pipelines
+				.iter()
+				.filter_map(|(&id, p)| Some((id, &p.node, p.shader.as_ref()?)))
+		};
+		for (id, node, shader) in shaders().filter(|(_, _, s)| s.workgroup_size().is_some()) {
+			let (reads, writes) = self.storage_access(shader);
+			graph.add_pass(
+				format!("compute {}", node),
+				reads,
+				writes,
+				NodePass::Compute(id),
+			);
+		}
+		for (node, passes) in &self.passes {
+			for (i, pass) in passes.buffers.iter().enumerate() {
+				let (Some(shader), Some(_)) = (&pass.shader, &pass.target) else {
+					continue;
+				};
+				// Passes drawn later this frame are read from the previous one.
+				let drawn = &passes.buffers[..i];
+				let (mut reads, mut writes) = self.storage_access(shader);
+				reads.extend(
+					pass.desc
+						.channels
+						.iter()
+						.flatten()
+						.filter(|name| drawn.iter().any(|p| p.desc.name == **name))
+						.map(|name| pass_resource(node, name)),
+				);
+				writes.push(pass_resource(node, &pass.desc.name));
+				graph.add_pass(
+					pass_resource(node, &pass.desc.name),
+					reads,
+					writes,
+					NodePass::Buffer(node.clone(), i),
+				);
+			}
+		}
+		let renders = || shaders().filter(|(_, _, s)| s.workgroup_size().is_none());
+		for node in self.channel_order() {
+			let texture = channel_resource(&node);
+			graph.add_texture(
+				texture.clone(),
+				TextureDesc {
+					size: self.target_size(&node, full),
+					format,
+				},
+			);
+			let reads = renders()
+				.filter(|(_, n, _)| **n == node)
+				.flat_map(|(_, _, shader)| self.draw_reads(&node, shader))
+				.collect();
+			graph.add_pass(
+				texture.clone(),
+				reads,
+				vec![texture],
+				NodePass::Channel(node),
+			);
+		}
+		let size = (full[2] as u32, full[3] as u32);
+		for (id, node, shader) in renders() {
+			let mut writes = self.storage_access(shader).1;
+			// Nodes outside of the target are not drawn, nor what only they read.
+			let area = self.target_area(node, full);
+			if writes.is_empty() && crate::render_graph::clip_viewport(area, size).is_none() {
+				continue;
+			}
+			writes.push(OUTPUT.to_string());
+			graph.add_pass(
+				format!("draw {}", node),
+				self.draw_reads(node, shader),
+				writes,
+				NodePass::Draw(id),
+			);
+		}
+		graph
+	}
+
+	/// Draws every node pipeline into its node area of `view`, on top of its content.
+	///
+	/// The passes run in the order of the frame graph: compute shaders, buffer passes drawn
+	/// into their targets, and nodes read by channels drawn into their channel texture, each
+	/// one before the passes reading what it writes.
+	pub fn render(&mut self, view: &TextureView, target_size: (u32, u32)) {
+		let Some((device, queue, format)) = self.context.clone() else {
+			return;
+		};
+		let (width, height) = (target_size.0 as f32, target_size.1 as f32);
+		let full = [0.0, 0.0, width, height];
+
+		let now = Instant::now();
+		let time_delta = self
+			.last_frame
+			.map_or(0.0, |last| (now - last).as_secs_f32());
+		self.last_frame = Some(now);
+
+		// The targets written this frame, read by the next one.
+		let parity = (self.frame & 1) as usize;
+		for (name, resource) in self.storage.iter_mut() {
+			if !resource.created() {
+				resource.create(&device, name);
+			}
+		}
+		self.update_pass_targets(&device, full);
+		self.build_passes(&device, &queue);
+		self.build_pipelines(&device, &queue, format);
+		let graph = self.frame_graph(format, full);
+		let plan = match graph.compile() {
+			Ok(plan) => plan,
+			Err(error) => {
+				log::error!("Cannot order the node passes: {}", error);
+				return;
+			}
+		};
+		let transients = self.transients.views(&device, &plan);
+		if self.update_channel_targets(&transients, full) {
+			self.build_pipelines(&device, &queue, format);
+		}
+
+		let date = crate::shadertoy::date_now();
+		for (node, passes) in &self.passes {
+			let area = self.target_area(node, full);
+			for pass in &passes.buffers {
+				let (Some(shader), Some(target)) = (&pass.shader, &pass.target) else {
+					continue;
+				};
+				let (width, height) = target.size();
+				let (width, height) = (width as f32, height as f32);
+				// The mouse in pixels of the target.
+				let (sx, sy) = (width / area[2].max(1.0), height / area[3].max(1.0));
+				let [mx, my, cx, cy] = self.mouse.input(area);
+				ShaderToyInputs {
+					resolution: (width, height),
+					time: shader.ellapsed(),
+					time_delta,
+					frame: self.frame,
+					mouse: [mx * sx, my * sy, cx * sx, cy * sy],
+					date,
+					channel_resolution: passes
+						.channel_resolution(&pass.desc.channels)
+						.map(Option::unwrap_or_default),
+				}
+				.write(shader, &queue);
+				shader.set_time(&queue, shader.ellapsed());
+				shader.set_surface_size(&queue, (width, height));
+			}
+		}
+		for pipeline in self.pipelines.values() {
+			let Some(shader) = &pipeline.shader else {
+				continue;
+			};
+			let area = self.target_area(&pipeline.node, full);
+			let mut channel_resolution = [(0.0, 0.0); CHANNEL_COUNT];
+			for (channel, source) in self.channels(&pipeline.node).iter().enumerate() {
+				if let Some(target) = source.as_ref().and_then(|s| self.channel_targets.get(s)) {
+					channel_resolution[channel] = (target.size.0 as f32, target.size.1 as f32);
+				}
+			}
+			if let Some(passes) = self.passes.get(&pipeline.node) {
+				let sizes = passes.channel_resolution(&passes.image_channels);
+				for (channel, size) in sizes.into_iter().enumerate() {
+					if let Some(size) = size {
+						channel_resolution[channel] = size;
+					}
+				}
+			}
+			ShaderToyInputs {
+				resolution: (area[2], area[3]),
+				time: shader.ellapsed(),
+				time_delta,
+				frame: self.frame,
+				mouse: self.mouse.input(area),
+				date,
+				channel_resolution,
+			}
+			.write(shader, &queue);
+			shader.set_time(&queue, shader.ellapsed());
+			shader.set_surface_size(&queue, (area[2], area[3]));
+			if shader.workgroup_size().is_none() {
+				let _ = shader.update_buffer(
+					&queue,
+					"Canvas Vertex Buffer",
+					bytemuck::cast_slice(&area_quad(area, target_size)),
+					(AREA_QUAD.start as usize * std::mem::size_of::<[f32; 3]>()) as u64,
+				);
+			}
+		}
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Node Pipelines"),
+		});
+		for &index in &plan.order {
+			match &graph.pass(index).payload {
+				NodePass::Compute(id) => {
+					let pipeline = &self.pipelines[id];
+					let Some(shader) = &pipeline.shader else {
+						continue;
+					};
+					if let Ok(workgroups) = pipeline.dispatch.workgroups(shader) {
+						let mut compute_pass =
+							encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+								label: Some("Node Compute"),
+								timestamp_writes: None,
+							});
+						shader.dispatch(&mut compute_pass, workgroups, parity);
+					}
+				}
+				NodePass::Buffer(node, i) => {
+					let pass = &self.passes[node].buffers[*i];
+					let (Some(shader), Some(target)) = (&pass.shader, &pass.target) else {
+						continue;
+					};
+					let mut render_pass = begin_color_pass(
+						&mut encoder,
+						"Node Buffer Pass",
+						target.view(parity),
+						wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+					);
+					shader.draw(&mut render_pass, parity);
+				}
+				NodePass::Channel(node) => {
+					let Some(view) = transients.get(&channel_resource(node)) else {
+						continue;
+					};
+					let mut render_pass = begin_color_pass(
+						&mut encoder,
+						"Node Channel",
+						view,
+						wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+					);
+					for pipeline in self.pipelines.values().filter(|p| p.node == *node) {
+						if let Some(shader) = &pipeline.shader {
+							shader.draw(&mut render_pass, parity);
+						}
+					}
+				}
+				NodePass::Draw(id) => {
+					let pipeline = &self.pipelines[id];
+					let Some(shader) = &pipeline.shader else {
+						continue;
+					};
+					let area = self.target_area(&pipeline.node, full);
+					let Some([x, y, w, h]) = crate::render_graph::clip_viewport(area, target_size)
+					else {
+						continue;
+					};
+					// The quad of the area is cut to the target, instead of squeezed into it.
+					let (x0, y0) = (x.round() as u32, y.round() as u32);
+					let (x1, y1) = ((x + w).round() as u32, (y + h).round() as u32);
+					if x1 <= x0 || y1 <= y0 {
+						continue;
+					}
+					let mut render_pass =
+						begin_color_pass(&mut encoder, "Node Pipelines", view, wgpu::LoadOp::Load);
+					render_pass.set_scissor_rect(x0, y0, x1 - x0, y1 - y0);
+					shader.draw_vertices(&mut render_pass, parity, AREA_QUAD);
+				}
+			}
+		}
+		queue.submit(std::iter::once(encoder.finish()));
+
+		self.frame += 1;
+		self.mouse.clicked = false;
+	}
+}
+
+/// Begins a render pass drawing into `view`, loaded with `load`.
+fn begin_color_pass<'a>(
+	encoder: &'a mut CommandEncoder,
+	label: &str,
+	view: &TextureView,
+	load: LoadOp<Color>,
+) -> RenderPass<'a> {
+	encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+		label: Some(label),
+		color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+			view,
+			resolve_target: None,
+			ops: wgpu::Operations {
+				load,
+				store: wgpu::StoreOp::Store,
+			},
+		})],
+		..Default::default()
+	})
+}
+
+// Threaded Render
+// tauri::async_runtime::spawn(async move {
+//     let frame_duration = Duration::from_millis(16); // ~60 FPS
 //     loop {
 //         let start = Instant::now();
 
@@ -425,3 +3273,528 @@ impl GraphicsHandle {
 //         }
 //     }
 // });
+
+#[test]
+fn test_wgsl_error_spans() {
+	let source =
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0) +;\n}\n";
+	let error = validate_wgsl(source).unwrap_err();
+	assert_eq!(error.kind, "ParseError");
+	assert_eq!(error.line, Some(3));
+	assert_eq!(error.text.as_deref(), Some("    return vec4<f32>(1.0) +;"));
+	assert!(!error.labels.is_empty());
+
+	let source = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return 1.0;\n}\n";
+	let error = validate_wgsl(source).unwrap_err();
+	assert_eq!(error.kind, "ValidationError");
+	assert!(error.message.contains("fs_main"), "{}", error.message);
+	assert!(
+		error.labels.iter().any(|label| label.line == 3),
+		"{:?}",
+		error.labels
+	);
+}
+
+#[test]
+fn test_node_shader_swap() {
+	let valid =
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0);\n}\n";
+	let mut graphics = NodeGraphics::default();
+	let pipeline_id = graphics.set_node_shader("wgsl_node", valid).unwrap();
+
+	// An invalid shader is reported and the node keeps drawing the valid one.
+	let error = graphics
+		.set_node_shader("wgsl_node", "@fragment fn fs_main() {")
+		.unwrap_err();
+	assert_eq!(error.line, Some(1));
+	assert_eq!(graphics.node_shader_source("wgsl_node"), Some(valid));
+
+	let updated = valid.replace("1.0", "0.5");
+	assert_eq!(
+		graphics.set_node_shader("wgsl_node", &updated),
+		Ok(pipeline_id)
+	);
+	assert_eq!(
+		graphics.node_shader_source("wgsl_node"),
+		Some(updated.as_str())
+	);
+}
+
+#[test]
+fn test_node_pipelines_reload() {
+	let source =
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0);\n}\n";
+	let mut graphics = NodeGraphics::default();
+	let first = graphics.create_pipeline("py_node", source).unwrap();
+	graphics
+		.create_buffer(first, 0, ScriptBufferKind::Uniform, &[0; 16])
+		.unwrap();
+	graphics.create_pipeline("gone", source).unwrap();
+	graphics.set_area("gone", [0.0, 0.0, 4.0, 4.0]);
+
+	let mut board = crate::board::create_board();
+	board.add_node(
+		Node::new("py_node").set_script(Script::Python("def build():\n    pass\n".to_string())),
+	);
+	board.add_node(Node::new("wgsl_node").set_script(Script::WGSL(source.to_string())));
+	graphics.load_board(&board);
+	let shader = graphics.node_shaders["wgsl_node"];
+	// The Python node runs again and creates its pipeline anew, without the previous one.
+	assert_eq!(graphics.pipelines.keys().collect::<Vec<_>>(), [&shader]);
+	assert!(graphics.areas.is_empty());
+	let second = graphics.create_pipeline("py_node", source).unwrap();
+	assert_ne!(second, first);
+	assert!(graphics
+		.create_buffer(first, 0, ScriptBufferKind::Uniform, &[0; 16])
+		.is_err());
+
+	// Reloading keeps the shader of the WGSL node.
+	graphics.load_board(&board);
+	assert_eq!(graphics.pipelines.keys().collect::<Vec<_>>(), [&shader]);
+	graphics.remove_node("wgsl_node");
+	assert!(graphics.pipelines.is_empty());
+	assert_eq!(graphics.node_shader_source("wgsl_node"), None);
+}
+
+#[test]
+fn test_reflect_bindings() {
+	let source = "
+struct Light { color: vec3<f32>, power: f32, direction: vec2<f32> }
+@group(0) @binding(0) var<uniform> elapsed_time: f32;
+@group(0) @binding(3) var<uniform> light: Light;
+@group(2) @binding(0) var<storage, read> points: array<vec4<f32>>;
+@group(2) @binding(1) var color_map: texture_2d<f32>;
+@group(2) @binding(2) var color_sampler: sampler;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    let c = textureSample(color_map, color_sampler, vec2<f32>(0.5));
+    return c * light.power * elapsed_time + points[0];
+}
+";
+	let module = validate_wgsl(source).unwrap();
+	let bindings = reflect_bindings(&module, source).unwrap();
+	let names: Vec<_> = bindings
+		.iter()
+		.map(|b| (b.group, b.binding, b.name.as_str(), b.size))
+		.collect();
+	assert_eq!(
+		names,
+		[
+			(0, 0, "elapsed_time", 16),
+			(0, 3, "light", 32),
+			(2, 0, "points", 16),
+			(2, 1, "color_map", 0),
+			(2, 2, "color_sampler", 0),
+		]
+	);
+	assert_eq!(
+		bindings[2].ty,
+		BindingType::Buffer {
+			ty: wgpu::BufferBindingType::Storage { read_only: true },
+			has_dynamic_offset: false,
+			min_binding_size: None,
+		}
+	);
+	assert!(matches!(
+		bindings[4].ty,
+		BindingType::Sampler(SamplerBindingType::Filtering)
+	));
+
+	let source = "
+@group(0) @binding(0) var<uniform> elapsed_time: f32;
+@group(0) @binding(1) var frames: texture_multisampled_2d<f32>;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return textureLoad(frames, vec2<i32>(0), 0) * elapsed_time;
+}
+";
+	let error = reflect_bindings(&validate_wgsl(source).unwrap(), source).unwrap_err();
+	assert_eq!(error.kind, "ReflectionError");
+	assert!(error.message.contains("frames"), "{}", error.message);
+	assert_eq!(error.line, Some(3));
+}
+
+#[test]
+fn test_headless_graphics() {
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("headless graphics test", &error);
+			return;
+		}
+	};
+	let green =
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(0.0, 1.0, 0.0, 1.0);\n}\n";
+	let mut graphics = create_headless_graphics(&context, 16, 16).with_shader(green);
+	assert_eq!(graphics.shaders.len(), 1);
+	let target = crate::OffscreenTarget::with_format(&graphics.device, 16, 16, graphics.format());
+	graphics.render_all_into(&target.view(), target.size());
+	let pixels = target.read_rgba(&graphics.device, &graphics.queue);
+	// The shader draws the triangle of the canvas.
+	let pixel = |x: usize, y: usize| &pixels[(y * 16 + x) * 4..][..4];
+	assert_eq!(pixel(8, 8), [0, 255, 0, 255]);
+	assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+
+	// There is no window frame to draw or resize.
+	graphics.resize(16, 16);
+	graphics.render_all();
+	let path = std::env::temp_dir().join(format!("wavemod_headless_{}.png", std::process::id()));
+	graphics.screenshot(&path).unwrap();
+	assert!(std::fs::metadata(&path).unwrap().len() > 0);
+	let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_reflected_uniforms() {
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::example::BunnyRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("reflected uniforms test", &error);
+			return;
+		}
+	};
+	let (device, queue) = (&context.device, &context.queue);
+	let target = crate::OffscreenTarget::new(device, 8, 8, false);
+
+	// Bindings the old layout did not have, in a group after an unused one.
+	let source = "
+@group(1) @binding(2) var<uniform> tint: vec4<f32>;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return tint;
+}
+";
+	let shader = create_shader_pipeline(device, target.format(), source).unwrap();
+	assert!(shader.has_buffer("tint") && !shader.has_buffer(TIME_UNIFORM));
+	shader
+		.update_buffer(
+			queue,
+			"tint",
+			bytemuck::cast_slice(&[0.0f32, 0.0, 1.0, 1.0]),
+			0,
+		)
+		.unwrap();
+	assert!(shader.update_buffer(queue, "tint", &[0; 32], 0).is_err());
+	assert!(shader.update_buffer(queue, "missing", &[0; 4], 0).is_err());
+
+	target.render_shader(&shader, device, queue);
+	let pixels = target.read_rgba(device, queue);
+	assert_eq!(&pixels[(4 * 8 + 4) * 4..][..4], [0, 0, 255, 255]);
+
+	// Writable storage is only visible to the fragment stage, which wgpu requires.
+	let source = "
+@group(0) @binding(0) var<storage, read_write> hits: array<u32>;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    hits[0] = 1u;
+    return vec4<f32>(1.0);
+}
+";
+	let bindings = reflect_bindings(&validate_wgsl(source).unwrap(), source).unwrap();
+	assert_eq!(
+		binding_visibility(ShaderStages::VERTEX_FRAGMENT, bindings[0].writable()),
+		ShaderStages::FRAGMENT
+	);
+	if context
+		.adapter
+		.get_downlevel_capabilities()
+		.flags
+		.contains(wgpu::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE)
+	{
+		create_shader_pipeline(device, target.format(), source).unwrap();
+	}
+}
+
+#[test]
+fn test_shader_channels() {
+	let mut graphics = NodeGraphics::default();
+	graphics.set_channel("blur", 0, Some("noise")).unwrap();
+	graphics.set_channel("glow", 1, Some("blur")).unwrap();
+	assert!(graphics.set_channel("noise", 0, Some("glow")).is_err());
+	assert!(graphics.set_channel("noise", 0, Some("noise")).is_err());
+	assert!(graphics.set_channel("noise", 4, Some("other")).is_err());
+	assert_eq!(graphics.channel_order(), ["noise", "blur"]);
+	assert_eq!(
+		graphics.channels("glow"),
+		[None, Some("blur".to_string()), None, None]
+	);
+	graphics.set_channel("glow", 1, None).unwrap();
+	assert_eq!(graphics.channel_order(), ["noise"]);
+
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("shader channels test", &error);
+			return;
+		}
+	};
+	let target = crate::OffscreenTarget::new(&context.device, 16, 8, false);
+	let mut graphics = NodeGraphics::default();
+	graphics.attach(
+		context.device.clone(),
+		context.queue.clone(),
+		target.format(),
+	);
+	// The source draws green in its half of the board, the reader shows it in the other half.
+	graphics
+		.set_node_shader(
+			"source",
+			"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(0.0, 1.0, 0.0, 1.0);\n}\n",
+		)
+		.unwrap();
+	graphics
+		.set_node_shader(
+			"reader",
+			"@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    let uv = (p.xy - vec2<f32>(8.0, 0.0)) / iResolution.xy;\n    return textureSample(iChannel0, iSampler0, uv) + vec4<f32>(iChannelResolution[0].x / 16.0, 0.0, 0.0, 0.0);\n}\n",
+		)
+		.unwrap();
+	graphics.set_area("source", [0.0, 0.0, 8.0, 8.0]);
+	graphics.set_area("reader", [8.0, 0.0, 8.0, 8.0]);
+	graphics.set_channel("reader", 0, Some("source")).unwrap();
+
+	graphics.render(&target.view(), target.size());
+	let pixels = target.read_rgba(&context.device, &context.queue);
+	let pixel = |x: usize, y: usize| &pixels[(y * 16 + x) * 4..][..4];
+	assert_eq!(pixel(4, 4), [0, 255, 0, 255]);
+	assert_eq!(pixel(12, 4), [128, 255, 0, 255]);
+}
+
+#[test]
+fn test_node_areas() {
+	let camera = BoardCamera {
+		pan: (-4.0, 0.0),
+		zoom: 2.0,
+	};
+	assert_eq!(
+		camera.to_target([0.0, 0.0, 4.0, 4.0]),
+		[-4.0, 0.0, 8.0, 8.0]
+	);
+	assert_eq!(camera.to_board((2.0, 6.0)), (3.0, 3.0));
+	let mut graphics = NodeGraphics::default();
+	assert!(graphics
+		.set_camera(BoardCamera {
+			zoom: 0.0,
+			..camera
+		})
+		.is_err());
+	graphics.set_camera(camera).unwrap();
+	graphics.set_area("uv", [0.0, 0.0, 4.0, 4.0]);
+	let pointer = PointerEvent {
+		kind: crate::board::PointerKind::Move,
+		x: 2.0,
+		y: 6.0,
+		button: None,
+		delta: (0.0, 0.0),
+	};
+	assert_eq!(graphics.node_pointer("uv", &pointer), Some((3.0, 3.0)));
+	assert_eq!(graphics.node_pointer("board", &pointer), Some((2.0, 6.0)));
+
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("node areas test", &error);
+			return;
+		}
+	};
+	let target = crate::OffscreenTarget::new(&context.device, 16, 8, false);
+	graphics.attach(
+		context.device.clone(),
+		context.queue.clone(),
+		target.format(),
+	);
+	let uv = "@fragment\nfn fs_main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {\n    return vec4<f32>(uv, surface_size.x / 16.0, 1.0);\n}\n@group(0) @binding(0) var<uniform> surface_size: vec2<f32>;\n";
+	graphics.set_node_shader("uv", uv).unwrap();
+	graphics.set_node_shader("hidden", uv).unwrap();
+	graphics.set_area("hidden", [100.0, 0.0, 4.0, 4.0]);
+
+	graphics.render(&target.view(), target.size());
+	let pixels = target.read_rgba(&context.device, &context.queue);
+	let pixel = |x: usize, y: usize| &pixels[(y * 16 + x) * 4..][..4];
+	// Half of the zoomed area is left of the target, and cut instead of squeezed.
+	assert_eq!(pixel(0, 0), [143, 16, 128, 255]);
+	assert_eq!(pixel(3, 7), [239, 239, 128, 255]);
+	assert_eq!(pixel(4, 0), [0, 0, 0, 0]);
+	let graph = graphics.frame_graph(target.format(), [0.0, 0.0, 16.0, 8.0]);
+	let plan = graph.compile().unwrap();
+	let order: Vec<&str> = plan
+		.order
+		.iter()
+		.map(|&i| graph.pass(i).name.as_str())
+		.collect();
+	assert_eq!(order, ["draw uv"]);
+}
+
+#[test]
+fn test_buffer_passes() {
+	let pass = |name: &str, source: &str, channels: [Option<&str>; CHANNEL_COUNT]| BufferPassDesc {
+		name: name.to_string(),
+		source: source.to_string(),
+		format: TargetFormat::Rgba16Float,
+		size: None,
+		channels: channels.map(|c| c.map(str::to_string)),
+	};
+	// A accumulates a quarter every frame, B doubles the current frame of A.
+	let a = pass(
+		"A",
+		"@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    return textureLoad(iChannel0, vec2<i32>(p.xy), 0) + vec4<f32>(0.25, 0.0, 0.0, 0.25);\n}\n",
+		[Some("A"), None, None, None],
+	);
+	let b = pass(
+		"B",
+		"@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    return textureLoad(iChannel1, vec2<i32>(p.xy), 0) * 2.0;\n}\n",
+		[None, Some("A"), None, None],
+	);
+	let passes = ShaderPasses {
+		buffers: vec![a.clone(), b.clone()],
+		image_channels: [Some("A".to_string()), Some("B".to_string()), None, None],
+	};
+
+	let mut graphics = NodeGraphics::default();
+	let unknown = ShaderPasses {
+		buffers: vec![pass("A", &a.source, [Some("C"), None, None, None])],
+		..Default::default()
+	};
+	assert!(graphics.set_passes("node", unknown).is_err());
+	let duplicate = ShaderPasses {
+		buffers: vec![a.clone(), a.clone()],
+		..Default::default()
+	};
+	assert!(graphics.set_passes("node", duplicate).is_err());
+	let broken = ShaderPasses {
+		buffers: vec![pass("A", "fn broken(", [None; CHANNEL_COUNT])],
+		..Default::default()
+	};
+	assert!(graphics.set_passes("node", broken).is_err());
+	graphics.set_passes("node", passes.clone()).unwrap();
+	assert_eq!(graphics.passes("node"), passes);
+	graphics
+		.set_passes("node", ShaderPasses::default())
+		.unwrap();
+	assert!(graphics.passes.is_empty());
+
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("buffer passes test", &error);
+			return;
+		}
+	};
+	let target = crate::OffscreenTarget::new(&context.device, 8, 8, false);
+	let mut graphics = NodeGraphics::default();
+	graphics.attach(
+		context.device.clone(),
+		context.queue.clone(),
+		target.format(),
+	);
+	graphics
+		.set_node_shader(
+			"node",
+			"@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    let texel = vec2<i32>(p.xy);\n    return vec4<f32>(textureLoad(iChannel0, texel, 0).r, textureLoad(iChannel1, texel, 0).r, 0.0, 1.0);\n}\n",
+		)
+		.unwrap();
+	graphics.set_passes("node", passes).unwrap();
+
+	let mut render = || {
+		graphics.render(&target.view(), target.size());
+		target.read_rgba(&context.device, &context.queue)[..4].to_vec()
+	};
+	assert_eq!(render(), [64, 128, 0, 255]);
+	assert_eq!(render(), [128, 255, 0, 255]);
+	assert_eq!(render(), [191, 255, 0, 255]);
+}
+
+#[test]
+fn test_compute_nodes() {
+	let fill = "@group(0) @binding(0) var<storage, read_write> values: array<f32>;\n@group(0) @binding(1) var image: texture_storage_2d<rgba8unorm, write>;\n\n@compute @workgroup_size(4)\nfn main(@builtin(global_invocation_id) id: vec3<u32>) {\n    values[id.x] = f32(id.x) / 16.0;\n    textureStore(image, vec2<i32>(i32(id.x), 0), vec4<f32>(0.0, 1.0, 0.0, 1.0));\n}\n";
+	let double = "@group(0) @binding(0) var<storage, read> values: array<f32>;\n@group(0) @binding(1) var<storage, read_write> doubled: array<f32>;\n\n@compute @workgroup_size(4)\nfn main(@builtin(global_invocation_id) id: vec3<u32>) {\n    doubled[id.x] = values[id.x] * 2.0;\n}\n";
+	let show = "@group(0) @binding(0) var<storage, read> doubled: array<f32>;\n@group(0) @binding(1) var image: texture_2d<f32>;\n\n@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    let x = u32(p.x);\n    return vec4<f32>(doubled[x], textureLoad(image, vec2<i32>(i32(x), 0), 0).g, 0.0, 1.0);\n}\n";
+
+	let module = validate_wgsl(double).unwrap();
+	assert!(is_compute_shader(&module));
+	let bindings = reflect_bindings(&module, double).unwrap();
+	assert_eq!((bindings[0].stride, bindings[0].writable()), (4, false));
+	assert!(bindings[1].writable());
+	assert!(!is_compute_shader(&validate_wgsl(show).unwrap()));
+
+	let mut graphics = NodeGraphics::default();
+	assert!(graphics.create_storage_buffer("values", &[0; 6]).is_err());
+	assert!(graphics
+		.set_node_dispatch("double", DispatchSize::default())
+		.is_err());
+	graphics.set_node_shader("double", double).unwrap();
+	assert!(graphics
+		.set_node_dispatch("double", DispatchSize::Workgroups([0, 1, 1]))
+		.is_err());
+
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("compute nodes test", &error);
+			return;
+		}
+	};
+	let downlevel = context.adapter.get_downlevel_capabilities();
+	if !downlevel
+		.flags
+		.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+	{
+		log::warn!("Skipping compute nodes test: the adapter has no compute shaders");
+		return;
+	}
+	let target = crate::OffscreenTarget::new(&context.device, 8, 8, false);
+	graphics.attach(
+		context.device.clone(),
+		context.queue.clone(),
+		target.format(),
+	);
+	graphics.create_storage_buffer("values", &[0; 32]).unwrap();
+	graphics.create_storage_buffer("doubled", &[0; 32]).unwrap();
+	graphics
+		.create_storage_texture("image", (8, 1), StorageTextureFormat::Rgba8Unorm)
+		.unwrap();
+	// Created after the shader reading what it writes, it is still dispatched first.
+	graphics.set_node_shader("fill", fill).unwrap();
+	graphics
+		.set_node_dispatch("fill", DispatchSize::Invocations([8, 1, 1]))
+		.unwrap();
+	graphics
+		.set_node_dispatch("double", DispatchSize::BufferLength("doubled".to_string()))
+		.unwrap();
+	graphics.set_node_shader("show", show).unwrap();
+
+	graphics.render(&target.view(), target.size());
+	let double_id = graphics.node_shaders["double"];
+	let graph = graphics.frame_graph(target.format(), [0.0, 0.0, 8.0, 8.0]);
+	let plan = graph.compile().unwrap();
+	let order: Vec<&str> = plan
+		.order
+		.iter()
+		.map(|&i| graph.pass(i).name.as_str())
+		.collect();
+	assert_eq!(order, ["compute fill", "compute double", "draw show"]);
+	let double_shader = graphics.pipelines[&double_id].shader.as_ref().unwrap();
+	assert_eq!(double_shader.workgroup_size(), Some([4, 1, 1]));
+	assert_eq!(
+		graphics.pipelines[&double_id]
+			.dispatch
+			.workgroups(double_shader),
+		Ok([2, 1, 1])
+	);
+
+	let pixels = target.read_rgba(&context.device, &context.queue);
+	let pixel = |x: usize| &pixels[(4 * 8 + x) * 4..][..4];
+	assert_eq!(pixel(0), [0, 255, 0, 255]);
+	assert_eq!(pixel(4), [128, 255, 0, 255]);
+}
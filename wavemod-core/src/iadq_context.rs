@@ -32,7 +32,7 @@ impl IADQContext {
 		)
 		.await;
 		// Make sure we use the texture resolution limits from the adapter, so we can support images the size of the surface.
-		let needed_limits = R::required_limits().using_resolution(adapter.limits());
+		let needed_limits = device_limits::<R>(&adapter);
 
 		let trace_dir = std::env::var("WGPU_TRACE");
 		let (device, queue) = adapter
@@ -56,6 +56,94 @@ impl IADQContext {
 			queue,
 		}
 	}
+
+	/// Initializes wgpu without a window, for rendering into an [`crate::OffscreenTarget`].
+	///
+	/// Falls back to a software adapter when no hardware one is available.
+	pub async fn init_headless<R: Renderer>() -> Result<Self, String> {
+		log::info!("Initializing headless wgpu...");
+
+		let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+		let mut adapter = None;
+		for force_fallback_adapter in [false, true] {
+			adapter = instance
+				.request_adapter(&wgpu::RequestAdapterOptions {
+					power_preference: wgpu::PowerPreference::from_env()
+						.unwrap_or(wgpu::PowerPreference::HighPerformance),
+					force_fallback_adapter,
+					compatible_surface: None,
+				})
+				.await
+				.filter(|adapter| adapter.features().contains(R::required_features()));
+			if adapter.is_some() {
+				break;
+			}
+		}
+		let adapter = adapter.ok_or("No suitable GPU adapter found")?;
+		let adapter_info = adapter.get_info();
+		log::info!("Using {} ({:?})", adapter_info.name, adapter_info.backend);
+
+		let (device, queue) = adapter
+			.request_device(
+				&wgpu::DeviceDescriptor {
+					label: None,
+					required_features: (R::optional_features() & adapter.features())
+						| R::required_features(),
+					required_limits: device_limits::<R>(&adapter),
+					memory_hints: wgpu::MemoryHints::MemoryUsage,
+				},
+				None,
+			)
+			.await
+			.map_err(|e| format!("Cannot create the device: {}", e))?;
+
+		Ok(Self {
+			instance,
+			adapter,
+			device,
+			queue,
+		})
+	}
+}
+
+/// Limits of `R` raised to the texture sizes of the adapter and, on native, to its storage
+/// and compute limits, which the compute shaders of node scripts need.
+fn device_limits<R: Renderer>(adapter: &wgpu::Adapter) -> wgpu::Limits {
+	let supported = adapter.limits();
+	let limits = R::required_limits().using_resolution(supported.clone());
+	if cfg!(target_arch = "wasm32") {
+		return limits;
+	}
+	wgpu::Limits {
+		max_storage_buffers_per_shader_stage: limits
+			.max_storage_buffers_per_shader_stage
+			.max(supported.max_storage_buffers_per_shader_stage),
+		max_storage_textures_per_shader_stage: limits
+			.max_storage_textures_per_shader_stage
+			.max(supported.max_storage_textures_per_shader_stage),
+		max_storage_buffer_binding_size: limits
+			.max_storage_buffer_binding_size
+			.max(supported.max_storage_buffer_binding_size),
+		max_compute_workgroup_storage_size: limits
+			.max_compute_workgroup_storage_size
+			.max(supported.max_compute_workgroup_storage_size),
+		max_compute_invocations_per_workgroup: limits
+			.max_compute_invocations_per_workgroup
+			.max(supported.max_compute_invocations_per_workgroup),
+		max_compute_workgroup_size_x: limits
+			.max_compute_workgroup_size_x
+			.max(supported.max_compute_workgroup_size_x),
+		max_compute_workgroup_size_y: limits
+			.max_compute_workgroup_size_y
+			.max(supported.max_compute_workgroup_size_y),
+		max_compute_workgroup_size_z: limits
+			.max_compute_workgroup_size_z
+			.max(supported.max_compute_workgroup_size_z),
+		max_compute_workgroups_per_dimension: limits
+			.max_compute_workgroups_per_dimension
+			.max(supported.max_compute_workgroups_per_dimension),
+		..limits
+	}
 }
 
 async fn create_iasdq_context(
@@ -0,0 +1,437 @@
+//! JavaScript node scripts, run by the embedded Boa engine on native and web builds alike.
+
+use std::path::Path;
+
+use boa_engine::object::ObjectInitializer;
+use boa_engine::property::Attribute;
+use boa_engine::{
+	js_string, Context, JsError, JsObject, JsResult, JsString, JsValue, NativeFunction, Source,
+};
+
+use crate::board::{source_text, BoardState, Node, NodeEvent, Script, ScriptError, ScriptFrame};
+use crate::schema::{SchemaObject, SchemaValue};
+
+/// Global functions a node script can define to receive `NodeEvent`s.
+const HANDLERS: [&str; 4] = ["onFrame", "onResize", "onPointer", "onPropertyChanged"];
+
+fn handler_name(event: &NodeEvent) -> &'static str {
+	match event {
+		NodeEvent::Frame { .. } => HANDLERS[0],
+		NodeEvent::Resize { .. } => HANDLERS[1],
+		NodeEvent::Pointer(_) => HANDLERS[2],
+		NodeEvent::PropertyChanged { .. } => HANDLERS[3],
+	}
+}
+
+fn schema_to_js(value: &SchemaValue, context: &mut Context) -> JsValue {
+	match value {
+		SchemaValue::String(s) | SchemaValue::SchemaLink(s) => JsString::from(s.as_str()).into(),
+		SchemaValue::U64(n) => JsValue::from(*n as f64),
+		SchemaValue::I64(n) => JsValue::from(*n as f64),
+		SchemaValue::F64(n) => JsValue::from(*n),
+		SchemaValue::Bool(b) => JsValue::from(*b),
+		SchemaValue::SchemaObject(object) => schema_object_to_js(object, context).into(),
+	}
+}
+
+fn schema_object_to_js(object: &SchemaObject, context: &mut Context) -> JsObject {
+	let mut entries: Vec<_> = object.entries.iter().collect();
+	entries.sort_by_key(|(key, _)| key.as_str());
+	let entries: Vec<_> = entries
+		.into_iter()
+		.map(|(key, value)| (JsString::from(key.as_str()), schema_to_js(value, context)))
+		.collect();
+
+	let mut object = ObjectInitializer::new(context);
+	for (key, value) in entries {
+		object.property(key, value, Attribute::all());
+	}
+	object.build()
+}
+
+/// `console` object writing to the log, prefixed with the node name like `commands::info`.
+fn console_object(node: &str, context: &mut Context) -> JsObject {
+	let node = JsString::from(node);
+	let mut console = ObjectInitializer::new(context);
+	for (name, level) in [
+		(js_string!("log"), log::Level::Info),
+		(js_string!("info"), log::Level::Info),
+		(js_string!("debug"), log::Level::Debug),
+		(js_string!("warn"), log::Level::Warn),
+		(js_string!("error"), log::Level::Error),
+	] {
+		let function = NativeFunction::from_copy_closure_with_captures(
+			move |_, args, node: &JsString, _| {
+				let line: Vec<_> = args
+					.iter()
+					.map(|arg| match arg.as_string() {
+						Some(s) => s.to_std_string_escaped(),
+						None => arg.display().to_string(),
+					})
+					.collect();
+				log::log!(
+					level,
+					"{}: {}",
+					node.to_std_string_escaped(),
+					line.join(" ")
+				);
+				Ok(JsValue::undefined())
+			},
+			node.clone(),
+		);
+		console.function(function, name, 0);
+	}
+	console.build()
+}
+
+/// Parses a line of an `Error.stack`, e.g. `    at build (plug:4:21)`.
+fn stack_frame(line: &str) -> Option<(String, String, u32, u32)> {
+	let (function, location) = line.trim().strip_prefix("at ")?.split_once(" (")?;
+	let mut location = location.strip_suffix(')')?.rsplitn(3, ':');
+	let column = location.next()?.parse().ok()?;
+	let line = location.next()?.parse().ok()?;
+	let file = location.next()?;
+	Some((file.to_string(), function.to_string(), line, column))
+}
+
+/// Converts an exception thrown by the node `script_name` into a [`ScriptError`].
+fn script_error(
+	error: JsError,
+	context: &mut Context,
+	script_name: &str,
+	source: &str,
+) -> ScriptError {
+	let (kind, message) = match error.try_native(context) {
+		Ok(native) => (native.kind().to_string(), native.message().to_string()),
+		Err(_) => {
+			let value = error.as_opaque().cloned().unwrap_or_default();
+			let message = match value.as_string() {
+				Some(s) => s.to_std_string_escaped(),
+				None => value.display().to_string(),
+			};
+			("Error".to_string(), message)
+		}
+	};
+	let mut error_info = ScriptError::new(&kind, message);
+
+	// Syntax errors are reported before anything runs, the location ends the message.
+	if error_info.kind == "SyntaxError" {
+		if let Some((message, location)) = error_info.message.rsplit_once(" at line ") {
+			let position = location.split_once(", col ").and_then(|(line, column)| {
+				Some((line.parse::<u32>().ok()?, column.parse::<u32>().ok()?))
+			});
+			if let Some((line, column)) = position {
+				error_info.message = message.to_string();
+				error_info.line = Some(line);
+				error_info.columns = Some((column, column + 1));
+				error_info.text = source_text(source, line);
+			}
+		}
+		return error_info;
+	}
+
+	let stack = error
+		.into_opaque(context)
+		.ok()
+		.and_then(|value| value.as_object())
+		.and_then(|object| object.get(js_string!("stack"), context).ok())
+		.and_then(|stack| stack.as_string())
+		.map(|stack| stack.to_std_string_escaped())
+		.unwrap_or_default();
+
+	// The stack lists the innermost call first.
+	for (file, function, line, column) in stack.lines().rev().filter_map(stack_frame) {
+		let in_node = file == script_name;
+		if in_node {
+			error_info.line = Some(line);
+			error_info.columns = Some((column, column + 1));
+			error_info.text = source_text(source, line);
+		}
+		error_info.frames.push(ScriptFrame {
+			text: in_node.then(|| source_text(source, line)).flatten(),
+			file,
+			function,
+			line: Some(line),
+		});
+	}
+
+	error_info
+}
+
+/// A JavaScript node, with its own global scope.
+struct JsNode {
+	name: String,
+	source: String,
+	context: Context,
+	/// Handlers the script defines, a handler throwing an exception is dropped.
+	handlers: Vec<&'static str>,
+}
+
+/// Creates the node context, runs the script and calls its `build()`.
+///
+/// Scripts see the node as `wmd.CURRENT_NODE`, its properties as `wmd.props`, and log
+/// through `console`.
+fn load_node(node: &Node, source: &str) -> Result<JsNode, Box<ScriptError>> {
+	let mut context = Context::default();
+	let result = (|| -> JsResult<()> {
+		let props = schema_object_to_js(node.properties(), &mut context);
+		let wmd = ObjectInitializer::new(&mut context)
+			.property(
+				js_string!("CURRENT_NODE"),
+				JsString::from(node.name()),
+				Attribute::READONLY,
+			)
+			.property(js_string!("props"), props, Attribute::all())
+			.build();
+		context.register_global_property(js_string!("wmd"), wmd, Attribute::all())?;
+		let console = console_object(node.name(), &mut context);
+		context.register_global_property(js_string!("console"), console, Attribute::all())?;
+
+		let path = Path::new(node.name());
+		context.eval(Source::from_bytes(source).with_path(path))?;
+
+		let build = context
+			.global_object()
+			.get(js_string!("build"), &mut context)?;
+		if let Some(build) = build.as_callable() {
+			build.call(&JsValue::undefined(), &[], &mut context)?;
+		}
+		Ok(())
+	})();
+
+	if let Err(error) = result {
+		return Err(script_error(error, &mut context, node.name(), source).into());
+	}
+
+	let global = context.global_object();
+	let handlers = HANDLERS
+		.into_iter()
+		.filter(|name| {
+			global
+				.get(JsString::from(*name), &mut context)
+				.is_ok_and(|handler| handler.is_callable())
+		})
+		.collect();
+	Ok(JsNode {
+		name: node.name().to_string(),
+		source: source.to_string(),
+		context,
+		handlers,
+	})
+}
+
+/// JavaScript node scripts of a board, kept loaded to receive events from the event loop.
+pub struct JsHost {
+	nodes: Vec<JsNode>,
+}
+
+impl JsHost {
+	/// Runs the JavaScript scripts of every node of the board, logging the ones that fail.
+	pub fn load_board(board: &BoardState) -> Self {
+		fn load(nodes: &mut Vec<JsNode>, node: &Node) {
+			if let Some(Script::JavaScript(source)) = node.script() {
+				match load_node(node, source) {
+					Ok(js_node) => nodes.push(js_node),
+					Err(error) => {
+						log::error!("JavaScript node '{}' failed: {}", node.name(), error)
+					}
+				}
+			}
+			for subnode in node.subnodes() {
+				load(nodes, subnode);
+			}
+		}
+
+		let mut nodes = Vec::new();
+		load(&mut nodes, board.root_node());
+		JsHost { nodes }
+	}
+
+	/// Calls the handler of `event` of `node`, or of every node when `None`.
+	///
+	/// Returns the errors thrown by handlers, which are not called again afterwards.
+	pub fn dispatch(
+		&mut self,
+		node: Option<&str>,
+		event: &NodeEvent,
+	) -> Vec<(String, ScriptError)> {
+		let handler = handler_name(event);
+		let mut errors = Vec::new();
+		for js_node in &mut self.nodes {
+			if node.is_some_and(|node| node != js_node.name) || !js_node.handlers.contains(&handler)
+			{
+				continue;
+			}
+			let context = &mut js_node.context;
+			let args = match event {
+				NodeEvent::Frame { dt } => vec![JsValue::from(*dt as f64)],
+				NodeEvent::Resize { width, height } => {
+					vec![JsValue::from(*width), JsValue::from(*height)]
+				}
+				NodeEvent::Pointer(pointer) => {
+					// Pointer events only reach nodes drawing under the pointer, in node coordinates.
+					#[cfg(not(target_arch = "wasm32"))]
+					let position = crate::graphics::node_graphics()
+						.lock()
+						.unwrap()
+						.node_pointer(&js_node.name, pointer);
+					#[cfg(target_arch = "wasm32")]
+					let position = pointer.local_position(None);
+					let Some((x, y)) = position else {
+						continue;
+					};
+					let button = pointer.button.map_or(JsValue::null(), JsValue::from);
+					let event = ObjectInitializer::new(context)
+						.property(
+							js_string!("kind"),
+							JsString::from(pointer.kind.name()),
+							Attribute::all(),
+						)
+						.property(js_string!("x"), x as f64, Attribute::all())
+						.property(js_string!("y"), y as f64, Attribute::all())
+						.property(js_string!("button"), button, Attribute::all())
+						.property(
+							js_string!("deltaX"),
+							pointer.delta.0 as f64,
+							Attribute::all(),
+						)
+						.property(
+							js_string!("deltaY"),
+							pointer.delta.1 as f64,
+							Attribute::all(),
+						)
+						.build();
+					vec![event.into()]
+				}
+				NodeEvent::PropertyChanged { key, value } => {
+					vec![
+						JsString::from(key.as_str()).into(),
+						schema_to_js(value, context),
+					]
+				}
+			};
+
+			let result = context
+				.global_object()
+				.get(JsString::from(handler), context)
+				.and_then(|function| match function.as_callable() {
+					Some(function) => function.call(&JsValue::undefined(), &args, context),
+					None => Ok(JsValue::undefined()),
+				});
+			if let Err(error) = result {
+				let error = script_error(error, context, &js_node.name, &js_node.source);
+				js_node.handlers.retain(|name| *name != handler);
+				errors.push((js_node.name.clone(), error));
+			}
+		}
+		errors
+	}
+}
+
+#[test]
+fn test_js() {
+	let node = Node::new("js_plug").with_schematic(&schema!({ speed: 3u64 }));
+	let result = load_node(
+		&node,
+		r#"
+console.log("loading", wmd.CURRENT_NODE);
+
+function build() {
+    if (wmd.props.speed !== 3) {
+        throw new Error("missing props");
+    }
+}
+"#,
+	)
+	.map(|_| ());
+
+	assert!(result.is_ok(), "load_node failed: {:?}", result.err());
+}
+
+#[test]
+fn test_js_error_lines() {
+	let source = r#"
+function divide(a, b) {
+    return a.value / b;
+}
+
+function build() {
+    divide(null, 0);
+}
+"#;
+	let error = load_node(&Node::new("js_err"), source).err().unwrap();
+
+	assert_eq!(error.kind, "TypeError");
+	assert_eq!(error.line, Some(3));
+	assert_eq!(error.text.as_deref(), Some("    return a.value / b;"));
+	let node_lines: Vec<_> = error
+		.frames
+		.iter()
+		.filter(|frame| frame.file == "js_err")
+		.map(|frame| (frame.function.as_str(), frame.line))
+		.collect();
+	assert_eq!(node_lines, [("build", Some(7)), ("divide", Some(3))]);
+
+	let error = load_node(&Node::new("js_syntax"), "let x = 1;\nlet y = (;\n")
+		.err()
+		.unwrap();
+	assert_eq!(error.kind, "SyntaxError");
+	assert_eq!(error.line, Some(2));
+	assert_eq!(error.text.as_deref(), Some("let y = (;"));
+}
+
+#[test]
+fn test_js_events() {
+	let mut board = crate::board::create_board();
+	board.add_node(
+		Node::new("js_events").set_script(Script::JavaScript(
+			r#"
+var events = [];
+
+function onFrame(dt) {
+    events.push("frame " + wmd.CURRENT_NODE + " " + dt);
+}
+
+function onPropertyChanged(key, value) {
+    events.push(key + " " + value);
+}
+
+function onResize(width, height) {
+    throw new RangeError("resize failed");
+}
+"#
+			.to_string(),
+		)),
+	);
+
+	let mut host = JsHost::load_board(&board);
+	assert!(host
+		.dispatch(None, &NodeEvent::Frame { dt: 0.5 })
+		.is_empty());
+	assert!(board.set_property("js_events", "speed", SchemaValue::U64(3)));
+	for (node, event) in board.take_events() {
+		assert!(host.dispatch(Some(&node), &event).is_empty());
+	}
+
+	// A failing handler reports its error once, then stops receiving events.
+	let resize = NodeEvent::Resize {
+		width: 800,
+		height: 600,
+	};
+	let errors = host.dispatch(None, &resize);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].0, "js_events");
+	assert_eq!(errors[0].1.kind, "RangeError");
+	assert_eq!(errors[0].1.line, Some(13));
+	assert!(host.dispatch(None, &resize).is_empty());
+
+	let js_node = &mut host.nodes[0];
+	let events = js_node
+		.context
+		.eval(Source::from_bytes("events.join(', ')"))
+		.unwrap();
+	assert_eq!(
+		events.as_string().unwrap().to_std_string_escaped(),
+		"frame js_events 0.5, speed 3"
+	);
+}
@@ -6,16 +6,27 @@ mod global;
 #[macro_use]
 mod schema;
 mod board;
+mod notebook;
+#[cfg(not(target_arch = "wasm32"))]
+mod c;
 mod commands;
 mod graphics;
+mod js;
 #[cfg(not(target_arch = "wasm32"))]
 mod python;
+#[cfg(not(target_arch = "wasm32"))]
+pub use python::handle_worker_arg;
+mod scripts;
 mod setup;
+mod shadertoy;
+
+pub mod render_graph;
 
 pub mod utils;
 pub use utils::*;
 
 mod example;
+pub use example::splatting;
 
 mod renderer;
 pub use renderer::*;
@@ -26,6 +37,17 @@ pub use surface_wrapper::*;
 mod iadq_context;
 pub use iadq_context::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod offscreen;
+#[cfg(not(target_arch = "wasm32"))]
+pub use offscreen::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod golden;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod render_loop;
+
 use crate::setup::setup_logging;
 
 use std::env;
@@ -38,13 +60,27 @@ use std::sync::Arc as SharedPtr;
 pub fn run() {
 	setup_logging();
 
+	// Python nodes of each board run in a copy of the app started as a worker.
+	#[cfg(not(target_arch = "wasm32"))]
+	if python::handle_worker_arg() {
+		return;
+	}
+
 	log::info!("Operating System: {}", env::consts::OS);
 	log::info!("Architecture: {}", env::consts::ARCH);
 
 	log::info!("Initializing application");
 
 	let title = "wavemod-core";
-	let loop_function = || setup::setup_app::<example::BunnyRenderer>(title.to_string());
+	// `WAVEMOD_RENDERER=splatting` shows the Gaussian splatting example instead of the bunnies.
+	let show_splatting = env::var("WAVEMOD_RENDERER").is_ok_and(|renderer| renderer == "splatting");
+	let loop_function = move || async move {
+		if show_splatting {
+			setup::setup_app::<splatting::SplattingRenderer>(title.to_string()).await
+		} else {
+			setup::setup_app::<example::BunnyRenderer>(title.to_string()).await
+		}
+	};
 
 	cfg_if::cfg_if! {
 	  if #[cfg(target_arch = "wasm32")] {
@@ -0,0 +1,431 @@
+//! Jupyter notebooks (nbformat 4) imported as boards, and boards exported as notebooks.
+//!
+//! Code cells become Python nodes and markdown cells become notes, placed in a column in
+//! cell order and chained by edges. As in a notebook, the code cells run in cell order in
+//! one namespace, so cells can use the names defined by earlier cells.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use base64::Engine;
+use serde_json::{json, Value};
+
+use crate::board::{create_board, BoardState, Node, NodeOutput, Script, CELL_TYPE, NOTE_TYPE};
+use crate::schema::{SchemaObject, SchemaValue};
+
+/// Vertical distance between the nodes of consecutive cells.
+const CELL_SPACING: f64 = 50.0;
+
+/// Code of the first exported cell, standing in for the prelude of node scripts.
+const PRELUDE_CELL: &str = "import wavemod as wmd\n";
+
+/// Code appended to exported cells of nodes defining `build()`.
+const BUILD_CALL: &str = "\n\nbuild()\n";
+
+#[derive(Debug)]
+pub enum NotebookError {
+	Json(serde_json::Error),
+	/// The notebook is not in the nbformat 4 layout.
+	Format(String),
+	/// Nodes whose edges form a cycle, which has no cell order.
+	Cycle(Vec<String>),
+}
+
+impl fmt::Display for NotebookError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			NotebookError::Json(e) => write!(f, "Invalid notebook JSON: {}", e),
+			NotebookError::Format(message) => write!(f, "Invalid notebook: {}", message),
+			NotebookError::Cycle(nodes) => {
+				write!(f, "The edges of nodes {} form a cycle", nodes.join(", "))
+			}
+		}
+	}
+}
+
+impl std::error::Error for NotebookError {}
+
+impl From<serde_json::Error> for NotebookError {
+	fn from(e: serde_json::Error) -> Self {
+		NotebookError::Json(e)
+	}
+}
+
+/// Text of a notebook field, which is a string or a list of lines.
+fn cell_text(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.clone(),
+		Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+		_ => String::new(),
+	}
+}
+
+/// Text as a list of lines keeping their line ending, as notebooks store it.
+fn notebook_lines(text: &str) -> Value {
+	text.split_inclusive('\n').collect()
+}
+
+/// Text and PNG outputs of a code cell. Other outputs are dropped.
+fn cell_outputs(outputs: &Value) -> Result<Vec<NodeOutput>, NotebookError> {
+	let mut node_outputs = Vec::new();
+	for output in outputs.as_array().into_iter().flatten() {
+		match output["output_type"].as_str() {
+			Some("stream") => node_outputs.push(NodeOutput::Text(cell_text(&output["text"]))),
+			Some("execute_result" | "display_data") => {
+				let data = &output["data"];
+				if let Some(png) = data.get("image/png") {
+					// Notebooks may wrap base64 data in lines.
+					let png: String = cell_text(png).split_whitespace().collect();
+					let png = base64::engine::general_purpose::STANDARD
+						.decode(png)
+						.map_err(|e| NotebookError::Format(format!("Invalid PNG output: {}", e)))?;
+					node_outputs.push(NodeOutput::Png(png));
+				} else if let Some(text) = data.get("text/plain") {
+					node_outputs.push(NodeOutput::Text(cell_text(text)));
+				}
+			}
+			Some("error") => node_outputs.push(NodeOutput::Text(format!(
+				"{}: {}",
+				output["ename"].as_str().unwrap_or_default(),
+				output["evalue"].as_str().unwrap_or_default()
+			))),
+			_ => {}
+		}
+	}
+	Ok(node_outputs)
+}
+
+/// Creates a board with a node per code and markdown cell of the notebook.
+pub fn import_notebook(json: &str) -> Result<BoardState, NotebookError> {
+	let notebook: Value = serde_json::from_str(json)?;
+	if notebook["nbformat"].as_u64() != Some(4) {
+		return Err(NotebookError::Format(
+			"only nbformat 4 notebooks are supported".to_string(),
+		));
+	}
+	let cells = notebook["cells"]
+		.as_array()
+		.ok_or_else(|| NotebookError::Format("missing cells".to_string()))?;
+
+	let mut board = create_board();
+	let mut previous: Option<String> = None;
+	let mut position = 0;
+	for (index, cell) in cells.iter().enumerate() {
+		// Cells exported from a board keep the name of their node.
+		let wavemod = &cell["metadata"]["wavemod"];
+		if wavemod["prelude"].as_bool() == Some(true) {
+			continue;
+		}
+		let mut source = cell_text(&cell["source"]);
+		let (name, mut node) = match cell["cell_type"].as_str() {
+			Some("code") => {
+				let name = wavemod["node"]
+					.as_str()
+					.map_or_else(|| format!("cell_{}", index + 1), str::to_string);
+				let mut node = Node::new(&name);
+				// Cells exported from nodes defining `build()` become such nodes again.
+				match wavemod["calls_build"].as_bool() == Some(true) {
+					true => {
+						if let Some(script) = source.strip_suffix(BUILD_CALL) {
+							source = format!("{}\n", script);
+						}
+					}
+					false => node = node.with_type(CELL_TYPE),
+				}
+				let node = node
+					.set_script(Script::Python(source))
+					.with_outputs(cell_outputs(&cell["outputs"])?);
+				(name, node)
+			}
+			Some("markdown") => {
+				let name = wavemod["node"]
+					.as_str()
+					.map_or_else(|| format!("note_{}", index + 1), str::to_string);
+				let mut text = SchemaObject::new();
+				text.entries
+					.insert("text".to_string(), SchemaValue::String(source));
+				let node = Node::new(&name).with_type(NOTE_TYPE).with_schematic(&text);
+				(name, node)
+			}
+			// Raw cells have nothing to run or show.
+			_ => continue,
+		};
+		node.set_property("x", SchemaValue::F64(0.0));
+		node.set_property("y", SchemaValue::F64(position as f64 * CELL_SPACING));
+		position += 1;
+
+		board.add_node(node);
+		if let Some(previous) = previous.replace(name.clone()) {
+			board.add_edge(&previous, &name);
+		}
+	}
+	Ok(board)
+}
+
+/// Nodes of the board that are exported, in board order.
+fn exported_nodes<'a>(node: &'a Node, nodes: &mut Vec<&'a Node>) {
+	for subnode in node.subnodes() {
+		let is_note = subnode.node_type() == Some(NOTE_TYPE);
+		if is_note || matches!(subnode.script(), Some(Script::Python(_))) {
+			nodes.push(subnode);
+		}
+		exported_nodes(subnode, nodes);
+	}
+}
+
+/// Orders nodes after the nodes they have an edge from, keeping the board order otherwise.
+fn topological_order<'a>(
+	nodes: &[&'a Node],
+	edges: &[(String, String)],
+) -> Result<Vec<&'a Node>, NotebookError> {
+	let index: HashMap<&str, usize> = nodes
+		.iter()
+		.enumerate()
+		.map(|(i, node)| (node.name(), i))
+		.collect();
+	let mut incoming = vec![0; nodes.len()];
+	let mut outgoing = vec![Vec::new(); nodes.len()];
+	for (from, to) in edges {
+		if let (Some(&from), Some(&to)) = (index.get(from.as_str()), index.get(to.as_str())) {
+			incoming[to] += 1;
+			outgoing[from].push(to);
+		}
+	}
+
+	let mut order = Vec::with_capacity(nodes.len());
+	let mut done = vec![false; nodes.len()];
+	while order.len() < nodes.len() {
+		let Some(next) = (0..nodes.len()).find(|&i| !done[i] && incoming[i] == 0) else {
+			let cycle = (0..nodes.len())
+				.filter(|&i| !done[i])
+				.map(|i| nodes[i].name().to_string())
+				.collect();
+			return Err(NotebookError::Cycle(cycle));
+		};
+		done[next] = true;
+		for &to in &outgoing[next] {
+			incoming[to] -= 1;
+		}
+		order.push(nodes[next]);
+	}
+	Ok(order)
+}
+
+fn output_json(output: &NodeOutput) -> Value {
+	match output {
+		NodeOutput::Text(text) => json!({
+			"output_type": "stream",
+			"name": "stdout",
+			"text": notebook_lines(text),
+		}),
+		NodeOutput::Png(png) => json!({
+			"output_type": "display_data",
+			"data": {
+				"image/png": base64::engine::general_purpose::STANDARD.encode(png),
+			},
+			"metadata": {},
+		}),
+	}
+}
+
+/// Writes the Python nodes and notes of the board as a notebook, in the order of their edges.
+///
+/// Code cells of nodes defining `build()` call it, so running the notebook runs the nodes.
+pub fn export_notebook(board: &BoardState) -> Result<String, NotebookError> {
+	let mut nodes = Vec::new();
+	exported_nodes(board.root_node(), &mut nodes);
+	let nodes = topological_order(&nodes, board.edges())?;
+
+	let mut cells = vec![json!({
+		"cell_type": "code",
+		"execution_count": null,
+		"metadata": { "wavemod": { "prelude": true } },
+		"outputs": [],
+		"source": notebook_lines(PRELUDE_CELL),
+	})];
+	for node in nodes {
+		let cell = match node.script() {
+			Some(Script::Python(source)) => {
+				let calls_build = source.lines().any(|line| line.starts_with("def build("));
+				let source = match calls_build {
+					true => format!("{}{}", source.trim_end(), BUILD_CALL),
+					false => source.clone(),
+				};
+				json!({
+					"cell_type": "code",
+					"execution_count": null,
+					"metadata": { "wavemod": { "node": node.name(), "calls_build": calls_build } },
+					"outputs": node.outputs().iter().map(output_json).collect::<Vec<_>>(),
+					"source": notebook_lines(&source),
+				})
+			}
+			_ => {
+				let text = match node.properties().get("text") {
+					Some(SchemaValue::String(text)) => text.as_str(),
+					_ => "",
+				};
+				json!({
+					"cell_type": "markdown",
+					"metadata": { "wavemod": { "node": node.name() } },
+					"source": notebook_lines(text),
+				})
+			}
+		};
+		cells.push(cell);
+	}
+
+	let notebook = json!({
+		"cells": cells,
+		"metadata": {
+			"kernelspec": {
+				"display_name": "Python 3",
+				"language": "python",
+				"name": "python3",
+			},
+			"language_info": { "name": "python" },
+		},
+		"nbformat": 4,
+		"nbformat_minor": 4,
+	});
+	Ok(serde_json::to_string_pretty(&notebook)?)
+}
+
+#[test]
+fn test_import_notebook() {
+	// A 1x1 PNG.
+	let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+	let notebook = json!({
+		"nbformat": 4,
+		"nbformat_minor": 5,
+		"metadata": {},
+		"cells": [
+			{ "cell_type": "markdown", "metadata": {}, "source": ["# Waves\n", "A sine."] },
+			{
+				"cell_type": "code",
+				"metadata": {},
+				"execution_count": 1,
+				"source": "import math\nprint(math.sin(0))",
+				"outputs": [
+					{ "output_type": "stream", "name": "stdout", "text": ["0.0\n"] },
+					{ "output_type": "display_data", "metadata": {}, "data": { "image/png": png, "text/plain": "<Figure>" } },
+				],
+			},
+			{ "cell_type": "raw", "metadata": {}, "source": "ignored" },
+			{ "cell_type": "code", "metadata": {}, "execution_count": null, "source": [], "outputs": [] },
+		],
+	});
+	let board = import_notebook(&notebook.to_string()).unwrap();
+
+	let names: Vec<_> = board
+		.root_node()
+		.subnodes()
+		.iter()
+		.map(Node::name)
+		.collect();
+	assert_eq!(names, ["note_1", "cell_2", "cell_4"]);
+	let note = board.root_node().find_node("note_1").unwrap();
+	assert_eq!(note.node_type(), Some(NOTE_TYPE));
+	assert_eq!(
+		note.properties().get("text"),
+		Some(&SchemaValue::String("# Waves\nA sine.".to_string()))
+	);
+	let cell = board.root_node().find_node("cell_2").unwrap();
+	assert_eq!(cell.node_type(), Some(CELL_TYPE));
+	assert_eq!(
+		cell.script(),
+		Some(&Script::Python(
+			"import math\nprint(math.sin(0))".to_string()
+		))
+	);
+	assert_eq!(
+		cell.properties().get("y"),
+		Some(&SchemaValue::F64(CELL_SPACING))
+	);
+	assert_eq!(cell.outputs().len(), 2);
+	assert_eq!(cell.outputs()[0], NodeOutput::Text("0.0\n".to_string()));
+	assert!(matches!(&cell.outputs()[1], NodeOutput::Png(data) if data.starts_with(b"\x89PNG")));
+	assert_eq!(
+		board.edges(),
+		[
+			("note_1".to_string(), "cell_2".to_string()),
+			("cell_2".to_string(), "cell_4".to_string()),
+		]
+	);
+
+	assert!(matches!(
+		import_notebook(r#"{"nbformat": 3, "worksheets": []}"#),
+		Err(NotebookError::Format(_))
+	));
+}
+
+#[test]
+fn test_export_notebook() {
+	let mut board = create_board();
+	board.add_node(
+		Node::new("plot")
+			.set_script(Script::Python(
+				"def build():\n    print(VALUE)\n".to_string(),
+			))
+			.with_outputs(vec![
+				NodeOutput::Text("42\n".to_string()),
+				NodeOutput::Png(vec![1, 2, 3]),
+			]),
+	);
+	board.add_node(Node::new("shader").set_script(Script::WGSL(String::new())));
+	board.add_node(Node::new("values").set_script(Script::Python("VALUE = 42\n".to_string())));
+	let mut intro = SchemaObject::new();
+	intro
+		.entries
+		.insert("text".to_string(), "Plots a value.".into());
+	board.add_node(
+		Node::new("intro")
+			.with_type(NOTE_TYPE)
+			.with_schematic(&intro),
+	);
+	assert!(board.add_edge("values", "plot"));
+	assert!(board.add_edge("intro", "values"));
+
+	let json = export_notebook(&board).unwrap();
+	let notebook: Value = serde_json::from_str(&json).unwrap();
+	let sources: Vec<_> = notebook["cells"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|cell| cell_text(&cell["source"]))
+		.collect();
+	assert_eq!(
+		sources,
+		[
+			PRELUDE_CELL,
+			"Plots a value.",
+			"VALUE = 42\n",
+			"def build():\n    print(VALUE)\n\nbuild()\n",
+		]
+	);
+
+	// Importing the export gives the nodes back, in cell order.
+	let imported = import_notebook(&json).unwrap();
+	let names: Vec<_> = imported
+		.root_node()
+		.subnodes()
+		.iter()
+		.map(Node::name)
+		.collect();
+	assert_eq!(names, ["intro", "values", "plot"]);
+	let plot = imported.root_node().find_node("plot").unwrap();
+	assert_eq!(
+		plot.script(),
+		Some(&Script::Python(
+			"def build():\n    print(VALUE)\n".to_string()
+		))
+	);
+	assert_eq!(
+		plot.outputs(),
+		board.root_node().find_node("plot").unwrap().outputs()
+	);
+
+	assert!(board.add_edge("plot", "intro"));
+	assert!(
+		matches!(export_notebook(&board), Err(NotebookError::Cycle(nodes)) if nodes.len() == 3)
+	);
+}
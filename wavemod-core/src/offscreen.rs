@@ -0,0 +1,303 @@
+//! Headless rendering, into a texture instead of a window surface.
+//!
+//! Renderers and shader pipelines draw into an [`OffscreenTarget`] the same way they draw
+//! into a swapchain frame, then the pixels are read back as RGBA for tests and exports. The
+//! shaders of a [`crate::graphics::GraphicsHandle`] draw without a window once created by
+//! [`crate::graphics::create_headless_graphics`].
+
+use crate::graphics::Shader;
+use crate::Renderer;
+
+/// wgpu requires the rows of texture copies to buffers to be aligned on this many bytes.
+const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+/// Texture standing in for a surface, with the configuration renderers expect from one.
+pub struct OffscreenTarget {
+	texture: wgpu::Texture,
+	config: wgpu::SurfaceConfiguration,
+}
+
+impl OffscreenTarget {
+	/// Creates an RGBA target of `width` x `height` pixels, sRGB when `srgb` is set.
+	pub fn new(device: &wgpu::Device, width: u32, height: u32, srgb: bool) -> Self {
+		let format = if srgb {
+			wgpu::TextureFormat::Rgba8UnormSrgb
+		} else {
+			wgpu::TextureFormat::Rgba8Unorm
+		};
+		Self::with_format(device, width, height, format)
+	}
+
+	/// Creates a target of a surface format, an 8 bits per channel RGBA or BGRA one.
+	pub fn with_format(
+		device: &wgpu::Device,
+		width: u32,
+		height: u32,
+		format: wgpu::TextureFormat,
+	) -> Self {
+		let config = wgpu::SurfaceConfiguration {
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+			format,
+			width,
+			height,
+			present_mode: wgpu::PresentMode::Fifo,
+			desired_maximum_frame_latency: 2,
+			alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+			view_formats: vec![format],
+		};
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Offscreen Target"),
+			size: wgpu::Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: config.usage,
+			view_formats: &[],
+		});
+		OffscreenTarget { texture, config }
+	}
+
+	/// Configuration to give to [`Renderer::init`] and [`Renderer::resize`].
+	pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+		&self.config
+	}
+
+	pub fn size(&self) -> (u32, u32) {
+		(self.config.width, self.config.height)
+	}
+
+	pub fn format(&self) -> wgpu::TextureFormat {
+		self.config.format
+	}
+
+	pub fn view(&self) -> wgpu::TextureView {
+		self.texture
+			.create_view(&wgpu::TextureViewDescriptor::default())
+	}
+
+	/// Creates a renderer for the target and draws one frame with it.
+	pub fn render_new<R: Renderer>(&self, context: &crate::IADQContext) -> R {
+		let mut renderer = R::init(
+			&self.config,
+			&context.adapter,
+			&context.device,
+			&context.queue,
+		);
+		self.render(&mut renderer, &context.device, &context.queue);
+		renderer
+	}
+
+	pub fn render<R: Renderer>(
+		&self,
+		renderer: &mut R,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+	) {
+		renderer.render(&self.view(), device, queue);
+	}
+
+	/// Draws a shader pipeline over the cleared target. The pipeline must have been created
+	/// for [`OffscreenTarget::format`].
+	pub fn render_shader(&self, shader: &Shader, device: &wgpu::Device, queue: &wgpu::Queue) {
+		let (width, height) = self.size();
+		let size = (width as f32, height as f32);
+		shader.set_surface_size(queue, size);
+		crate::shadertoy::ShaderToyInputs {
+			resolution: size,
+			time: shader.ellapsed(),
+			date: crate::shadertoy::date_now(),
+			..Default::default()
+		}
+		.write(shader, queue);
+		shader.render(&self.view(), device, queue);
+	}
+
+	/// Reads the target back, as tightly packed RGBA rows from the top, whatever the channel
+	/// order of its format.
+	pub fn read_rgba(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
+		let (width, height) = self.size();
+		let row_size = width * 4;
+		let padded_row_size = row_size.div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT;
+
+		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Offscreen Readback"),
+			size: (padded_row_size * height) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Offscreen Readback"),
+		});
+		encoder.copy_texture_to_buffer(
+			self.texture.as_image_copy(),
+			wgpu::TexelCopyBufferInfo {
+				buffer: &buffer,
+				layout: wgpu::TexelCopyBufferLayout {
+					offset: 0,
+					bytes_per_row: Some(padded_row_size),
+					rows_per_image: Some(height),
+				},
+			},
+			self.texture.size(),
+		);
+		queue.submit(std::iter::once(encoder.finish()));
+
+		let slice = buffer.slice(..);
+		slice.map_async(wgpu::MapMode::Read, |result| {
+			result.expect("Cannot map the offscreen readback buffer")
+		});
+		device.poll(wgpu::Maintain::Wait);
+
+		let padded = slice.get_mapped_range();
+		let mut pixels: Vec<u8> = padded
+			.chunks(padded_row_size as usize)
+			.flat_map(|row| &row[..row_size as usize])
+			.copied()
+			.collect();
+		drop(padded);
+		buffer.unmap();
+		if matches!(
+			self.format(),
+			wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+		) {
+			pixels.chunks_mut(4).for_each(|pixel| pixel.swap(0, 2));
+		}
+		pixels
+	}
+
+	/// Reads the target back and writes it to a PNG file.
+	pub fn save_png(&self, device: &wgpu::Device, queue: &wgpu::Queue, path: String) {
+		let (width, height) = self.size();
+		crate::utils::output_image_native(
+			self.read_rgba(device, queue),
+			(width as usize, height as usize),
+			path,
+		);
+	}
+}
+
+/// Renderer requirements of shader pipelines, which draw no frame themselves.
+pub(crate) struct ShaderRenderer;
+
+impl Renderer for ShaderRenderer {
+	fn init(
+		_config: &wgpu::SurfaceConfiguration,
+		_adapter: &wgpu::Adapter,
+		_device: &wgpu::Device,
+		_queue: &wgpu::Queue,
+	) -> Self {
+		ShaderRenderer
+	}
+
+	fn resize(
+		&mut self,
+		_config: &wgpu::SurfaceConfiguration,
+		_device: &wgpu::Device,
+		_queue: &wgpu::Queue,
+	) {
+	}
+
+	fn update(&mut self, _event: winit::event::WindowEvent) {}
+
+	fn render(&mut self, _view: &wgpu::TextureView, _device: &wgpu::Device, _queue: &wgpu::Queue) {}
+}
+
+/// Clears the target to a color, as a minimal [`Renderer`].
+#[cfg(test)]
+struct ClearRenderer(wgpu::Color);
+
+#[cfg(test)]
+impl Renderer for ClearRenderer {
+	fn init(
+		_config: &wgpu::SurfaceConfiguration,
+		_adapter: &wgpu::Adapter,
+		_device: &wgpu::Device,
+		_queue: &wgpu::Queue,
+	) -> Self {
+		ClearRenderer(wgpu::Color::GREEN)
+	}
+
+	fn resize(
+		&mut self,
+		_config: &wgpu::SurfaceConfiguration,
+		_device: &wgpu::Device,
+		_queue: &wgpu::Queue,
+	) {
+	}
+
+	fn update(&mut self, _event: winit::event::WindowEvent) {}
+
+	fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+		let mut encoder =
+			device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+		encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: None,
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(self.0),
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		queue.submit(std::iter::once(encoder.finish()));
+	}
+}
+
+#[test]
+fn test_offscreen_render() {
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<ClearRenderer>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("offscreen rendering test", &error);
+			return;
+		}
+	};
+	let (device, queue) = (&context.device, &context.queue);
+
+	// A width whose rows need padding in the readback buffer.
+	let target = OffscreenTarget::new(device, 30, 20, false);
+	target.render_new::<ClearRenderer>(&context);
+	let pixels = target.read_rgba(device, queue);
+	assert_eq!(pixels.len(), 30 * 20 * 4);
+	assert!(pixels.chunks(4).all(|pixel| pixel == [0, 255, 0, 255]));
+
+	let shader = crate::graphics::create_shader_pipeline(
+		device,
+		target.format(),
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0, 0.0, 0.0, 1.0);\n}\n",
+	)
+	.unwrap();
+	target.render_shader(&shader, device, queue);
+	let pixels = target.read_rgba(device, queue);
+	let pixel = |x: usize, y: usize| &pixels[(y * 30 + x) * 4..][..4];
+	// The canvas triangle covers the center, the corners are cleared.
+	assert_eq!(pixel(15, 10), [255, 0, 0, 255]);
+	assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+
+	// Surfaces are often BGRA, read back as RGBA all the same.
+	let bgra = OffscreenTarget::with_format(device, 30, 20, wgpu::TextureFormat::Bgra8Unorm);
+	let shader = crate::graphics::create_shader_pipeline(
+		device,
+		bgra.format(),
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0, 0.0, 0.0, 1.0);\n}\n",
+	)
+	.unwrap();
+	bgra.render_shader(&shader, device, queue);
+	assert_eq!(
+		&bgra.read_rgba(device, queue)[(10 * 30 + 15) * 4..][..4],
+		[255, 0, 0, 255]
+	);
+
+	let path = std::env::temp_dir().join("wavemod_offscreen_test.png");
+	target.save_png(device, queue, path.to_string_lossy().into_owned());
+	assert!(std::fs::metadata(&path).unwrap().len() > 0);
+	let _ = std::fs::remove_file(path);
+}
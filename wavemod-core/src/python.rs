@@ -1,61 +1,1289 @@
 #![allow(unused)]
 
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use pyo3::exceptions::{PyRuntimeError, PySyntaxError};
 use pyo3::prelude::*;
-use pyo3::types::{PyList, PyModule, PyNone, PyTuple};
+use pyo3::types::{PyDict, PyList, PyModule, PyNone, PyTuple};
 use pyo3_ffi::c_str;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{
+	source_text, BoardState, Node, NodeEvent, PointerEvent, Script, ScriptError, ScriptFrame,
+	CELL_TYPE,
+};
+use crate::schema::{SchemaObject, SchemaValue};
+
+mod debug;
+mod gpu;
+mod profile;
+mod requirements;
+mod stubs;
+mod types;
+mod worker;
+
+use debug::Breakpoints;
+pub use debug::{py_debugger, DebugCommand, DebugPause, PyDebugger};
+pub use profile::{py_profiler, NodeProfile, PyProfiler};
+pub use requirements::UnmetRequirement;
+pub use types::{node_types, NodeType, NodeTypes};
+pub use worker::{handle_worker_arg, run_worker, WORKER_ARG};
+use worker::{NodeScript, Request, Response, Worker};
+
+/// Code prepended to every node script.
+const PRELUDE: &str = "import wavemod as wmd\n\n";
+/// Number of lines `PRELUDE` shifts the node source by.
+const PRELUDE_LINES: u32 = 2;
+
+/// Sources of the `wavemod` Python package, bundled into the binary so installed builds
+/// don't depend on the source tree being around.
+const WAVEMOD_PY: &[(&str, &str)] = &[
+	(
+		"wavemod/__init__.py",
+		include_str!("../src-py/wavemod/__init__.py"),
+	),
+	(
+		"wavemod/debugger.py",
+		include_str!("../src-py/wavemod/debugger.py"),
+	),
+	(
+		"wavemod/events.py",
+		include_str!("../src-py/wavemod/events.py"),
+	),
+	("wavemod/gpu.py", include_str!("../src-py/wavemod/gpu.py")),
+	("wavemod/node.py", include_str!("../src-py/wavemod/node.py")),
+	(
+		"wavemod/profiler.py",
+		include_str!("../src-py/wavemod/profiler.py"),
+	),
+	(
+		"wavemod/requirements.py",
+		include_str!("../src-py/wavemod/requirements.py"),
+	),
+	(
+		"wavemod/tests.py",
+		include_str!("../src-py/wavemod/tests.py"),
+	),
+	(
+		"wavemod/utils.py",
+		include_str!("../src-py/wavemod/utils.py"),
+	),
+	(
+		"wavemod/window.py",
+		include_str!("../src-py/wavemod/window.py"),
+	),
+	("wavemod/py.typed", ""),
+	("wavemod_rs.pyi", include_str!("../src-py/wavemod_rs.pyi")),
+];
+
+/// Python environment of a board, read from the root node properties.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PyEnv {
+	/// Extra import folders (`PYTHON_PATH`), highest priority first.
+	pub python_path: Vec<PathBuf>,
+	/// Working directory scripts are executed in (`cwd`).
+	pub cwd: Option<PathBuf>,
+	/// Virtualenv whose packages are made importable (`VIRTUAL_ENV`).
+	pub venv: Option<PathBuf>,
+	/// Packages the board needs, as `requirements.txt` lines (`PY_REQUIREMENTS`).
+	pub requirements: Vec<String>,
+}
+
+impl PyEnv {
+	pub fn from_props(props: &SchemaObject) -> Self {
+		let string_prop = |key: &str| match props.get(key) {
+			Some(SchemaValue::String(s)) if !s.trim().is_empty() => Some(s.trim().to_string()),
+			_ => None,
+		};
+
+		let cwd = string_prop("cwd").map(PathBuf::from);
+		let resolve = |path: PathBuf| match &cwd {
+			Some(cwd) if path.is_relative() => cwd.join(path),
+			_ => path,
+		};
+
+		// PYTHON_PATH follows the platform convention of the PYTHONPATH variable.
+		let python_path = string_prop("PYTHON_PATH")
+			.map(|paths| {
+				std::env::split_paths(&paths)
+					.filter(|p| !p.as_os_str().is_empty())
+					.map(&resolve)
+					.collect()
+			})
+			.unwrap_or_default();
+		let venv = string_prop("VIRTUAL_ENV").map(PathBuf::from).map(&resolve);
+		// One requirement per line, `#` starts a comment.
+		let requirements = string_prop("PY_REQUIREMENTS")
+			.map(|text| {
+				text.lines()
+					.map(|line| line.split('#').next().unwrap_or_default().trim())
+					.filter(|line| !line.is_empty())
+					.map(str::to_string)
+					.collect()
+			})
+			.unwrap_or_default();
+
+		PyEnv {
+			python_path,
+			cwd,
+			venv,
+			requirements,
+		}
+	}
+
+	/// `site-packages` folder of the virtualenv for the given interpreter version.
+	fn venv_site_packages(venv: &Path, version: (u8, u8)) -> PathBuf {
+		if cfg!(target_os = "windows") {
+			venv.join("Lib").join("site-packages")
+		} else {
+			venv.join("lib")
+				.join(format!("python{}.{}", version.0, version.1))
+				.join("site-packages")
+		}
+	}
+
+	/// Checks that a virtualenv was created for the interpreter `version`.
+	///
+	/// Workers embed their own interpreter instead of running the one of the virtualenv, so
+	/// its packages, compiled extensions included, only work for the same Python version.
+	fn check_venv_version(venv: &Path, version: (u8, u8)) -> Result<(), String> {
+		match PyEnv::venv_version(venv) {
+			Some(venv_version) if venv_version == version => Ok(()),
+			Some(venv_version) => Err(format!(
+				"Virtualenv '{}' targets Python {}.{}, but the embedded interpreter is {}.{}",
+				venv.display(),
+				venv_version.0,
+				venv_version.1,
+				version.0,
+				version.1
+			)),
+			None => Err(format!(
+				"Cannot read the Python version of virtualenv '{}' from its pyvenv.cfg",
+				venv.display()
+			)),
+		}
+	}
+
+	/// Python version a virtualenv was created with, as recorded in its `pyvenv.cfg`.
+	fn venv_version(venv: &Path) -> Option<(u8, u8)> {
+		let cfg = std::fs::read_to_string(venv.join("pyvenv.cfg")).ok()?;
+		let version = cfg.lines().find_map(|line| {
+			let (key, value) = line.split_once('=')?;
+			matches!(key.trim(), "version" | "version_info").then(|| value.trim().to_string())
+		})?;
+		let mut parts = version.split('.');
+		Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+	}
+}
+
+/// Writes the bundled `wavemod` package into a versioned cache folder and returns it.
+///
+/// Files are only rewritten when their content differs, so concurrent boards can share it.
+fn install_wavemod_package() -> std::io::Result<PathBuf> {
+	let root = std::env::temp_dir().join(format!("wavemod-py-{}", env!("CARGO_PKG_VERSION")));
+	for (file, source) in WAVEMOD_PY {
+		let path = root.join(file);
+		if std::fs::read_to_string(&path).is_ok_and(|current| current == *source) {
+			continue;
+		}
+		std::fs::create_dir_all(path.parent().unwrap())?;
+		std::fs::write(&path, source)?;
+	}
+	Ok(root)
+}
+
+/// Prepends `folder` to `sys.path` unless it is already there.
+fn add_sys_path(path: &Bound<'_, PyList>, folder: &Path) -> PyResult<()> {
+	let folder = folder.to_string_lossy();
+	if !path.contains(folder.as_ref())? {
+		path.insert(0, folder.as_ref())?;
+	}
+	Ok(())
+}
+
+/// Makes the board environment importable: bundled package, virtualenv, then `PYTHON_PATH`.
+fn setup_sys_path(py: Python<'_>, env: &PyEnv) -> PyResult<()> {
+	let sys = py.import("sys")?;
+	let path: Bound<'_, PyList> = sys.getattr("path")?.downcast_into()?;
+
+	let wavemod_py_folder = install_wavemod_package().map_err(|e| {
+		PyRuntimeError::new_err(format!("Cannot install the wavemod package: {}", e))
+	})?;
+	add_sys_path(&path, &wavemod_py_folder)?;
+
+	if let Some(venv) = &env.venv {
+		let version_info = sys.getattr("version_info")?;
+		let version: (u8, u8) = (
+			version_info.getattr("major")?.extract()?,
+			version_info.getattr("minor")?.extract()?,
+		);
+		PyEnv::check_venv_version(venv, version).map_err(PyRuntimeError::new_err)?;
+		// addsitedir also processes the .pth files installed by packages.
+		let site_packages = PyEnv::venv_site_packages(venv, version);
+		if !site_packages.is_dir() {
+			return Err(PyRuntimeError::new_err(format!(
+				"Cannot find virtualenv packages in '{}'",
+				site_packages.display()
+			)));
+		}
+		py.import("site")?
+			.call_method1("addsitedir", (site_packages.to_string_lossy(),))?;
+	}
+
+	// Iterate in reverse so the first PYTHON_PATH entry ends up first in sys.path.
+	for folder in env.python_path.iter().rev() {
+		if !folder.is_dir() {
+			log::warn!("PYTHON_PATH entry '{}' is not a folder", folder.display());
+		}
+		add_sys_path(&path, folder)?;
+	}
+
+	Ok(())
+}
 
+/// Restores the previous working directory when dropped.
+///
+/// The working directory is shared by the whole process, so it is only entered in Python
+/// workers, which run the scripts of a single board.
+struct CwdGuard(Option<PathBuf>);
+
+impl CwdGuard {
+	fn enter(cwd: Option<&Path>) -> PyResult<Self> {
+		let Some(cwd) = cwd else {
+			return Ok(CwdGuard(None));
+		};
+		if !worker::in_worker() {
+			return Err(PyRuntimeError::new_err(
+				"The working directory of a board is only entered in Python workers",
+			));
+		}
+		let previous = std::env::current_dir()?;
+		std::env::set_current_dir(cwd).map_err(|e| {
+			PyRuntimeError::new_err(format!("Cannot enter cwd '{}': {}", cwd.display(), e))
+		})?;
+		Ok(CwdGuard(Some(previous)))
+	}
+}
+
+impl Drop for CwdGuard {
+	fn drop(&mut self) {
+		if let Some(previous) = &self.0 {
+			let _ = std::env::set_current_dir(previous);
+		}
+	}
+}
+
+/// Prints a message, to check the module is reachable from Python.
 #[pyfunction]
-fn pytest() {
-	println!("wavemod_rs.test");
+#[pyo3(text_signature = "() -> None")]
+fn pytest(py: Python<'_>) -> PyResult<()> {
+	// Through `print`, as the stdout of Python workers is reserved to the app.
+	py.import("builtins")?
+		.call_method1("print", ("wavemod_rs.test",))?;
+	Ok(())
+}
+
+/// Maps a line of the executed code back to the node source, `None` inside the prelude.
+fn source_line(line: u32) -> Option<u32> {
+	line.checked_sub(PRELUDE_LINES).filter(|line| *line > 0)
 }
 
-pub fn execute_py(script_name: &str, py_code: &str) -> PyResult<()> {
+/// Converts a Python exception raised by the node `script_name` into a [`ScriptError`].
+fn script_error(py: Python<'_>, err: &PyErr, script_name: &str, source: &str) -> ScriptError {
+	let value = err.value(py);
+	let ty = value.get_type();
+	let kind = match (ty.module(), ty.qualname()) {
+		(Ok(module), Ok(name)) if module != "builtins" => {
+			format!("{}.{}", module, name)
+		}
+		(_, Ok(name)) => name.to_string(),
+		_ => "Exception".to_string(),
+	};
+
+	let message = value.str().map(|s| s.to_string()).unwrap_or_default();
+	let mut error = ScriptError::new(&kind, message);
+
+	let frames = err.traceback(py).and_then(|traceback| {
+		py.import("traceback")
+			.and_then(|module| module.call_method1("extract_tb", (traceback,)))
+			.and_then(|frames| frames.try_iter()?.collect::<PyResult<Vec<_>>>())
+			.ok()
+	});
+	for frame in frames.unwrap_or_default() {
+		let file: String = frame
+			.getattr("filename")
+			.and_then(|f| f.extract())
+			.unwrap_or_default();
+		let line: Option<u32> = frame
+			.getattr("lineno")
+			.and_then(|l| l.extract())
+			.ok()
+			.flatten();
+		let in_node = file == script_name;
+
+		let frame_line = if in_node {
+			line.and_then(source_line)
+		} else {
+			line
+		};
+		let text = match frame_line {
+			Some(line) if in_node => source_text(source, line),
+			_ => frame
+				.getattr("line")
+				.and_then(|l| l.extract())
+				.ok()
+				.flatten(),
+		};
+		if in_node && frame_line.is_some() {
+			// Python 3.11+ reports the 0-based, end-exclusive column range of the failing expression.
+			let column = |attr: &str| -> Option<u32> {
+				frame.getattr(attr).and_then(|c| c.extract()).ok().flatten()
+			};
+			error.line = frame_line;
+			error.columns = column("colno")
+				.zip(column("end_colno"))
+				.map(|(s, e)| (s + 1, e + 1));
+			error.text = text.clone();
+		}
+
+		error.frames.push(ScriptFrame {
+			file,
+			function: frame
+				.getattr("name")
+				.and_then(|n| n.extract())
+				.unwrap_or_default(),
+			line: frame_line,
+			text,
+		});
+	}
+
+	// Syntax errors happen before any frame of the node exists, the location is on the exception.
+	if err.is_instance_of::<PySyntaxError>(py) {
+		let attr = |name: &str| -> Option<u32> {
+			value.getattr(name).and_then(|a| a.extract()).ok().flatten()
+		};
+		let file: Option<String> = value.getattr("filename").and_then(|f| f.extract()).ok();
+		if file.as_deref() == Some(script_name) {
+			error.line = attr("lineno").and_then(source_line);
+			error.columns = attr("offset").zip(attr("end_offset"));
+			error.text = error.line.and_then(|line| source_text(source, line));
+			if let Ok(msg) = value.getattr("msg").and_then(|m| m.extract()) {
+				error.message = msg;
+			}
+		}
+	}
+
+	error
+}
+
+/// Starts a Python worker.
+fn spawn_worker() -> Result<Worker, Box<ScriptError>> {
+	Worker::spawn().map_err(|e| {
+		ScriptError::new(
+			"WorkerError",
+			format!("Cannot start the Python worker: {}", e),
+		)
+		.into()
+	})
+}
+
+/// Idle workers of [`execute_py`], with the environment they were set up for.
+static EXECUTE_WORKERS: Mutex<Vec<(PyEnv, Worker)>> = Mutex::new(Vec::new());
+
+/// Runs a node script and its `build()` in a Python worker set up for `env`, dropping the
+/// pipelines its previous run created.
+///
+/// Workers are kept for the next scripts, and shared by the boards with the same environment:
+/// each script runs in a module of its own, but the modules it imports and the rest of the
+/// interpreter state carry over. A worker runs one script at a time, concurrent calls take
+/// another idle worker or start one. Workers that failed are dropped.
+pub fn execute_py(env: &PyEnv, script_name: &str, py_code: &str) -> Result<(), Box<ScriptError>> {
+	// Taken out of the pool while the script runs, so other calls are not blocked by it.
+	let idle = {
+		let mut workers = EXECUTE_WORKERS.lock().unwrap();
+		let index = workers.iter().position(|(worker_env, _)| worker_env == env);
+		index.map(|index| workers.swap_remove(index).1)
+	};
+	let mut worker = match idle {
+		Some(worker) => worker,
+		None => spawn_worker()?,
+	};
+	crate::graphics::node_graphics()
+		.lock()
+		.unwrap()
+		.remove_pipelines(script_name);
+	let request = Request::Execute {
+		env: env.clone(),
+		name: script_name.to_string(),
+		source: py_code.to_string(),
+	};
+	let response = worker.request(&request, LOAD_TIMEOUT)?;
+	EXECUTE_WORKERS.lock().unwrap().push((env.clone(), worker));
+	match response {
+		Response::Done { mut errors, .. } => {
+			errors.pop().map_or(Ok(()), |(_, error)| Err(error.into()))
+		}
+		response => Err(worker::unexpected(response).into()),
+	}
+}
+
+/// Runs a node script in this process, inside of a worker.
+fn execute_local(env: &PyEnv, script_name: &str, py_code: &str) -> Result<(), Box<ScriptError>> {
 	Python::with_gil(|py| {
-		// Load sys module
-		let sys = py.import("sys")?;
-		let sys_modules = sys.getattr("modules")?;
-		let path: Bound<'_, PyList> = sys.getattr("path")?.downcast_into()?;
-
-		// Load wavemod_rs module
-		let wavemod_rs = PyModule::new(py, "wavemod_rs")?;
-		wavemod_rs.add_function(wrap_pyfunction!(pytest, &wavemod_rs)?)?;
-		sys_modules.set_item("wavemod_rs", wavemod_rs)?;
-
-		// Load wavemod_py path
-		let crate_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
-		let wavemod_py_folder = crate_root
-			.join("src-py/")
-			.canonicalize()
-			.expect("Cannot find wavemod_py folder");
-		path.insert(0, wavemod_py_folder.to_str().unwrap())?;
-
-		// Load wavemod_plugin code
-		let script_name_cstr = std::ffi::CString::new(script_name).unwrap();
-		let py_code_cstr =
-			std::ffi::CString::new(format!("import wavemod as wmd\n\n{}", py_code)).unwrap();
-		let wavemod_plugin = PyModule::from_code(
-			py,
-			&py_code_cstr,
-			&script_name_cstr,
-			c_str!("wavemod_plugins"),
-		)?;
-
-		// Call wavemod_plugin built-in
-		let func = wavemod_plugin.getattr("build")?;
-		func.call0()?.downcast::<PyNone>()?;
-
-		// Call pip package example
-		let numpy = PyModule::import(py, "numpy")?.getattr("array")?;
-		let array = numpy.call1((PyTuple::new(py, &[1, 2, 3, 4])?,))?;
-		println!("User's numpy array: {}", array);
-
-		Ok(())
+		run_py(py, env, script_name, py_code)
+			.map_err(|err| script_error(py, &err, script_name, py_code).into())
 	})
 }
 
+/// Loads a Python module in a worker of its own, and registers the node types it defines.
+pub fn load_node_types(env: &PyEnv, module: &str) -> Result<Vec<NodeType>, Box<ScriptError>> {
+	let mut worker = spawn_worker()?;
+	let request = Request::NodeTypes {
+		env: env.clone(),
+		module: module.to_string(),
+	};
+	let types = match worker.request(&request, LOAD_TIMEOUT)? {
+		Response::NodeTypes(types) => types?,
+		response => return Err(worker::unexpected(response).into()),
+	};
+	node_types()
+		.lock()
+		.unwrap()
+		.register_module(module, types.clone());
+	Ok(types)
+}
+
+/// Describes the node types of a module in this process, inside of a worker.
+fn describe_node_types(env: &PyEnv, module: &str) -> Result<Vec<NodeType>, Box<ScriptError>> {
+	Python::with_gil(|py| {
+		setup_interpreter(py, env)
+			.and_then(|_| {
+				let _cwd = CwdGuard::enter(env.cwd.as_deref())?;
+				types::describe(py, module)
+			})
+			.map_err(|err| script_error(py, &err, module, "").into())
+	})
+}
+
+fn run_py(py: Python<'_>, env: &PyEnv, script_name: &str, py_code: &str) -> PyResult<()> {
+	load_module(py, env, script_name, py_code, &SchemaObject::new(), None)?;
+	Ok(())
+}
+
+/// Checks the requirements of a board in a Python worker, returning the ones its
+/// interpreter does not satisfy.
+pub fn check_requirements(env: &PyEnv) -> Result<Vec<UnmetRequirement>, Box<ScriptError>> {
+	if env.requirements.is_empty() {
+		return Ok(Vec::new());
+	}
+	let mut worker = spawn_worker()?;
+	let request = Request::Requirements { env: env.clone() };
+	match worker.request(&request, LOAD_TIMEOUT)? {
+		Response::Requirements(unmet) => unmet,
+		response => Err(worker::unexpected(response).into()),
+	}
+}
+
+/// Installs the requirements of a board into its virtualenv, from a folder of wheels.
+pub fn install_requirements(env: &PyEnv, wheels: &Path) -> Result<(), Box<ScriptError>> {
+	requirements::install(env, wheels)
+}
+
+/// Checks the requirements of a board in this process, inside of a worker.
+fn unmet_requirements(env: &PyEnv) -> Result<Vec<UnmetRequirement>, Box<ScriptError>> {
+	if env.requirements.is_empty() {
+		return Ok(Vec::new());
+	}
+	Python::with_gil(|py| {
+		setup_interpreter(py, env)
+			.and_then(|_| requirements::check(py, &env.requirements))
+			.map_err(|err| script_error(py, &err, "PY_REQUIREMENTS", "").into())
+	})
+}
+
+/// Native module the `wavemod` package is built on.
+fn wavemod_rs_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+	let wavemod_rs = PyModule::new(py, "wavemod_rs")?;
+	wavemod_rs.add_function(wrap_pyfunction!(pytest, &wavemod_rs)?)?;
+	gpu::register(&wavemod_rs)?;
+	debug::register(&wavemod_rs)?;
+	Ok(wavemod_rs)
+}
+
+/// Registers `wavemod_rs`, then runs the node script and its `build()`.
+///
+/// The node properties are available to the script as `wmd.CURRENT_PROPERTIES`. Notebook
+/// cells run in the `notebook` namespace instead, without `build()`.
+fn load_module<'py>(
+	py: Python<'py>,
+	env: &PyEnv,
+	script_name: &str,
+	py_code: &str,
+	properties: &SchemaObject,
+	notebook: Option<&Bound<'py, PyModule>>,
+) -> PyResult<Bound<'py, PyModule>> {
+	setup_interpreter(py, env)?;
+	let _cwd = CwdGuard::enter(env.cwd.as_deref())?;
+	let wavemod = py.import("wavemod")?;
+	wavemod.setattr("CURRENT_NODE", script_name)?;
+	wavemod.setattr(
+		"CURRENT_PROPERTIES",
+		schema_to_py(py, &SchemaValue::SchemaObject(properties.clone()))?,
+	)?;
+
+	if let Some(notebook) = notebook {
+		let builtins = py.import("builtins")?;
+		let code = builtins.call_method1("compile", (node_code(py_code), script_name, "exec"))?;
+		builtins.call_method1("exec", (code, notebook.dict()))?;
+		return Ok(notebook.clone());
+	}
+
+	// Load wavemod_plugin code, in a new module as importing reuses the module of that name
+	py.import("sys")?
+		.getattr("modules")?
+		.call_method1("pop", ("wavemod_plugins", py.None()))?;
+	let script_name_cstr = std::ffi::CString::new(script_name)?;
+	let py_code_cstr = std::ffi::CString::new(node_code(py_code))?;
+	let wavemod_plugin = PyModule::from_code(
+		py,
+		&py_code_cstr,
+		&script_name_cstr,
+		c_str!("wavemod_plugins"),
+	)?;
+
+	// Call wavemod_plugin built-in
+	let func = wavemod_plugin.getattr("build")?;
+	call_node(py, script_name, func, [])?.downcast::<PyNone>()?;
+
+	Ok(wavemod_plugin)
+}
+
+/// Registers `wavemod_rs` and makes the board packages importable.
+fn setup_interpreter(py: Python<'_>, env: &PyEnv) -> PyResult<()> {
+	// Load sys module
+	let sys = py.import("sys")?;
+	let sys_modules = sys.getattr("modules")?;
+
+	// Load wavemod_rs module
+	sys_modules.set_item("wavemod_rs", wavemod_rs_module(py)?)?;
+
+	// Load wavemod_py and board paths
+	setup_sys_path(py, env)
+}
+
+/// Code executed for a node script.
+fn node_code(py_code: &str) -> String {
+	format!("{}{}", PRELUDE, py_code)
+}
+
+/// `wavemod.debugger.DEBUGGER`, which build functions and handlers are called through.
+fn debugger(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+	py.import("wavemod.debugger")?.getattr("DEBUGGER")
+}
+
+/// `wavemod.profiler.PROFILER`, timing build functions and handlers.
+fn profiler(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+	py.import("wavemod.profiler")?.getattr("PROFILER")
+}
+
+/// Calls the build function or a handler of `node`, through the debugger and the profiler.
+fn call_node<'py>(
+	py: Python<'py>,
+	node: &str,
+	function: Bound<'py, PyAny>,
+	args: impl IntoIterator<Item = Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+	let mut call_args = vec![
+		profiler(py)?.getattr("call")?,
+		node.into_pyobject(py)?.into_any(),
+		function,
+	];
+	call_args.extend(args);
+	debugger(py)?.call_method1("call", PyTuple::new(py, call_args)?)
+}
+
+/// Turns the collection of call stacks on or off, in a worker.
+fn set_profiling(py: Python<'_>, enabled: bool) -> PyResult<()> {
+	let profiler = profiler(py)?;
+	profiler.setattr("line_offset", PRELUDE_LINES)?;
+	profiler.setattr("enabled", enabled)
+}
+
+/// Times of the nodes since the last call, in a worker.
+fn take_profiles() -> Vec<NodeProfile> {
+	let reports = Python::with_gil(|py| {
+		profiler(py)?.call_method0("take")?.extract::<Vec<(
+			String,
+			u64,
+			f64,
+			std::collections::BTreeMap<String, f64>,
+		)>>()
+	});
+	match reports {
+		Ok(reports) => reports
+			.into_iter()
+			.map(|(node, calls, seconds, stacks)| NodeProfile {
+				node,
+				calls,
+				seconds,
+				stacks,
+			})
+			.collect(),
+		Err(e) => {
+			log::error!("Cannot read the Python profiler: {}", e);
+			Vec::new()
+		}
+	}
+}
+
+/// Module-level functions a node script can define to receive `NodeEvent`s.
+const HANDLERS: [&str; 4] = ["on_frame", "on_resize", "on_pointer", "on_property_changed"];
+
+fn handler_name(event: &NodeEvent) -> &'static str {
+	match event {
+		NodeEvent::Frame { .. } => HANDLERS[0],
+		NodeEvent::Resize { .. } => HANDLERS[1],
+		NodeEvent::Pointer(_) => HANDLERS[2],
+		NodeEvent::PropertyChanged { .. } => HANDLERS[3],
+	}
+}
+
+fn schema_to_py<'py>(py: Python<'py>, value: &SchemaValue) -> PyResult<Bound<'py, PyAny>> {
+	Ok(match value {
+		SchemaValue::String(s) | SchemaValue::SchemaLink(s) => s.into_pyobject(py)?.into_any(),
+		SchemaValue::U64(n) => n.into_pyobject(py)?.into_any(),
+		SchemaValue::I64(n) => n.into_pyobject(py)?.into_any(),
+		SchemaValue::F64(n) => n.into_pyobject(py)?.into_any(),
+		SchemaValue::Bool(b) => b.into_pyobject(py)?.to_owned().into_any(),
+		SchemaValue::SchemaObject(object) => {
+			let dict = PyDict::new(py);
+			for (key, value) in &object.entries {
+				dict.set_item(key, schema_to_py(py, value)?)?;
+			}
+			dict.into_any()
+		}
+	})
+}
+
+/// Converts a property value of a Python node type.
+fn py_to_schema(value: &Bound<'_, PyAny>) -> PyResult<SchemaValue> {
+	// `bool` is a subclass of `int`, and is checked first.
+	if let Ok(b) = value.downcast::<pyo3::types::PyBool>() {
+		Ok(SchemaValue::Bool(b.is_true()))
+	} else if value.is_instance_of::<pyo3::types::PyInt>() {
+		value.extract().map(SchemaValue::I64)
+	} else if value.is_instance_of::<pyo3::types::PyFloat>() {
+		value.extract().map(SchemaValue::F64)
+	} else if value.is_instance_of::<pyo3::types::PyString>() {
+		value.extract().map(SchemaValue::String)
+	} else if let Ok(dict) = value.downcast::<PyDict>() {
+		let mut object = SchemaObject::new();
+		for (key, value) in dict.iter() {
+			object.entries.insert(key.extract()?, py_to_schema(&value)?);
+		}
+		Ok(SchemaValue::SchemaObject(object))
+	} else {
+		Err(pyo3::exceptions::PyTypeError::new_err(format!(
+			"unsupported value {}",
+			value.repr()?
+		)))
+	}
+}
+
+/// Time the scripts of a board have to load, imports of large packages included.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time a handler has to run before its worker is considered hung and stopped.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Request for the worker of a board, with the handler it calls.
+struct HostRequest {
+	request: Request,
+	handler: Option<&'static str>,
+}
+
+/// Python node scripts of a board, kept loaded in a worker process to receive events
+/// from the event loop.
+pub struct PyHost {
+	/// `None` when the board has no Python node, or once the worker stopped.
+	worker: Option<Worker>,
+	/// Requests waiting for the worker to answer the running one.
+	queue: VecDeque<HostRequest>,
+	/// Request the worker is running.
+	running: Option<HostRequest>,
+	/// Loaded nodes, with the handlers their script defines.
+	nodes: Vec<(String, Vec<&'static str>)>,
+	event_timeout: Duration,
+	/// Node paused by the debugger, with the handler it is paused in (`None` in `build()`).
+	paused: Option<(String, Option<&'static str>)>,
+	/// Revision of the breakpoints the worker has.
+	breakpoints: u64,
+	/// Revision of the profiling state the worker has.
+	profiling: u64,
+	/// Requirements of the board its interpreter does not satisfy.
+	requirements: Vec<UnmetRequirement>,
+}
+
+impl PyHost {
+	/// Runs the Python scripts of every node of the board in a new worker, logging the
+	/// ones that fail.
+	pub fn load_board(board: &BoardState) -> Self {
+		fn collect_scripts(node: &Node, scripts: &mut Vec<NodeScript>) {
+			if let Some(Script::Python(source)) = node.script() {
+				scripts.push(NodeScript {
+					name: node.name().to_string(),
+					source: source.clone(),
+					properties: node.properties().clone(),
+					cell: node.node_type() == Some(CELL_TYPE),
+				});
+			}
+			for subnode in node.subnodes() {
+				collect_scripts(subnode, scripts);
+			}
+		}
+
+		let mut host = PyHost {
+			worker: None,
+			queue: VecDeque::new(),
+			running: None,
+			nodes: Vec::new(),
+			event_timeout: EVENT_TIMEOUT,
+			paused: None,
+			breakpoints: 0,
+			profiling: 0,
+			requirements: Vec::new(),
+		};
+		let mut scripts = Vec::new();
+		collect_scripts(board.root_node(), &mut scripts);
+		if scripts.is_empty() {
+			return host;
+		}
+		match Worker::spawn() {
+			Ok(worker) => host.worker = Some(worker),
+			Err(e) => {
+				log::error!("Cannot start the Python worker: {}", e);
+				return host;
+			}
+		}
+
+		let breakpoints = {
+			let debugger = py_debugger().lock().unwrap();
+			host.breakpoints = debugger.revision();
+			debugger.breakpoints()
+		};
+		let profile = {
+			let profiler = py_profiler().lock().unwrap();
+			host.profiling = profiler.revision();
+			profiler.enabled()
+		};
+		let request = Request::LoadBoard {
+			env: PyEnv::from_props(board.properties()),
+			nodes: scripts,
+			breakpoints,
+			profile,
+		};
+		let mut errors = Vec::new();
+		let response = host.request(&request, LOAD_TIMEOUT);
+		host.handle_response(response, None, &mut errors);
+		for requirement in &host.requirements {
+			log::warn!("Python requirement not met: {}", requirement);
+		}
+		for (node, error) in errors {
+			log::error!("Python node '{}' failed: {}", node, error);
+		}
+		host
+	}
+
+	/// Requirements of the board its interpreter did not satisfy when the board loaded.
+	pub fn unmet_requirements(&self) -> &[UnmetRequirement] {
+		&self.requirements
+	}
+
+	/// Sends a request to the worker, which is dropped after any error.
+	fn request(
+		&mut self,
+		request: &Request,
+		timeout: Duration,
+	) -> Result<Response, Box<ScriptError>> {
+		let worker = self
+			.worker
+			.as_mut()
+			.ok_or_else(|| ScriptError::new("WorkerError", "The Python worker is not running"))?;
+		let response = worker.request(request, timeout);
+		if response.is_err() {
+			self.worker = None;
+		}
+		response
+	}
+
+	/// Applies the answer of the worker to a call of `handler`, collecting the errors of
+	/// the nodes. `handler` is not called again on the nodes it failed in.
+	fn handle_response(
+		&mut self,
+		response: Result<Response, Box<ScriptError>>,
+		handler: Option<&'static str>,
+		errors: &mut Vec<(String, ScriptError)>,
+	) {
+		match response {
+			Ok(Response::Loaded {
+				nodes,
+				errors: node_errors,
+				requirements,
+				profiles,
+			}) => {
+				add_profiles(profiles);
+				self.requirements = requirements;
+				self.nodes = nodes
+					.into_iter()
+					.map(|(name, handlers)| {
+						let handlers = HANDLERS
+							.into_iter()
+							.filter(|handler| handlers.iter().any(|h| h == handler))
+							.collect();
+						(name, handlers)
+					})
+					.collect();
+				errors.extend(node_errors);
+			}
+			Ok(Response::Done {
+				errors: node_errors,
+				profiles,
+			}) => {
+				add_profiles(profiles);
+				for (name, handlers) in &mut self.nodes {
+					if node_errors.iter().any(|(node, _)| node == name) {
+						handlers.retain(|name| Some(*name) != handler);
+					}
+				}
+				errors.extend(node_errors);
+			}
+			Ok(Response::Paused(pause)) => {
+				// Events are not delivered while a node is paused.
+				self.queue
+					.retain(|queued| !matches!(queued.request, Request::Dispatch { .. }));
+				self.paused = Some((pause.node.clone(), handler));
+				py_debugger().lock().unwrap().pause(pause);
+			}
+			response => {
+				let error = response.map_or_else(|error| *error, worker::unexpected);
+				log::error!("Python worker failed: {}", error);
+				self.worker = None;
+				self.queue.clear();
+				self.running = None;
+				if let Some((node, _)) = self.paused.take() {
+					py_debugger().lock().unwrap().forget(&node);
+				}
+				errors.extend(self.nodes.drain(..).map(|(name, _)| (name, error.clone())));
+			}
+		}
+	}
+
+	/// Resumes the paused node once the debugger has a command for it.
+	///
+	/// Returns whether the node is still paused.
+	fn resume(&mut self, errors: &mut Vec<(String, ScriptError)>) -> bool {
+		let Some((node, handler)) = self.paused.clone() else {
+			return false;
+		};
+		let (command, breakpoints) = {
+			let mut debugger = py_debugger().lock().unwrap();
+			let Some(command) = debugger.take_command(&node) else {
+				return true;
+			};
+			let breakpoints = (debugger.revision() != self.breakpoints).then(|| {
+				self.breakpoints = debugger.revision();
+				debugger.breakpoints()
+			});
+			(command, breakpoints)
+		};
+		self.paused = None;
+		let request = Request::Debug {
+			command,
+			breakpoints,
+		};
+		self.post(HostRequest { request, handler }, errors);
+		false
+	}
+
+	/// Sends a request to the worker, which is dropped after any error.
+	fn post(&mut self, next: HostRequest, errors: &mut Vec<(String, ScriptError)>) {
+		let Some(worker) = &mut self.worker else {
+			return;
+		};
+		match worker.post(&next.request, self.event_timeout) {
+			Ok(()) => self.running = Some(next),
+			Err(error) => self.handle_response(Err(error), next.handler, errors),
+		}
+	}
+
+	/// Collects the answer of the worker to the running request when it arrives within
+	/// `wait`, then sends the next queued request.
+	///
+	/// Returns once the worker is busy past `wait`, is done with the queue, is paused by the
+	/// debugger or stopped.
+	fn pump(&mut self, wait: Duration, errors: &mut Vec<(String, ScriptError)>) {
+		loop {
+			if self.resume(errors) {
+				return;
+			}
+			if let Some(running) = &self.running {
+				let handler = running.handler;
+				let Some(worker) = &mut self.worker else {
+					return;
+				};
+				let response = match worker.poll(wait) {
+					Ok(None) => return,
+					Ok(Some(response)) => Ok(response),
+					Err(error) => Err(error),
+				};
+				self.running = None;
+				self.handle_response(response, handler, errors);
+				continue;
+			}
+			match self.queue.pop_front() {
+				Some(next) => self.post(next, errors),
+				None => return,
+			}
+		}
+	}
+
+	/// Queues the call of `handler` on `node`, merging frames the worker did not start yet.
+	fn enqueue(&mut self, node: Option<String>, event: NodeEvent, handler: &'static str) {
+		if let NodeEvent::Frame { dt } = event {
+			let queued = self
+				.queue
+				.iter_mut()
+				.find_map(|queued| match &mut queued.request {
+					Request::Dispatch {
+						node: queued_node,
+						event: NodeEvent::Frame { dt },
+					} if *queued_node == node => Some(dt),
+					_ => None,
+				});
+			if let Some(queued_dt) = queued {
+				*queued_dt += dt;
+				return;
+			}
+		}
+		self.queue.push_back(HostRequest {
+			request: Request::Dispatch { node, event },
+			handler: Some(handler),
+		});
+	}
+
+	/// Sends the handler calls of `event` of `node`, or of every node when `None`, to the
+	/// worker without waiting for them.
+	///
+	/// Returns the errors raised by the handlers of earlier events, which are not called
+	/// again afterwards. Frames the worker is not ready for yet are merged into one. When
+	/// the worker crashes or hangs, every node reports the error and stops receiving events.
+	/// While a node is paused by the debugger, events are not delivered to the board.
+	pub fn dispatch(
+		&mut self,
+		node: Option<&str>,
+		event: &NodeEvent,
+	) -> Vec<(String, ScriptError)> {
+		let mut errors = Vec::new();
+		self.pump(Duration::ZERO, &mut errors);
+		if self.worker.is_none() || self.paused.is_some() {
+			return errors;
+		}
+
+		let revision = py_debugger().lock().unwrap().revision();
+		if revision != self.breakpoints {
+			self.breakpoints = revision;
+			let breakpoints = py_debugger().lock().unwrap().breakpoints();
+			self.queue.push_back(HostRequest {
+				request: Request::Breakpoints(breakpoints),
+				handler: None,
+			});
+		}
+
+		let (revision, enabled) = {
+			let profiler = py_profiler().lock().unwrap();
+			(profiler.revision(), profiler.enabled())
+		};
+		if revision != self.profiling {
+			self.profiling = revision;
+			self.queue.push_back(HostRequest {
+				request: Request::Profile(enabled),
+				handler: None,
+			});
+		}
+
+		let handler = handler_name(event);
+		let targets: Vec<&str> = self
+			.nodes
+			.iter()
+			.filter(|(name, handlers)| {
+				node.is_none_or(|node| node == name) && handlers.contains(&handler)
+			})
+			.map(|(name, _)| name.as_str())
+			.collect();
+
+		let requests = match event {
+			// Pointer events only reach nodes drawing under the pointer, in node coordinates.
+			NodeEvent::Pointer(pointer) => targets
+				.into_iter()
+				.filter_map(|name| {
+					let (x, y) = crate::graphics::node_graphics()
+						.lock()
+						.unwrap()
+						.node_pointer(name, pointer)?;
+					let pointer = PointerEvent {
+						x,
+						y,
+						..pointer.clone()
+					};
+					Some((Some(name.to_string()), NodeEvent::Pointer(pointer)))
+				})
+				.collect(),
+			_ if targets.is_empty() => Vec::new(),
+			_ => vec![(node.map(str::to_string), event.clone())],
+		};
+
+		for (node, event) in requests {
+			self.enqueue(node, event, handler);
+		}
+		self.pump(Duration::ZERO, &mut errors);
+		errors
+	}
+
+	/// Waits for the worker to run the events sent to the board, returning the errors of
+	/// their handlers.
+	///
+	/// Returns early while a node is paused by the debugger.
+	pub fn flush(&mut self) -> Vec<(String, ScriptError)> {
+		let mut errors = Vec::new();
+		self.pump(Duration::MAX, &mut errors);
+		errors
+	}
+}
+
+/// Adds the times measured by a worker to the node profiles.
+fn add_profiles(profiles: Vec<NodeProfile>) {
+	if profiles.is_empty() {
+		return;
+	}
+	let mut profiler = py_profiler().lock().unwrap();
+	for profile in profiles {
+		profiler.add(profile);
+	}
+}
+
+/// Python node scripts loaded in a worker.
+#[derive(Default)]
+struct PyNodes {
+	env: PyEnv,
+	nodes: Vec<PyNode>,
+}
+
+struct PyNode {
+	name: String,
+	source: String,
+	module: Py<PyModule>,
+	/// Handlers the script defines, a handler raising an exception is dropped.
+	handlers: Vec<&'static str>,
+}
+
+impl PyNodes {
+	/// Runs the node scripts, returning the errors of the ones that fail.
+	fn load(
+		&mut self,
+		env: PyEnv,
+		scripts: &[NodeScript],
+		breakpoints: Breakpoints,
+		profile: bool,
+	) -> Vec<(String, ScriptError)> {
+		self.env = env;
+		// Breakpoints need the code of the nodes, and are set before any `build()` runs, as
+		// is profiling.
+		let registered = Python::with_gil(|py| {
+			setup_interpreter(py, &self.env)?;
+			let debugger = debugger(py)?;
+			for script in scripts {
+				debugger.call_method1("add_node", (&script.name, node_code(&script.source)))?;
+			}
+			set_profiling(py, profile)
+		});
+		if let Err(e) = registered {
+			log::error!("Cannot set up the Python debugger: {}", e);
+		}
+		let mut errors = self.set_breakpoints(breakpoints);
+		// Cells of a notebook run in board order in one namespace, and receive no events.
+		let mut notebook: Option<Py<PyModule>> = None;
+		for script in scripts {
+			let NodeScript {
+				name,
+				source,
+				properties,
+				cell,
+			} = script;
+			Python::with_gil(|py| {
+				let namespace = match (&notebook, cell) {
+					(_, false) => Ok(None),
+					(Some(notebook), true) => Ok(Some(notebook.bind(py).clone())),
+					(None, true) => PyModule::new(py, "wavemod_notebook").map(|module| {
+						notebook = Some(module.clone().unbind());
+						Some(module)
+					}),
+				};
+				let loaded = namespace
+					.and_then(|namespace| {
+						load_module(py, &self.env, name, source, properties, namespace.as_ref())
+					})
+					.map(|module| PyNode {
+						name: name.clone(),
+						source: source.clone(),
+						handlers: match cell {
+							true => Vec::new(),
+							false => HANDLERS
+								.into_iter()
+								.filter(|name| module.hasattr(*name).unwrap_or(false))
+								.collect(),
+						},
+						module: module.unbind(),
+					});
+				match loaded {
+					Ok(py_node) => self.nodes.push(py_node),
+					Err(err) => errors.push((name.clone(), script_error(py, &err, name, source))),
+				}
+			});
+		}
+		errors
+	}
+
+	/// Turns the collection of call stacks on or off.
+	fn set_profiling(&self, enabled: bool) {
+		if let Err(e) = Python::with_gil(|py| set_profiling(py, enabled)) {
+			log::error!("Cannot set up the Python profiler: {}", e);
+		}
+	}
+
+	/// Replaces the breakpoints, returning the ones that are not on a line of code.
+	fn set_breakpoints(&self, breakpoints: Breakpoints) -> Vec<(String, ScriptError)> {
+		self.breakpoint_errors("set_breakpoints", (debug::to_file_lines(breakpoints),))
+	}
+
+	/// Calls a method of the debugger returning breakpoints it ignored, as errors.
+	fn breakpoint_errors(
+		&self,
+		method: &str,
+		args: impl for<'py> IntoPyObject<'py, Target = PyTuple>,
+	) -> Vec<(String, ScriptError)> {
+		let ignored = Python::with_gil(|py| {
+			debugger(py)?
+				.call_method1(method, args)?
+				.extract::<Vec<(String, u32, String)>>()
+		});
+		match ignored {
+			Ok(ignored) => ignored
+				.into_iter()
+				.map(|(node, line, _)| {
+					let line = source_line(line);
+					let message = format!("Line {} has no code to break on", line.unwrap_or(0));
+					let mut error = ScriptError::new("BreakpointError", message);
+					error.line = line;
+					(node, error)
+				})
+				.collect(),
+			Err(e) => {
+				log::error!("Cannot set the Python breakpoints: {}", e);
+				Vec::new()
+			}
+		}
+	}
+
+	fn handlers(&self) -> Vec<(String, Vec<String>)> {
+		self.nodes
+			.iter()
+			.map(|py_node| {
+				let handlers = py_node.handlers.iter().map(|h| h.to_string()).collect();
+				(py_node.name.clone(), handlers)
+			})
+			.collect()
+	}
+
+	/// Calls the handler of `event` of `node`, or of every node when `None`.
+	///
+	/// Pointer events are already in node coordinates.
+	fn dispatch(&mut self, node: Option<&str>, event: &NodeEvent) -> Vec<(String, ScriptError)> {
+		let handler = handler_name(event);
+		let mut errors = Vec::new();
+		Python::with_gil(|py| {
+			for py_node in &mut self.nodes {
+				if node.is_some_and(|node| node != py_node.name)
+					|| !py_node.handlers.contains(&handler)
+				{
+					continue;
+				}
+				let result = match event {
+					NodeEvent::Pointer(pointer) => {
+						call_handler(py, &self.env, py_node, handler, |py| {
+							let kind = pointer.kind.name();
+							let event = py.import("wavemod")?.getattr("PointerEvent")?.call1((
+								kind,
+								pointer.x,
+								pointer.y,
+								pointer.button,
+								pointer.delta.0,
+								pointer.delta.1,
+							))?;
+							PyTuple::new(py, [event])
+						})
+					}
+					NodeEvent::Frame { dt } => {
+						call_handler(py, &self.env, py_node, handler, |py| {
+							(*dt,).into_pyobject(py)
+						})
+					}
+					NodeEvent::Resize { width, height } => {
+						call_handler(py, &self.env, py_node, handler, |py| {
+							(*width, *height).into_pyobject(py)
+						})
+					}
+					NodeEvent::PropertyChanged { key, value } => {
+						call_handler(py, &self.env, py_node, handler, |py| {
+							PyTuple::new(
+								py,
+								[key.into_pyobject(py)?.into_any(), schema_to_py(py, value)?],
+							)
+						})
+					}
+				};
+				if let Err(err) = result {
+					let error = script_error(py, &err, &py_node.name, &py_node.source);
+					py_node.handlers.retain(|name| *name != handler);
+					errors.push((py_node.name.clone(), error));
+				}
+			}
+		});
+		errors.extend(self.breakpoint_errors("take_ignored", ()));
+		errors
+	}
+}
+
+fn call_handler<'py>(
+	py: Python<'py>,
+	env: &PyEnv,
+	py_node: &PyNode,
+	handler: &str,
+	args: impl FnOnce(Python<'py>) -> PyResult<Bound<'py, PyTuple>>,
+) -> PyResult<()> {
+	let _cwd = CwdGuard::enter(env.cwd.as_deref())?;
+	py.import("wavemod")?
+		.setattr("CURRENT_NODE", &py_node.name)?;
+	let function = py_node.module.bind(py).getattr(handler)?;
+	call_node(py, &py_node.name, function, args(py)?.iter())?;
+	Ok(())
+}
+
 #[test]
 fn test_python() {
 	let result = execute_py(
+		&PyEnv::default(),
 		"plug1",
 		r#"
 
@@ -73,3 +1301,580 @@ def build():
 
 	assert!(result.is_ok(), "execute_py failed: {:?}", result.err());
 }
+
+#[test]
+fn test_python_script_modules() {
+	// Scripts run by the same worker don't see the names of each other.
+	let env = PyEnv::default();
+	let result = execute_py(&env, "plug_first", "def build():\n    pass\n\nleaked = 1\n");
+	assert!(result.is_ok(), "execute_py failed: {:?}", result.err());
+	let error = execute_py(&env, "plug_second", "def build():\n    print(leaked)\n").unwrap_err();
+	assert_eq!(error.kind, "NameError");
+}
+
+#[test]
+fn test_python_notebook_cells() {
+	// Cells use the names of earlier cells, and report through a file.
+	let log = std::env::temp_dir().join("wavemod-test-python-cells.txt");
+	let _ = std::fs::remove_file(&log);
+	let cell = |source: String| serde_json::json!({ "cell_type": "code", "source": source });
+	let notebook = serde_json::json!({
+		"nbformat": 4,
+		"cells": [
+			cell("import math\nradius = 2.0\n".to_string()),
+			cell("area = round(math.pi * radius ** 2, 2)\n".to_string()),
+			cell(format!("with open({:?}, 'w') as log:\n    log.write(str(area))\n", log)),
+		],
+	});
+	let board = crate::notebook::import_notebook(&notebook.to_string()).unwrap();
+	let host = PyHost::load_board(&board);
+	assert!(host.nodes.iter().all(|(_, handlers)| handlers.is_empty()));
+	assert_eq!(std::fs::read_to_string(&log).unwrap(), "12.57");
+
+	// Other nodes still need a build function.
+	let error = execute_py(&PyEnv::default(), "plug_cell", "total = sum([1, 2, 3])\n").unwrap_err();
+	assert_eq!(error.kind, "AttributeError");
+}
+
+#[test]
+fn test_python_env() {
+	let board = std::env::temp_dir().join("wavemod-test-python-env");
+	std::fs::create_dir_all(board.join("libs")).unwrap();
+	std::fs::write(board.join("libs/board_helper.py"), "VALUE = 42\n").unwrap();
+	std::fs::write(board.join("data.txt"), "board data").unwrap();
+
+	let env = PyEnv::from_props(&schema!({
+		PYTHON_PATH: "libs",
+		cwd: board.to_str().unwrap(),
+	}));
+	assert_eq!(env.python_path, vec![board.join("libs")]);
+
+	let result = execute_py(
+		&env,
+		"plug_env",
+		r#"
+import board_helper
+
+def build():
+    assert board_helper.VALUE == 42
+    assert open("data.txt").read() == "board data"
+"#,
+	);
+
+	assert!(result.is_ok(), "execute_py failed: {:?}", result.err());
+}
+
+#[test]
+fn test_python_venv_version() {
+	let venv = std::env::temp_dir().join("wavemod-test-python-venv");
+	std::fs::create_dir_all(venv.join("lib/python2.7/site-packages")).unwrap();
+	let _ = std::fs::remove_file(venv.join("pyvenv.cfg"));
+	let env = PyEnv::from_props(&schema!({
+		VIRTUAL_ENV: venv.to_str().unwrap(),
+	}));
+
+	let error = execute_py(&env, "plug_venv", "").unwrap_err();
+	assert!(error.message.contains("pyvenv.cfg"), "{}", error.message);
+
+	std::fs::write(
+		venv.join("pyvenv.cfg"),
+		"home = /usr/bin\nversion = 2.7.18\n",
+	)
+	.unwrap();
+	let error = execute_py(&env, "plug_venv", "").unwrap_err();
+	assert!(
+		error.message.contains("targets Python 2.7"),
+		"{}",
+		error.message
+	);
+}
+
+#[test]
+fn test_python_requirements() {
+	// A distribution is installed when its metadata is on the import path.
+	let folder = std::env::temp_dir().join("wavemod-test-python-requirements");
+	let dist_info = folder.join("fakepkg-1.2.3.dist-info");
+	std::fs::create_dir_all(&dist_info).unwrap();
+	std::fs::write(
+		dist_info.join("METADATA"),
+		"Metadata-Version: 2.1\nName: fakepkg\nVersion: 1.2.3\n",
+	)
+	.unwrap();
+
+	let mut board = crate::board::create_board();
+	assert!(board.set_property("root", "PYTHON_PATH", folder.to_str().unwrap().into()));
+	assert!(board.set_property(
+		"root",
+		"PY_REQUIREMENTS",
+		"fakepkg>=1.0,<2\n# pinned for the shaders\nfakepkg==1.3.*\n\nmissing-wavemod-pkg ; python_version > '3'\n"
+			.into(),
+	));
+	let env = PyEnv::from_props(board.properties());
+	assert_eq!(env.requirements.len(), 3);
+
+	let unmet = check_requirements(&env).unwrap();
+	assert_eq!(
+		unmet,
+		[
+			UnmetRequirement {
+				requirement: "fakepkg==1.3.*".to_string(),
+				installed: Some("1.2.3".to_string()),
+			},
+			UnmetRequirement {
+				requirement: "missing-wavemod-pkg ; python_version > '3'".to_string(),
+				installed: None,
+			},
+		]
+	);
+
+	board.add_node(
+		Node::new("plug_requirements")
+			.set_script(Script::Python("def build():\n    pass\n".to_string())),
+	);
+	let host = PyHost::load_board(&board);
+	assert_eq!(host.unmet_requirements(), unmet);
+
+	let invalid = PyEnv {
+		requirements: vec!["fakepkg >> 1".to_string()],
+		..env.clone()
+	};
+	assert_eq!(check_requirements(&invalid).unwrap_err().kind, "ValueError");
+	let error = install_requirements(&env, Path::new("wheels")).unwrap_err();
+	assert_eq!(error.kind, "PipError");
+}
+
+#[test]
+fn test_python_error_lines() {
+	let source = r#"
+def divide(a, b):
+    return a / b
+
+def build():
+    divide(1, 0)
+"#;
+	let error = execute_py(&PyEnv::default(), "plug_err", source).unwrap_err();
+
+	assert_eq!(error.kind, "ZeroDivisionError");
+	assert_eq!(error.line, Some(3));
+	assert_eq!(error.text.as_deref(), Some("    return a / b"));
+	let node_lines: Vec<_> = error
+		.frames
+		.iter()
+		.filter(|frame| frame.file == "plug_err")
+		.map(|frame| (frame.function.as_str(), frame.line))
+		.collect();
+	assert_eq!(node_lines, [("build", Some(6)), ("divide", Some(3))]);
+}
+
+#[test]
+fn test_python_syntax_error_lines() {
+	let error = execute_py(&PyEnv::default(), "plug_syntax", "x = 1\ny = (\n").unwrap_err();
+
+	assert_eq!(error.kind, "SyntaxError");
+	assert_eq!(error.line, Some(2));
+	assert_eq!(error.text.as_deref(), Some("y = ("));
+}
+
+/// Dispatches an event and waits for its handlers.
+#[cfg(test)]
+fn dispatch_now(
+	host: &mut PyHost,
+	node: Option<&str>,
+	event: &NodeEvent,
+) -> Vec<(String, ScriptError)> {
+	let mut errors = host.dispatch(node, event);
+	errors.extend(host.flush());
+	errors
+}
+
+#[test]
+fn test_python_events() {
+	// Handlers run in the worker, they report the events they receive through a file.
+	let log = std::env::temp_dir().join("wavemod-test-python-events.txt");
+	let _ = std::fs::remove_file(&log);
+	let mut board = crate::board::create_board();
+	let node = Node::new("plug_events").set_script(Script::Python(format!(
+		r#"
+LOG = {:?}
+events = []
+
+def record(event):
+    events.append(event)
+    with open(LOG, "w") as log:
+        log.write(repr(events))
+
+def build():
+    pass
+
+def on_frame(dt):
+    record(("frame", wmd.CURRENT_NODE, dt))
+
+def on_pointer(event):
+    record((event.kind, event.x, event.button))
+
+def on_property_changed(key, value):
+    record((key, value))
+
+def on_resize(width, height):
+    raise RuntimeError("resize failed")
+"#,
+		log
+	)));
+	board.add_node(node);
+
+	let mut host = PyHost::load_board(&board);
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 }).is_empty());
+	let pointer = PointerEvent {
+		kind: crate::board::PointerKind::Down,
+		x: 4.0,
+		y: 2.0,
+		button: Some(0),
+		delta: (0.0, 0.0),
+	};
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Pointer(pointer)).is_empty());
+	assert!(board.set_property("plug_events", "speed", SchemaValue::U64(3)));
+	for (node, event) in board.take_events() {
+		assert!(dispatch_now(&mut host, Some(&node), &event).is_empty());
+	}
+
+	// A failing handler reports its error once, then stops receiving events.
+	let resize = NodeEvent::Resize {
+		width: 800,
+		height: 600,
+	};
+	let errors = dispatch_now(&mut host, None, &resize);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].0, "plug_events");
+	assert_eq!(errors[0].1.kind, "RuntimeError");
+	assert_eq!(errors[0].1.line, Some(23));
+	assert!(dispatch_now(&mut host, None, &resize).is_empty());
+
+	assert_eq!(
+		std::fs::read_to_string(&log).unwrap(),
+		"[('frame', 'plug_events', 0.5), ('down', 4.0, 0), ('speed', 3)]"
+	);
+}
+
+#[test]
+fn test_python_node_types() {
+	let folder = std::env::temp_dir().join("wavemod-test-python-node-types");
+	let log = folder.join("frames.txt");
+	std::fs::create_dir_all(&folder).unwrap();
+	let _ = std::fs::remove_file(&log);
+	std::fs::write(
+		folder.join("blur_types.py"),
+		format!(
+			r#"
+import wavemod as wmd
+
+LOG = {:?}
+
+@wmd.register_node_type
+class Blur(wmd.Node):
+    """Blurs its input."""
+    properties = {{"radius": 4.0, "passes": 2, "label": "blur", "enabled": True}}
+    inputs = ["image"]
+    outputs = ["image"]
+    default_size = (200.0, 120.0)
+
+    def build(self):
+        self.frames = []
+
+    def on_frame(self, dt):
+        self.frames.append((self.name, dt, self.properties["radius"], self.properties["passes"]))
+        with open(LOG, "w") as log:
+            log.write(repr(self.frames))
+
+class Unregistered(wmd.Node):
+    pass
+"#,
+			log
+		),
+	)
+	.unwrap();
+
+	let mut board = crate::board::create_board();
+	assert!(board.set_property("root", "PYTHON_PATH", folder.to_str().unwrap().into()));
+	let env = PyEnv::from_props(board.properties());
+	let types = load_node_types(&env, "blur_types").unwrap();
+	assert_eq!(types.len(), 1);
+	let blur = &types[0];
+	assert_eq!(blur.name, "Blur");
+	assert_eq!(blur.class, "Blur");
+	assert_eq!(blur.doc.as_deref(), Some("Blurs its input."));
+	assert_eq!(blur.inputs, ["image"]);
+	assert_eq!(blur.default_size, (200.0, 120.0));
+	assert_eq!(
+		blur.properties,
+		schema!({ radius: 4.0, passes: 2i64, label: "blur", enabled: true })
+	);
+	assert_eq!(node_types().lock().unwrap().get("Blur"), Some(blur));
+
+	let mut node = node_types()
+		.lock()
+		.unwrap()
+		.get("Blur")
+		.unwrap()
+		.instantiate("blur1");
+	assert_eq!(node.node_type(), Some("Blur"));
+	assert_eq!(
+		node.layout(),
+		Some(&crate::board::NodeLayout {
+			inputs: vec!["image".to_string()],
+			outputs: vec!["image".to_string()],
+			size: (200.0, 120.0),
+		})
+	);
+	// A saved value replaces the default of the type.
+	node.set_property("passes", SchemaValue::I64(3));
+	board.add_node(node);
+	let mut host = PyHost::load_board(&board);
+	board.take_events();
+	assert!(board.set_property("blur1", "radius", SchemaValue::F64(8.0)));
+	for (node, event) in board.take_events() {
+		assert!(dispatch_now(&mut host, Some(&node), &event).is_empty());
+	}
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 }).is_empty());
+	assert_eq!(
+		std::fs::read_to_string(&log).unwrap(),
+		"[('blur1', 0.5, 8.0, 3)]"
+	);
+
+	let error = load_node_types(&env, "missing_types").unwrap_err();
+	assert_eq!(error.kind, "ModuleNotFoundError");
+}
+
+#[test]
+fn test_python_board_isolation() {
+	let board_with = |name: &str, source: &str| {
+		let mut board = crate::board::create_board();
+		board.add_node(Node::new(name).set_script(Script::Python(source.to_string())));
+		board
+	};
+	let leaking = board_with(
+		"plug_leak",
+		r#"
+import sys
+sys.modules["wavemod_leak"] = sys
+
+def build():
+    pass
+
+def on_frame(dt):
+    import os
+    os._exit(3)
+"#,
+	);
+	let checking = board_with(
+		"plug_check",
+		r#"
+import sys
+
+def build():
+    pass
+
+def on_frame(dt):
+    assert "wavemod_leak" not in sys.modules
+
+def on_resize(width, height):
+    while True:
+        pass
+"#,
+	);
+
+	let mut leaking = PyHost::load_board(&leaking);
+	let mut checking = PyHost::load_board(&checking);
+	checking.event_timeout = Duration::from_millis(500);
+	let frame = NodeEvent::Frame { dt: 0.1 };
+	assert!(dispatch_now(&mut checking, None, &frame).is_empty());
+
+	// A crashing board reports the crash and stops, without affecting the other board.
+	let errors = dispatch_now(&mut leaking, None, &frame);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].0, "plug_leak");
+	assert_eq!(errors[0].1.kind, "WorkerError");
+	assert!(dispatch_now(&mut leaking, None, &frame).is_empty());
+	assert!(dispatch_now(&mut checking, None, &frame).is_empty());
+
+	// So does a hung one.
+	let resize = NodeEvent::Resize {
+		width: 800,
+		height: 600,
+	};
+	let errors = dispatch_now(&mut checking, None, &resize);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].1.kind, "Timeout");
+	assert!(dispatch_now(&mut checking, None, &frame).is_empty());
+}
+
+#[test]
+fn test_python_debugger() {
+	let mut board = crate::board::create_board();
+	let node = Node::new("plug_debug").set_script(Script::Python(
+		r#"
+def build():
+    pass
+
+def double(value):
+    result = value * 2
+    return result
+
+def on_frame(dt):
+    total = double(dt)
+    total += 1
+"#
+		.to_string(),
+	));
+	board.add_node(node);
+	py_debugger()
+		.lock()
+		.unwrap()
+		.set_breakpoints("plug_debug", &[10]);
+
+	let mut host = PyHost::load_board(&board);
+	let paused = || {
+		py_debugger()
+			.lock()
+			.unwrap()
+			.paused()
+			.into_iter()
+			.find(|pause| pause.node == "plug_debug")
+	};
+	let resume = |command| {
+		assert!(py_debugger().lock().unwrap().resume("plug_debug", command));
+	};
+	let variables = |pause: &DebugPause| pause.frames.last().unwrap().variables.clone();
+
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 }).is_empty());
+	let pause = paused().unwrap();
+	assert_eq!(pause.line, Some(10));
+	assert_eq!(pause.frames.last().unwrap().function, "on_frame");
+	assert_eq!(variables(&pause), [("dt".to_string(), "0.5".to_string())]);
+	// Events are held back until the node resumes.
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 }).is_empty());
+	assert_eq!(paused(), Some(pause));
+
+	resume(DebugCommand::StepInto);
+	dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 });
+	let pause = paused().unwrap();
+	assert_eq!(pause.line, Some(6));
+	assert_eq!(
+		pause.frames.last().unwrap().text.as_deref(),
+		Some("    result = value * 2")
+	);
+	assert_eq!(
+		variables(&pause),
+		[("value".to_string(), "0.5".to_string())]
+	);
+
+	resume(DebugCommand::StepOut);
+	dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 });
+	let pause = paused().unwrap();
+	assert_eq!(pause.line, Some(11));
+	assert!(variables(&pause).contains(&("total".to_string(), "1.0".to_string())));
+
+	// Continuing finishes the handler, then the next frame stops on the breakpoint again.
+	resume(DebugCommand::Continue);
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.25 }).is_empty());
+	let pause = paused().unwrap();
+	assert_eq!(pause.line, Some(10));
+	assert_eq!(variables(&pause), [("dt".to_string(), "0.25".to_string())]);
+
+	py_debugger()
+		.lock()
+		.unwrap()
+		.set_breakpoints("plug_debug", &[4]);
+	resume(DebugCommand::Continue);
+	let errors = dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.25 });
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].1.kind, "BreakpointError");
+	assert_eq!(errors[0].1.line, Some(4));
+	assert_eq!(paused(), None);
+
+	py_debugger()
+		.lock()
+		.unwrap()
+		.set_breakpoints("plug_debug", &[]);
+	assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.25 }).is_empty());
+	assert_eq!(paused(), None);
+}
+
+#[test]
+fn test_python_profiler() {
+	let mut board = crate::board::create_board();
+	let node = Node::new("plug_profile").set_script(Script::Python(
+		r#"
+def build():
+    slow(10000)
+
+def slow(n):
+    return sum(range(n))
+
+def on_frame(dt):
+    slow(100000)
+"#
+		.to_string(),
+	));
+	board.add_node(node);
+	py_profiler().lock().unwrap().set_enabled(true);
+
+	let mut host = PyHost::load_board(&board);
+	for _ in 0..3 {
+		assert!(dispatch_now(&mut host, None, &NodeEvent::Frame { dt: 0.5 }).is_empty());
+	}
+	let profile = py_profiler()
+		.lock()
+		.unwrap()
+		.profile("plug_profile")
+		.cloned()
+		.unwrap();
+	assert_eq!(profile.calls, 4);
+	assert!(profile.seconds > 0.0);
+	assert!(profile.mean() <= profile.seconds);
+	let folded = profile.folded();
+	assert!(folded.contains("build (plug_profile:2);slow (plug_profile:5);sum "));
+	assert!(folded.contains("on_frame (plug_profile:8);slow (plug_profile:5);sum "));
+	for line in folded.lines() {
+		let (_, micros) = line.rsplit_once(' ').unwrap();
+		assert!(micros.parse::<u64>().is_ok());
+	}
+}
+
+#[test]
+fn test_python_frames_merge() {
+	let log = std::env::temp_dir().join("wavemod-test-python-frames.txt");
+	let _ = std::fs::remove_file(&log);
+	let mut board = crate::board::create_board();
+	let node = Node::new("plug_frames").set_script(Script::Python(format!(
+		r#"
+import time
+
+LOG = {:?}
+frames = []
+
+def build():
+    pass
+
+def on_frame(dt):
+    time.sleep(0.3)
+    frames.append(round(dt, 3))
+    with open(LOG, "w") as log:
+        log.write(repr(frames))
+"#,
+		log
+	)));
+	board.add_node(node);
+
+	// Frames are sent without waiting for the handlers, the ones the worker is not ready
+	// for yet are merged.
+	let mut host = PyHost::load_board(&board);
+	let start = std::time::Instant::now();
+	for _ in 0..3 {
+		assert!(host
+			.dispatch(None, &NodeEvent::Frame { dt: 0.1 })
+			.is_empty());
+	}
+	assert!(start.elapsed() < Duration::from_millis(300));
+	assert!(host.flush().is_empty());
+	assert_eq!(std::fs::read_to_string(&log).unwrap(), "[0.1, 0.2]");
+}
@@ -0,0 +1,190 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Command of the editor to a paused node script.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DebugCommand {
+	Continue,
+	StepOver,
+	StepInto,
+	StepOut,
+}
+
+impl DebugCommand {
+	/// Name of the command in `wavemod.debugger`.
+	fn name(&self) -> &'static str {
+		match self {
+			DebugCommand::Continue => "continue",
+			DebugCommand::StepOver => "step_over",
+			DebugCommand::StepInto => "step_into",
+			DebugCommand::StepOut => "step_out",
+		}
+	}
+}
+
+/// A call frame of a paused script.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebugFrame {
+	pub file: String,
+	pub function: String,
+	/// 1-based line, relative to the node source when `file` is a node.
+	pub line: Option<u32>,
+	pub text: Option<String>,
+	/// Local variables, with their `repr`.
+	pub variables: Vec<(String, String)>,
+}
+
+/// Where a node script is paused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebugPause {
+	pub node: String,
+	/// Line of the node source execution is paused before.
+	pub line: Option<u32>,
+	/// Call stack, outermost call first.
+	pub frames: Vec<DebugFrame>,
+}
+
+/// Breakpoints per node, as 1-based lines of the node source.
+pub type Breakpoints = HashMap<String, Vec<u32>>;
+
+/// Debugging state of the Python nodes, shared between the editor commands and the hosts.
+#[derive(Debug, Default)]
+pub struct PyDebugger {
+	breakpoints: HashMap<String, BTreeSet<u32>>,
+	/// Bumped on every breakpoint change, so hosts know when to send them to their worker.
+	revision: u64,
+	paused: HashMap<String, DebugPause>,
+	commands: HashMap<String, DebugCommand>,
+}
+
+pub fn py_debugger() -> &'static Mutex<PyDebugger> {
+	static PY_DEBUGGER: std::sync::OnceLock<Mutex<PyDebugger>> = std::sync::OnceLock::new();
+	PY_DEBUGGER.get_or_init(Default::default)
+}
+
+impl PyDebugger {
+	/// Replaces the breakpoints of `node`.
+	pub fn set_breakpoints(&mut self, node: &str, lines: &[u32]) {
+		let lines: BTreeSet<u32> = lines.iter().copied().collect();
+		if lines.is_empty() {
+			self.breakpoints.remove(node);
+		} else {
+			self.breakpoints.insert(node.to_string(), lines);
+		}
+		self.revision += 1;
+	}
+
+	pub fn breakpoints(&self) -> Breakpoints {
+		self.breakpoints
+			.iter()
+			.map(|(node, lines)| (node.clone(), lines.iter().copied().collect()))
+			.collect()
+	}
+
+	pub fn revision(&self) -> u64 {
+		self.revision
+	}
+
+	/// Paused nodes.
+	pub fn paused(&self) -> Vec<DebugPause> {
+		self.paused.values().cloned().collect()
+	}
+
+	/// Resumes a paused node on the next frame, `false` when it is not paused.
+	pub fn resume(&mut self, node: &str, command: DebugCommand) -> bool {
+		if !self.paused.contains_key(node) {
+			return false;
+		}
+		self.commands.insert(node.to_string(), command);
+		true
+	}
+
+	pub(super) fn pause(&mut self, pause: DebugPause) {
+		self.commands.remove(&pause.node);
+		self.paused.insert(pause.node.clone(), pause);
+	}
+
+	/// Takes the command resuming `node`, which is then no longer paused.
+	pub(super) fn take_command(&mut self, node: &str) -> Option<DebugCommand> {
+		let command = self.commands.remove(node)?;
+		self.paused.remove(node);
+		Some(command)
+	}
+
+	/// Forgets a node that will not resume, because its worker stopped.
+	pub(super) fn forget(&mut self, node: &str) {
+		self.commands.remove(node);
+		self.paused.remove(node);
+	}
+}
+
+/// Frame sent by `wavemod.debugger`: file, function, line, text, whether the file is a
+/// node, and variables.
+type PyFrame = (String, String, u32, String, bool, HashMap<String, String>);
+
+/// Sends where a script is paused to the app and waits for the command resuming it.
+///
+/// `frames` are ordered outermost call first. Returns the command name, and the new
+/// breakpoints when they changed.
+#[pyfunction]
+#[pyo3(
+	name = "_debug_pause",
+	text_signature = "(node: str, frames: list[tuple[str, str, int, str, bool, dict[str, str]]]) -> tuple[str, dict[str, list[int]] | None]"
+)]
+fn debug_pause(
+	node: String,
+	frames: Vec<PyFrame>,
+) -> PyResult<(&'static str, Option<Breakpoints>)> {
+	let frames: Vec<DebugFrame> = frames
+		.into_iter()
+		.map(|(file, function, line, text, in_node, variables)| {
+			let mut variables: Vec<_> = variables.into_iter().collect();
+			variables.sort();
+			DebugFrame {
+				file,
+				function,
+				line: if in_node {
+					super::source_line(line)
+				} else {
+					Some(line)
+				},
+				text: Some(text).filter(|text| !text.is_empty()),
+				variables,
+			}
+		})
+		.collect();
+	let pause = DebugPause {
+		line: frames
+			.iter()
+			.rev()
+			.find(|frame| frame.file == node)
+			.and_then(|frame| frame.line),
+		node,
+		frames,
+	};
+	let (command, breakpoints) = super::worker::wait_debug_command(pause)
+		.map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+	Ok((command.name(), breakpoints.map(to_file_lines)))
+}
+
+/// Maps breakpoints on node lines to lines of the executed code, which starts with the prelude.
+pub fn to_file_lines(breakpoints: Breakpoints) -> Breakpoints {
+	breakpoints
+		.into_iter()
+		.map(|(node, lines)| {
+			let lines = lines
+				.into_iter()
+				.map(|line| line + super::PRELUDE_LINES)
+				.collect();
+			(node, lines)
+		})
+		.collect()
+}
+
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_function(wrap_pyfunction!(debug_pause, module)?)?;
+	Ok(())
+}
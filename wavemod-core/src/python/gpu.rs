@@ -0,0 +1,554 @@
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyBufferError, PyException, PyRuntimeError, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::{
+	node_graphics, DispatchSize, NodeGraphics, ScriptBufferKind, ScriptTextureFormat,
+	StorageTextureFormat,
+};
+
+create_exception!(
+	wavemod_rs,
+	ShaderError,
+	PyException,
+	"Raised when a node shader fails to compile or validate."
+);
+
+/// Element types that can be uploaded to the GPU as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+	U8,
+	I32,
+	U32,
+	F32,
+}
+
+/// C-contiguous view of an object implementing the buffer protocol (`bytes`, NumPy arrays...).
+///
+/// The memory is read in place, and the view is released when dropped.
+struct BufferView<'py> {
+	view: Box<ffi::Py_buffer>,
+	_gil: PhantomData<Python<'py>>,
+}
+
+impl<'py> BufferView<'py> {
+	fn get(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+		let mut view = Box::new(ffi::Py_buffer::new());
+		let flags = ffi::PyBUF_C_CONTIGUOUS | ffi::PyBUF_FORMAT;
+		if unsafe { ffi::PyObject_GetBuffer(obj.as_ptr(), &mut *view, flags) } == -1 {
+			let err = PyErr::fetch(obj.py());
+			if err.is_instance_of::<PyBufferError>(obj.py()) {
+				return Err(PyValueError::new_err(format!(
+					"{} (copy it with numpy.ascontiguousarray first)",
+					err.value(obj.py())
+				)));
+			}
+			return Err(err);
+		}
+		Ok(BufferView {
+			view,
+			_gil: PhantomData,
+		})
+	}
+
+	fn bytes(&self) -> &[u8] {
+		if self.view.len == 0 {
+			return &[];
+		}
+		unsafe { std::slice::from_raw_parts(self.view.buf as *const u8, self.view.len as usize) }
+	}
+
+	fn shape(&self) -> Vec<usize> {
+		let ndim = self.view.ndim as usize;
+		if self.view.shape.is_null() {
+			return vec![self.view.len as usize / self.view.itemsize as usize];
+		}
+		unsafe { std::slice::from_raw_parts(self.view.shape, ndim) }
+			.iter()
+			.map(|dim| *dim as usize)
+			.collect()
+	}
+
+	/// Element type, from the struct-module style format of the buffer.
+	fn scalar(&self) -> PyResult<Scalar> {
+		let format = match self.view.format.is_null() {
+			true => "B",
+			false => unsafe { CStr::from_ptr(self.view.format) }
+				.to_str()
+				.unwrap_or_default(),
+		};
+		let big_endian = format.starts_with(['>', '!']);
+		let code = format.trim_start_matches(['@', '=', '<', '>', '!']);
+		let scalar = match (code, self.view.itemsize) {
+			("B" | "b" | "c" | "?", 1) => Some(Scalar::U8),
+			("i" | "l", 4) => Some(Scalar::I32),
+			("I" | "L", 4) => Some(Scalar::U32),
+			("f", 4) => Some(Scalar::F32),
+			_ => None,
+		};
+		match scalar {
+			Some(scalar) if !big_endian || scalar == Scalar::U8 => Ok(scalar),
+			Some(_) => Err(PyValueError::new_err(format!(
+				"Big-endian element type '{}' is not supported",
+				format
+			))),
+			None if code == "d" || code == "e" => Err(PyValueError::new_err(format!(
+				"{}-bit floats have no GPU equivalent, convert with astype(numpy.float32)",
+				self.view.itemsize * 8
+			))),
+			None => Err(PyValueError::new_err(format!(
+				"Unsupported element type '{}' ({} bytes), expected uint8, int32, uint32 or float32",
+				format, self.view.itemsize
+			))),
+		}
+	}
+}
+
+impl Drop for BufferView<'_> {
+	fn drop(&mut self) {
+		unsafe { ffi::PyBuffer_Release(&mut *self.view) };
+	}
+}
+
+/// Call of a node script into the board graphics.
+///
+/// Python workers forward them to the app, which owns the GPU device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GpuCall {
+	CreatePipeline {
+		node: String,
+		source: String,
+	},
+	CreateBuffer {
+		pipeline: usize,
+		binding: u32,
+		kind: ScriptBufferKind,
+	},
+	WriteBuffer {
+		pipeline: usize,
+		buffer: usize,
+		offset: u64,
+	},
+	CreateTexture {
+		pipeline: usize,
+		binding: u32,
+		size: (u32, u32),
+		format: ScriptTextureFormat,
+	},
+	TextureInfo {
+		pipeline: usize,
+		texture: usize,
+	},
+	WriteTexture {
+		pipeline: usize,
+		texture: usize,
+	},
+	SetArea {
+		node: String,
+		area: [f32; 4],
+	},
+	SetDispatch {
+		pipeline: usize,
+		size: DispatchSize,
+	},
+	CreateStorageBuffer {
+		name: String,
+	},
+	WriteStorageBuffer {
+		name: String,
+		offset: u64,
+	},
+	CreateStorageTexture {
+		name: String,
+		size: (u32, u32),
+		format: StorageTextureFormat,
+	},
+}
+
+/// Result of a [`GpuCall`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GpuValue {
+	None,
+	Id(usize),
+	TextureInfo((u32, u32), ScriptTextureFormat),
+}
+
+impl GpuCall {
+	/// Runs the call, `data` being the content uploaded by creations and writes.
+	pub fn apply(self, graphics: &mut NodeGraphics, data: &[u8]) -> Result<GpuValue, String> {
+		match self {
+			GpuCall::CreatePipeline { node, source } => {
+				graphics.create_pipeline(&node, &source).map(GpuValue::Id)
+			}
+			GpuCall::CreateBuffer {
+				pipeline,
+				binding,
+				kind,
+			} => graphics
+				.create_buffer(pipeline, binding, kind, data)
+				.map(GpuValue::Id),
+			GpuCall::WriteBuffer {
+				pipeline,
+				buffer,
+				offset,
+			} => graphics
+				.write_buffer(pipeline, buffer, offset, data)
+				.map(|_| GpuValue::None),
+			GpuCall::CreateTexture {
+				pipeline,
+				binding,
+				size,
+				format,
+			} => graphics
+				.create_texture(pipeline, binding, size, format)
+				.map(GpuValue::Id),
+			GpuCall::TextureInfo { pipeline, texture } => graphics
+				.texture_info(pipeline, texture)
+				.map(|(size, format)| GpuValue::TextureInfo(size, format)),
+			GpuCall::WriteTexture { pipeline, texture } => graphics
+				.write_texture(pipeline, texture, data)
+				.map(|_| GpuValue::None),
+			GpuCall::SetArea { node, area } => {
+				graphics.set_area(&node, area);
+				Ok(GpuValue::None)
+			}
+			GpuCall::SetDispatch { pipeline, size } => graphics
+				.set_dispatch(pipeline, size)
+				.map(|_| GpuValue::None),
+			GpuCall::CreateStorageBuffer { name } => graphics
+				.create_storage_buffer(&name, data)
+				.map(|_| GpuValue::None),
+			GpuCall::WriteStorageBuffer { name, offset } => graphics
+				.write_storage_buffer(&name, offset, data)
+				.map(|_| GpuValue::None),
+			GpuCall::CreateStorageTexture { name, size, format } => graphics
+				.create_storage_texture(&name, size, format)
+				.map(|_| GpuValue::None),
+		}
+	}
+}
+
+/// Runs `call` on the board graphics, through the app when running in a Python worker.
+fn gpu_call(call: GpuCall, data: &[u8]) -> Result<GpuValue, String> {
+	match super::worker::forward_gpu_call(&call, data) {
+		Some(result) => result,
+		None => call.apply(&mut node_graphics().lock().unwrap(), data),
+	}
+}
+
+fn gpu_id(call: GpuCall, data: &[u8]) -> Result<usize, String> {
+	match gpu_call(call, data)? {
+		GpuValue::Id(id) => Ok(id),
+		value => Err(format!("Unexpected GPU call result {:?}", value)),
+	}
+}
+
+/// Compiles a WGSL fragment shader drawn in the node area, or a compute shader dispatched
+/// every frame, returning the pipeline id.
+#[pyfunction]
+#[pyo3(text_signature = "(node: str, source: str) -> int")]
+fn create_pipeline(node: &str, source: &str) -> PyResult<usize> {
+	let call = GpuCall::CreatePipeline {
+		node: node.to_string(),
+		source: source.to_string(),
+	};
+	gpu_id(call, &[]).map_err(ShaderError::new_err)
+}
+
+/// Creates a uniform or read-only storage buffer from `data`, returning the buffer id.
+#[pyfunction]
+#[pyo3(
+	signature = (pipeline, binding, data, storage=false),
+	text_signature = "(pipeline: int, binding: int, data: Buffer, storage: bool = False) -> int"
+)]
+fn create_buffer(
+	pipeline: usize,
+	binding: u32,
+	data: &Bound<'_, PyAny>,
+	storage: bool,
+) -> PyResult<usize> {
+	let data = BufferView::get(data)?;
+	data.scalar()?;
+	let kind = match storage {
+		true => ScriptBufferKind::Storage,
+		false => ScriptBufferKind::Uniform,
+	};
+	let call = GpuCall::CreateBuffer {
+		pipeline,
+		binding,
+		kind,
+	};
+	gpu_id(call, data.bytes()).map_err(PyValueError::new_err)
+}
+
+/// Writes `data` at `offset` bytes into a buffer of the pipeline.
+#[pyfunction]
+#[pyo3(
+	signature = (pipeline, buffer, data, offset=0),
+	text_signature = "(pipeline: int, buffer: int, data: Buffer, offset: int = 0) -> None"
+)]
+fn write_buffer(
+	pipeline: usize,
+	buffer: usize,
+	data: &Bound<'_, PyAny>,
+	offset: u64,
+) -> PyResult<()> {
+	let data = BufferView::get(data)?;
+	data.scalar()?;
+	let call = GpuCall::WriteBuffer {
+		pipeline,
+		buffer,
+		offset,
+	};
+	gpu_call(call, data.bytes())
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Creates a 2D texture read with `textureLoad`, returning the texture id.
+#[pyfunction]
+#[pyo3(
+	signature = (pipeline, binding, width, height, format="rgba8unorm"),
+	text_signature = "(pipeline: int, binding: int, width: int, height: int, format: str = \"rgba8unorm\") -> int"
+)]
+fn create_texture(
+	pipeline: usize,
+	binding: u32,
+	width: u32,
+	height: u32,
+	format: &str,
+) -> PyResult<usize> {
+	let format = ScriptTextureFormat::parse(format)
+		.ok_or_else(|| PyValueError::new_err(format!("Unsupported texture format '{}'", format)))?;
+	let call = GpuCall::CreateTexture {
+		pipeline,
+		binding,
+		size: (width, height),
+		format,
+	};
+	gpu_id(call, &[]).map_err(PyValueError::new_err)
+}
+
+/// Uploads a `(height, width[, channels])` array, or raw bytes for 8-bit formats.
+#[pyfunction]
+#[pyo3(text_signature = "(pipeline: int, texture: int, data: Buffer) -> None")]
+fn write_texture(pipeline: usize, texture: usize, data: &Bound<'_, PyAny>) -> PyResult<()> {
+	let data = BufferView::get(data)?;
+	let scalar = data.scalar()?;
+	let (size, format) = match gpu_call(GpuCall::TextureInfo { pipeline, texture }, &[]) {
+		Ok(GpuValue::TextureInfo(size, format)) => (size, format),
+		Ok(value) => {
+			return Err(PyValueError::new_err(format!(
+				"Unexpected GPU call result {:?}",
+				value
+			)))
+		}
+		Err(error) => return Err(PyValueError::new_err(error)),
+	};
+
+	let expected = if format.is_float() {
+		Scalar::F32
+	} else {
+		Scalar::U8
+	};
+	if scalar != expected {
+		return Err(PyValueError::new_err(format!(
+			"{:?} textures expect {:?} elements, got {:?}",
+			format, expected, scalar
+		)));
+	}
+
+	let shape = data.shape();
+	let channels = format.channels();
+	let shape_ok = match shape.as_slice() {
+		[h, w] => (*w as u32, *h as u32) == size && channels == 1,
+		[h, w, c] => (*w as u32, *h as u32) == size && *c == channels,
+		[_] => scalar == Scalar::U8,
+		_ => false,
+	};
+	if !shape_ok {
+		return Err(PyValueError::new_err(format!(
+			"Expected shape ({}, {}, {}) for a {}x{} {:?} texture, got {:?}",
+			size.1, size.0, channels, size.0, size.1, format, shape
+		)));
+	}
+
+	gpu_call(GpuCall::WriteTexture { pipeline, texture }, data.bytes())
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Sets the workgroups a compute pipeline dispatches every frame, or its invocations.
+#[pyfunction]
+#[pyo3(
+	signature = (pipeline, x, y=1, z=1, invocations=false),
+	text_signature = "(pipeline: int, x: int, y: int = 1, z: int = 1, invocations: bool = False) -> None"
+)]
+fn set_dispatch(pipeline: usize, x: u32, y: u32, z: u32, invocations: bool) -> PyResult<()> {
+	let size = match invocations {
+		true => DispatchSize::Invocations([x, y, z]),
+		false => DispatchSize::Workgroups([x, y, z]),
+	};
+	gpu_call(GpuCall::SetDispatch { pipeline, size }, &[])
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Dispatches a compute pipeline with one invocation per element of a storage buffer array.
+#[pyfunction]
+#[pyo3(text_signature = "(pipeline: int, buffer: str) -> None")]
+fn set_dispatch_buffer(pipeline: usize, buffer: &str) -> PyResult<()> {
+	let size = DispatchSize::BufferLength(buffer.to_string());
+	gpu_call(GpuCall::SetDispatch { pipeline, size }, &[])
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Creates the storage buffer bound to the shader variables named `name`.
+#[pyfunction]
+#[pyo3(text_signature = "(name: str, data: Buffer) -> None")]
+fn create_storage_buffer(name: &str, data: &Bound<'_, PyAny>) -> PyResult<()> {
+	let data = BufferView::get(data)?;
+	data.scalar()?;
+	let call = GpuCall::CreateStorageBuffer {
+		name: name.to_string(),
+	};
+	gpu_call(call, data.bytes())
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Writes `data` at `offset` bytes into a storage buffer.
+#[pyfunction]
+#[pyo3(
+	signature = (name, data, offset=0),
+	text_signature = "(name: str, data: Buffer, offset: int = 0) -> None"
+)]
+fn write_storage_buffer(name: &str, data: &Bound<'_, PyAny>, offset: u64) -> PyResult<()> {
+	let data = BufferView::get(data)?;
+	data.scalar()?;
+	let call = GpuCall::WriteStorageBuffer {
+		name: name.to_string(),
+		offset,
+	};
+	gpu_call(call, data.bytes())
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Creates the 2D storage texture bound to the shader variables named `name`.
+#[pyfunction]
+#[pyo3(
+	signature = (name, width, height, format="rgba8unorm"),
+	text_signature = "(name: str, width: int, height: int, format: str = \"rgba8unorm\") -> None"
+)]
+fn create_storage_texture(name: &str, width: u32, height: u32, format: &str) -> PyResult<()> {
+	let format = StorageTextureFormat::parse(format).ok_or_else(|| {
+		PyValueError::new_err(format!("Unsupported storage texture format '{}'", format))
+	})?;
+	let call = GpuCall::CreateStorageTexture {
+		name: name.to_string(),
+		size: (width, height),
+		format,
+	};
+	gpu_call(call, &[])
+		.map(|_| ())
+		.map_err(PyValueError::new_err)
+}
+
+/// Sets the area of the board the node pipelines are drawn into, in physical pixels.
+#[pyfunction]
+#[pyo3(text_signature = "(node: str, x: float, y: float, width: float, height: float) -> None")]
+fn set_node_area(node: &str, x: f32, y: f32, width: f32, height: f32) -> PyResult<()> {
+	let call = GpuCall::SetArea {
+		node: node.to_string(),
+		area: [x, y, width, height],
+	};
+	gpu_call(call, &[])
+		.map(|_| ())
+		.map_err(PyRuntimeError::new_err)
+}
+
+/// Adds the GPU functions backing `wavemod.gpu` to the `wavemod_rs` module.
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add("ShaderError", module.py().get_type::<ShaderError>())?;
+	module.add_function(wrap_pyfunction!(create_pipeline, module)?)?;
+	module.add_function(wrap_pyfunction!(create_buffer, module)?)?;
+	module.add_function(wrap_pyfunction!(write_buffer, module)?)?;
+	module.add_function(wrap_pyfunction!(create_texture, module)?)?;
+	module.add_function(wrap_pyfunction!(write_texture, module)?)?;
+	module.add_function(wrap_pyfunction!(set_node_area, module)?)?;
+	module.add_function(wrap_pyfunction!(set_dispatch, module)?)?;
+	module.add_function(wrap_pyfunction!(set_dispatch_buffer, module)?)?;
+	module.add_function(wrap_pyfunction!(create_storage_buffer, module)?)?;
+	module.add_function(wrap_pyfunction!(write_storage_buffer, module)?)?;
+	module.add_function(wrap_pyfunction!(create_storage_texture, module)?)?;
+	Ok(())
+}
+
+#[test]
+fn test_shader_error() {
+	let result = crate::python::execute_py(
+		&Default::default(),
+		"plug_gpu",
+		r#"
+def build():
+    renderer = wmd.Renderer()
+    try:
+        renderer.create_pipeline("@fragment fn fs_main() -> @location(0) vec4<f32> { return 1.0; }")
+    except wmd.ShaderError as error:
+        assert "fs_main" in str(error) or "vec4" in str(error), str(error)
+    else:
+        raise AssertionError("invalid WGSL was accepted")
+
+    pipeline = renderer.create_pipeline(
+        "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }"
+    )
+    buffer = pipeline.create_buffer(0, bytes(16))
+    buffer.write(b"\x00\x00\x80\x3f", offset=4)
+"#,
+	);
+
+	assert!(result.is_ok(), "execute_py failed: {:?}", result.err());
+}
+
+#[test]
+fn test_buffer_protocol_upload() {
+	let result = crate::python::execute_py(
+		&Default::default(),
+		"plug_upload",
+		r#"
+from array import array
+
+def expect_error(call, *args):
+    try:
+        call(*args)
+    except ValueError as error:
+        return str(error)
+    raise AssertionError("no error raised")
+
+def build():
+    pipeline = wmd.Renderer().create_pipeline(
+        "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }"
+    )
+    buffer = pipeline.create_buffer(0, array("f", [0.0] * 8), storage=True)
+    buffer.write(array("f", [1.0, 2.0]), offset=8)
+    assert "float32" in expect_error(buffer.write, array("d", [1.0]))
+    assert "aligned" in expect_error(buffer.write, b"\x00\x00")
+    assert "contiguous" in expect_error(buffer.write, memoryview(bytearray(32))[::2])
+
+    texture = pipeline.create_texture(1, 4, 2, "rgba8unorm")
+    texture.write(memoryview(bytearray(32)).cast("B", [2, 4, 4]))
+    assert "shape" in expect_error(texture.write, memoryview(bytearray(32)).cast("B", [4, 2, 4]))
+    assert "F32" in expect_error(texture.write, array("f", [0.0] * 8))
+"#,
+	);
+
+	assert!(result.is_ok(), "execute_py failed: {:?}", result.err());
+}
@@ -0,0 +1,106 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Execution time of a Python node, with its call stacks when profiling is enabled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeProfile {
+	pub node: String,
+	/// Number of `build()` and handler calls.
+	pub calls: u64,
+	/// Time spent in those calls, in seconds.
+	pub seconds: f64,
+	/// Time spent in each call stack, excluding its callees, in seconds. Stacks are the
+	/// `;` separated functions from the outermost call.
+	pub stacks: BTreeMap<String, f64>,
+}
+
+impl NodeProfile {
+	/// Mean time of a call, in seconds.
+	pub fn mean(&self) -> f64 {
+		match self.calls {
+			0 => 0.0,
+			calls => self.seconds / calls as f64,
+		}
+	}
+
+	/// Call stacks in the folded format of flamegraph tools, with times in microseconds.
+	pub fn folded(&self) -> String {
+		self.stacks
+			.iter()
+			.map(|(stack, seconds)| (stack, (seconds * 1e6).round() as u64))
+			.filter(|(_, micros)| *micros > 0)
+			.map(|(stack, micros)| format!("{} {}\n", stack, micros))
+			.collect()
+	}
+
+	fn merge(&mut self, other: NodeProfile) {
+		self.calls += other.calls;
+		self.seconds += other.seconds;
+		for (stack, seconds) in other.stacks {
+			*self.stacks.entry(stack).or_default() += seconds;
+		}
+	}
+}
+
+/// Profiling state of the Python nodes, shared between the editor commands and the hosts.
+#[derive(Debug, Default)]
+pub struct PyProfiler {
+	enabled: bool,
+	/// Bumped when profiling is toggled, so hosts know when to tell their worker.
+	revision: u64,
+	profiles: HashMap<String, NodeProfile>,
+}
+
+pub fn py_profiler() -> &'static Mutex<PyProfiler> {
+	static PY_PROFILER: std::sync::OnceLock<Mutex<PyProfiler>> = std::sync::OnceLock::new();
+	PY_PROFILER.get_or_init(Default::default)
+}
+
+impl PyProfiler {
+	/// Turns the collection of call stacks on or off, starting new stacks when turned on.
+	pub fn set_enabled(&mut self, enabled: bool) {
+		if enabled && !self.enabled {
+			for profile in self.profiles.values_mut() {
+				profile.stacks.clear();
+			}
+		}
+		self.enabled = enabled;
+		self.revision += 1;
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.enabled
+	}
+
+	pub fn revision(&self) -> u64 {
+		self.revision
+	}
+
+	/// Profiles of the nodes, slowest first.
+	pub fn profiles(&self) -> Vec<NodeProfile> {
+		let mut profiles: Vec<_> = self.profiles.values().cloned().collect();
+		profiles.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+		profiles
+	}
+
+	pub fn profile(&self, node: &str) -> Option<&NodeProfile> {
+		self.profiles.get(node)
+	}
+
+	/// Forgets the times measured so far.
+	pub fn reset(&mut self) {
+		self.profiles.clear();
+	}
+
+	pub(super) fn add(&mut self, profile: NodeProfile) {
+		self.profiles
+			.entry(profile.node.clone())
+			.or_insert_with(|| NodeProfile {
+				node: profile.node.clone(),
+				..Default::default()
+			})
+			.merge(profile);
+	}
+}
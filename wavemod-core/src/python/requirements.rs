@@ -0,0 +1,98 @@
+//! Python package requirements of boards (`PY_REQUIREMENTS`).
+//!
+//! They are checked with `wavemod.requirements` by the interpreter running the board, and
+//! can be installed into the board virtualenv from a folder of wheels, without an index.
+
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::PyEnv;
+use crate::board::ScriptError;
+
+/// A requirement of a board its interpreter does not satisfy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnmetRequirement {
+	pub requirement: String,
+	/// Installed version, `None` when the package is missing.
+	pub installed: Option<String>,
+}
+
+impl fmt::Display for UnmetRequirement {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.installed {
+			Some(version) => write!(f, "{} (installed {})", self.requirement, version),
+			None => write!(f, "{} (missing)", self.requirement),
+		}
+	}
+}
+
+/// Checks the requirements against the packages importable by the interpreter.
+pub fn check(py: Python<'_>, requirements: &[String]) -> PyResult<Vec<UnmetRequirement>> {
+	let unmet: Vec<(String, Option<String>)> = py
+		.import("wavemod.requirements")?
+		.call_method1("check", (requirements.to_vec(),))?
+		.extract()?;
+	Ok(unmet
+		.into_iter()
+		.map(|(requirement, installed)| UnmetRequirement {
+			requirement,
+			installed,
+		})
+		.collect())
+}
+
+/// Interpreter of a virtualenv.
+fn venv_python(venv: &Path) -> std::path::PathBuf {
+	if cfg!(target_os = "windows") {
+		venv.join("Scripts").join("python.exe")
+	} else {
+		venv.join("bin").join("python")
+	}
+}
+
+/// Installs the requirements into the board virtualenv with pip, from the wheels of `wheels`
+/// only. A relative `wheels` folder is relative to the board `cwd`.
+pub fn install(env: &PyEnv, wheels: &Path) -> Result<(), Box<ScriptError>> {
+	if env.requirements.is_empty() {
+		return Ok(());
+	}
+	let venv = env.venv.as_ref().ok_or_else(|| {
+		ScriptError::new(
+			"PipError",
+			"The board has no VIRTUAL_ENV to install its requirements into",
+		)
+	})?;
+	let wheels = match &env.cwd {
+		Some(cwd) if wheels.is_relative() => cwd.join(wheels),
+		_ => wheels.to_path_buf(),
+	};
+
+	let output = Command::new(venv_python(venv))
+		.args([
+			"-m",
+			"pip",
+			"install",
+			"--no-index",
+			"--disable-pip-version-check",
+		])
+		.arg("--find-links")
+		.arg(&wheels)
+		.args(&env.requirements)
+		.output()
+		.map_err(|e| ScriptError::new("PipError", format!("Cannot run pip: {}", e)))?;
+	if output.status.success() {
+		return Ok(());
+	}
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	let message = stderr
+		.lines()
+		.rev()
+		.find(|line| !line.trim().is_empty())
+		.unwrap_or("pip failed")
+		.trim();
+	Err(ScriptError::new("PipError", format!("{} ({})", message, output.status)).into())
+}
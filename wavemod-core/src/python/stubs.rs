@@ -0,0 +1,65 @@
+//! Type stub of the `wavemod_rs` module, generated from the PyO3 function signatures.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyType;
+
+const HEADER: &str = "\
+# Generated from the wavemod_rs bindings, run the tests with WAVEMOD_UPDATE_STUBS=1 to update.
+
+from typing_extensions import Buffer
+";
+
+/// Writes the body of a stub item: its docstring, or `...` when it has none.
+fn stub_body(doc: Option<&str>) -> String {
+	match doc.map(str::trim).filter(|doc| !doc.is_empty()) {
+		Some(doc) => format!("    \"\"\"{}\"\"\"\n", doc.replace('\n', "\n    ")),
+		None => "    ...\n".to_string(),
+	}
+}
+
+/// Stub of the public functions and exception classes of `module`, sorted by name.
+///
+/// Functions must declare a `text_signature`, which PyO3 writes on the first line of their doc.
+pub fn module_stub(module: &Bound<'_, PyModule>) -> PyResult<String> {
+	let dict = module.dict();
+	let mut names: Vec<String> = dict.keys().extract()?;
+	names.sort();
+
+	let mut stub = HEADER.to_string();
+	for name in names.iter().filter(|name| !name.starts_with('_')) {
+		let item = module.getattr(name.as_str())?;
+		let doc: Option<String> = item.getattr("__doc__")?.extract()?;
+		if let Ok(class) = item.downcast::<PyType>() {
+			let base = class.getattr("__base__")?.getattr("__name__")?;
+			stub += &format!("\nclass {}({}):\n", name, base);
+			stub += &stub_body(doc.as_deref());
+		} else if item.is_callable() {
+			let (signature, doc) = doc
+				.as_deref()
+				.and_then(|doc| doc.strip_prefix(name.as_str()))
+				.and_then(|doc| doc.split_once("\n--\n\n"))
+				.ok_or_else(|| {
+					PyValueError::new_err(format!("`{}` has no text_signature", name))
+				})?;
+			stub += &format!("\ndef {}{}:\n", name, signature);
+			stub += &stub_body(Some(doc));
+		}
+	}
+	Ok(stub)
+}
+
+#[test]
+fn test_wavemod_rs_stub() {
+	let stub = Python::with_gil(|py| module_stub(&super::wavemod_rs_module(py)?)).unwrap();
+
+	let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src-py/wavemod_rs.pyi");
+	if std::env::var_os("WAVEMOD_UPDATE_STUBS").is_some() {
+		std::fs::write(path, &stub).unwrap();
+	}
+	assert_eq!(
+		std::fs::read_to_string(path).unwrap(),
+		stub,
+		"wavemod_rs.pyi is outdated, run the tests with WAVEMOD_UPDATE_STUBS=1 to update it"
+	);
+}
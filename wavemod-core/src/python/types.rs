@@ -0,0 +1,140 @@
+//! Node types defined in Python, by subclassing `wavemod.Node`.
+//!
+//! A module registers its classes with `wavemod.register_node_type`. Loading the module
+//! in a worker describes them to the app, which keeps them in [`node_types`] until the
+//! module is loaded again. Nodes of a type run a script creating an instance of the class.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Node, NodeLayout, Script};
+use crate::schema::SchemaObject;
+
+/// A node type implemented by a Python class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeType {
+	pub name: String,
+	/// Module defining the class.
+	pub module: String,
+	/// Qualified name of the class in `module`.
+	pub class: String,
+	pub doc: Option<String>,
+	/// Properties of new nodes, with their default value.
+	pub properties: SchemaObject,
+	pub inputs: Vec<String>,
+	pub outputs: Vec<String>,
+	pub default_size: (f32, f32),
+}
+
+impl NodeType {
+	/// Script of the nodes of the type, exposing the methods of an instance of the class.
+	pub fn script(&self) -> String {
+		format!(
+			"wmd.node.bind_node_type(globals(), {:?}, {:?})\n",
+			self.module, self.class
+		)
+	}
+
+	/// Creates a node of the type, with the default properties, connectors and size.
+	pub fn instantiate(&self, name: &str) -> Node {
+		Node::new(name)
+			.with_schematic(&self.properties)
+			.with_type(&self.name)
+			.with_layout(NodeLayout {
+				inputs: self.inputs.clone(),
+				outputs: self.outputs.clone(),
+				size: self.default_size,
+			})
+			.set_script(Script::Python(self.script()))
+	}
+}
+
+/// Registered node types, by name.
+#[derive(Debug, Default)]
+pub struct NodeTypes {
+	types: BTreeMap<String, NodeType>,
+}
+
+pub fn node_types() -> &'static Mutex<NodeTypes> {
+	static NODE_TYPES: std::sync::OnceLock<Mutex<NodeTypes>> = std::sync::OnceLock::new();
+	NODE_TYPES.get_or_init(Default::default)
+}
+
+impl NodeTypes {
+	/// Replaces the types registered by `module`.
+	pub fn register_module(&mut self, module: &str, types: Vec<NodeType>) {
+		self.types.retain(|_, node_type| node_type.module != module);
+		for node_type in types {
+			let name = node_type.name.clone();
+			if let Some(previous) = self.types.insert(name.clone(), node_type) {
+				log::warn!(
+					"Node type '{}' of {} replaces the one of {}",
+					name,
+					module,
+					previous.module
+				);
+			}
+		}
+	}
+
+	pub fn get(&self, name: &str) -> Option<&NodeType> {
+		self.types.get(name)
+	}
+
+	/// Registered types, sorted by name.
+	pub fn list(&self) -> Vec<NodeType> {
+		self.types.values().cloned().collect()
+	}
+}
+
+/// Node type sent by `wavemod.node.describe_node_types`: name, class, doc, properties,
+/// inputs, outputs and default size.
+type PyNodeType<'py> = (
+	String,
+	String,
+	Option<String>,
+	Bound<'py, PyDict>,
+	Vec<String>,
+	Vec<String>,
+	(f32, f32),
+);
+
+/// Imports `module` and describes the node types it registers.
+pub fn describe(py: Python<'_>, module: &str) -> PyResult<Vec<NodeType>> {
+	let types: Vec<PyNodeType> = py
+		.import("wavemod.node")?
+		.call_method1("describe_node_types", (module,))?
+		.extract()?;
+	types
+		.into_iter()
+		.map(
+			|(name, class, doc, properties, inputs, outputs, default_size)| {
+				let mut schema = SchemaObject::new();
+				for (key, value) in properties.iter() {
+					let key: String = key.extract()?;
+					let value = super::py_to_schema(&value).map_err(|e| {
+						pyo3::exceptions::PyTypeError::new_err(format!(
+							"Property '{}' of node type '{}': {}",
+							key, name, e
+						))
+					})?;
+					schema.entries.insert(key, value);
+				}
+				Ok(NodeType {
+					name,
+					module: module.to_string(),
+					class,
+					doc: doc.map(|doc| doc.trim().to_string()),
+					properties: schema,
+					inputs,
+					outputs,
+					default_size,
+				})
+			},
+		)
+		.collect()
+}
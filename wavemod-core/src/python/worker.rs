@@ -0,0 +1,481 @@
+//! Python worker processes.
+//!
+//! Every board runs its Python nodes in a process of its own, so scripts can't leak
+//! interpreter state into other boards, and a crash or a hang only stops that board.
+//! The worker is the app executable started with [`WORKER_ARG`], whose `main` hands it to
+//! [`handle_worker_arg`]. It talks to the app over
+//! its stdin and stdout with length-prefixed JSON messages, each followed by a binary
+//! payload carrying GPU uploads, while script output goes to stderr.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pyo3::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::debug::{Breakpoints, DebugCommand, DebugPause};
+use super::gpu::{GpuCall, GpuValue};
+use super::profile::NodeProfile;
+use super::requirements::UnmetRequirement;
+use super::types::NodeType;
+use super::PyEnv;
+use crate::board::{NodeEvent, ScriptError};
+use crate::graphics::node_graphics;
+use crate::schema::SchemaObject;
+
+/// Argument starting the app executable as a Python worker.
+pub const WORKER_ARG: &str = "--py-worker";
+
+/// Line a worker prints before the first message, anything printed before it is skipped.
+const HANDSHAKE: &str = "wavemod-py-worker 1";
+
+/// Message of the app to a worker.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+	/// Runs the scripts of the board nodes, given as name, source and properties.
+	LoadBoard {
+		env: PyEnv,
+		nodes: Vec<NodeScript>,
+		breakpoints: Breakpoints,
+		profile: bool,
+	},
+	Breakpoints(Breakpoints),
+	/// Turns the collection of call stacks on or off.
+	Profile(bool),
+	Dispatch {
+		node: Option<String>,
+		event: NodeEvent,
+	},
+	/// Runs a single script, for `execute_py`.
+	Execute {
+		env: PyEnv,
+		name: String,
+		source: String,
+	},
+	/// Checks the requirements of a board, for `check_requirements`.
+	Requirements {
+		env: PyEnv,
+	},
+	/// Imports a module defining node types, for `load_node_types`.
+	NodeTypes {
+		env: PyEnv,
+		module: String,
+	},
+	GpuResult(Result<GpuValue, String>),
+	/// Resumes a paused script, with the breakpoints when they changed.
+	Debug {
+		command: DebugCommand,
+		breakpoints: Option<Breakpoints>,
+	},
+}
+
+/// Python script of a board node, loaded by the worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeScript {
+	pub name: String,
+	pub source: String,
+	pub properties: SchemaObject,
+	/// Whether the node is a notebook cell, run in the namespace of the notebook.
+	pub cell: bool,
+}
+
+/// Message of a worker to the app.
+///
+/// Answers carry the time nodes took since the previous answer.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+	/// Nodes that loaded, with the event handlers they define, and the requirements of the
+	/// board that are not met.
+	Loaded {
+		nodes: Vec<(String, Vec<String>)>,
+		errors: Vec<(String, ScriptError)>,
+		requirements: Vec<UnmetRequirement>,
+		profiles: Vec<NodeProfile>,
+	},
+	Done {
+		errors: Vec<(String, ScriptError)>,
+		profiles: Vec<NodeProfile>,
+	},
+	Requirements(Result<Vec<UnmetRequirement>, Box<ScriptError>>),
+	NodeTypes(Result<Vec<NodeType>, Box<ScriptError>>),
+	/// GPU call of a script, answered with a `GpuResult`.
+	Gpu(GpuCall),
+	/// A script stopped on a breakpoint or after a step, answered with `Debug`.
+	Paused(DebugPause),
+}
+
+fn write_message<T: Serialize>(
+	writer: &mut impl Write,
+	message: &T,
+	data: &[u8],
+) -> io::Result<()> {
+	let json = serde_json::to_vec(message)?;
+	writer.write_all(&(json.len() as u32).to_le_bytes())?;
+	writer.write_all(&json)?;
+	writer.write_all(&(data.len() as u64).to_le_bytes())?;
+	writer.write_all(data)?;
+	writer.flush()
+}
+
+fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<(T, Vec<u8>)> {
+	let mut length = [0; 4];
+	reader.read_exact(&mut length)?;
+	let mut json = vec![0; u32::from_le_bytes(length) as usize];
+	reader.read_exact(&mut json)?;
+	let mut length = [0; 8];
+	reader.read_exact(&mut length)?;
+	let mut data = vec![0; u64::from_le_bytes(length) as usize];
+	reader.read_exact(&mut data)?;
+	Ok((serde_json::from_slice(&json)?, data))
+}
+
+/// Worker side of the pipe to the app.
+struct AppPipe {
+	input: io::Stdin,
+	output: io::Stdout,
+}
+
+/// Pipe to the app, only set in worker processes.
+static APP: Mutex<Option<AppPipe>> = Mutex::new(None);
+
+/// Whether this process is a Python worker.
+pub fn in_worker() -> bool {
+	APP.lock().unwrap().is_some()
+}
+
+/// Runs a GPU call of a script in the app, `None` when not running in a worker.
+pub fn forward_gpu_call(call: &GpuCall, data: &[u8]) -> Option<Result<GpuValue, String>> {
+	let mut app = APP.lock().unwrap();
+	let pipe = app.as_mut()?;
+	let result = write_message(&mut pipe.output, &Response::Gpu(call.clone()), data)
+		.and_then(|_| read_message(&mut pipe.input.lock()));
+	Some(match result {
+		Ok((Request::GpuResult(result), _)) => result,
+		Ok((request, _)) => Err(format!("Unexpected request {:?}", request)),
+		Err(e) => Err(format!("Lost the connection to the app: {}", e)),
+	})
+}
+
+/// Sends where a script is paused to the app, and waits for the command resuming it.
+pub fn wait_debug_command(
+	pause: DebugPause,
+) -> Result<(DebugCommand, Option<Breakpoints>), String> {
+	let mut app = APP.lock().unwrap();
+	let pipe = app.as_mut().ok_or("Not running in a Python worker")?;
+	let result = write_message(&mut pipe.output, &Response::Paused(pause), &[])
+		.and_then(|_| read_message(&mut pipe.input.lock()));
+	match result {
+		Ok((
+			Request::Debug {
+				command,
+				breakpoints,
+			},
+			_,
+		)) => Ok((command, breakpoints)),
+		Ok((request, _)) => Err(format!("Unexpected request {:?}", request)),
+		Err(e) => Err(format!("Lost the connection to the app: {}", e)),
+	}
+}
+
+/// Runs this process as a Python worker if it was started as one, with [`WORKER_ARG`],
+/// returning `true` once the app closed the worker.
+///
+/// Workers are copies of the app executable, so an app with a `main` of its own, like an
+/// embedding shell, must call this before anything else and exit when it returns `true`.
+/// Otherwise each worker starts another app, and Python nodes time out. `wavemod_core::run`
+/// calls it.
+pub fn handle_worker_arg() -> bool {
+	if std::env::args().nth(1).as_deref() != Some(WORKER_ARG) {
+		return false;
+	}
+	run_worker();
+	true
+}
+
+/// Serves the requests of the app until it closes the pipe.
+pub fn run_worker() {
+	let output = io::stdout();
+	if writeln!(output.lock(), "{}", HANDSHAKE).is_err() {
+		return;
+	}
+	*APP.lock().unwrap() = Some(AppPipe {
+		input: io::stdin(),
+		output,
+	});
+
+	// stdout carries the messages, scripts print to stderr instead.
+	let redirected = Python::with_gil(|py| {
+		let sys = py.import("sys")?;
+		sys.setattr("stdout", sys.getattr("stderr")?)
+	});
+	if let Err(e) = redirected {
+		log::error!("Cannot redirect the Python output: {}", e);
+	}
+
+	let mut nodes = super::PyNodes::default();
+	loop {
+		let request = {
+			let mut app = APP.lock().unwrap();
+			read_message::<Request>(&mut app.as_mut().unwrap().input.lock())
+		};
+		let response = match request {
+			Ok((
+				Request::LoadBoard {
+					env,
+					nodes: scripts,
+					breakpoints,
+					profile,
+				},
+				_,
+			)) => {
+				let requirements = super::unmet_requirements(&env).unwrap_or_else(|error| {
+					log::error!("Cannot check the Python requirements: {}", error);
+					Vec::new()
+				});
+				let errors = nodes.load(env, &scripts, breakpoints, profile);
+				Response::Loaded {
+					nodes: nodes.handlers(),
+					errors,
+					requirements,
+					profiles: super::take_profiles(),
+				}
+			}
+			Ok((Request::Breakpoints(breakpoints), _)) => Response::Done {
+				errors: nodes.set_breakpoints(breakpoints),
+				profiles: Vec::new(),
+			},
+			Ok((Request::Profile(enabled), _)) => {
+				nodes.set_profiling(enabled);
+				Response::Done {
+					errors: Vec::new(),
+					profiles: Vec::new(),
+				}
+			}
+			Ok((Request::Dispatch { node, event }, _)) => Response::Done {
+				errors: nodes.dispatch(node.as_deref(), &event),
+				profiles: super::take_profiles(),
+			},
+			Ok((Request::Execute { env, name, source }, _)) => Response::Done {
+				errors: super::execute_local(&env, &name, &source)
+					.err()
+					.map(|error| vec![(name, *error)])
+					.unwrap_or_default(),
+				profiles: super::take_profiles(),
+			},
+			Ok((Request::Requirements { env }, _)) => {
+				Response::Requirements(super::unmet_requirements(&env))
+			}
+			Ok((Request::NodeTypes { env, module }, _)) => {
+				Response::NodeTypes(super::describe_node_types(&env, &module))
+			}
+			Ok((request, _)) => {
+				log::error!("Unexpected request {:?}", request);
+				continue;
+			}
+			// The app closed the pipe.
+			Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+			Err(e) => {
+				log::error!("Cannot read the app request: {}", e);
+				return;
+			}
+		};
+		let mut app = APP.lock().unwrap();
+		if let Err(e) = write_message(&mut app.as_mut().unwrap().output, &response, &[]) {
+			log::error!("Cannot answer the app: {}", e);
+			return;
+		}
+	}
+}
+
+#[cfg(not(test))]
+fn worker_command() -> io::Result<Command> {
+	let mut command = Command::new(std::env::current_exe()?);
+	command.arg(WORKER_ARG);
+	Ok(command)
+}
+
+/// Set in the worker processes of tests.
+#[cfg(test)]
+const TEST_WORKER_ENV: &str = "WAVEMOD_TEST_PY_WORKER";
+
+/// Tests run the test executable instead of the app, filtered down to `test_worker_entry`.
+#[cfg(test)]
+fn worker_command() -> io::Result<Command> {
+	let mut command = Command::new(std::env::current_exe()?);
+	command
+		.args(["python::worker::test_worker_entry", "--exact"])
+		.args(["--nocapture", "--quiet", "--test-threads=1"])
+		.env(TEST_WORKER_ENV, "1");
+	Ok(command)
+}
+
+/// Longest wait for the next message of a script making GPU calls in a row.
+const GPU_CALL_WAIT: Duration = Duration::from_millis(2);
+
+/// App side of a worker process, killed when dropped.
+pub struct Worker {
+	child: Child,
+	input: ChildStdin,
+	messages: mpsc::Receiver<io::Result<(Response, Vec<u8>)>>,
+	/// Timeout of the posted request, and when it passes.
+	deadline: Option<(Duration, Instant)>,
+}
+
+impl Worker {
+	pub fn spawn() -> io::Result<Self> {
+		let mut child = worker_command()?
+			.env("PYTHONUNBUFFERED", "1")
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::inherit())
+			.spawn()?;
+		let input = child.stdin.take().unwrap();
+		let mut output = BufReader::new(child.stdout.take().unwrap());
+
+		// Messages are read on a thread of their own, so a hung worker can be timed out.
+		let (sender, messages) = mpsc::channel();
+		std::thread::Builder::new()
+			.name("python-worker".to_string())
+			.spawn(move || {
+				let mut line = String::new();
+				loop {
+					line.clear();
+					match output.read_line(&mut line) {
+						Ok(0) => {
+							let _ = sender.send(Err(io::ErrorKind::UnexpectedEof.into()));
+							return;
+						}
+						Ok(_) if line.trim_end().ends_with(HANDSHAKE) => break,
+						Ok(_) => log::debug!("Python worker: {}", line.trim_end()),
+						Err(e) => {
+							let _ = sender.send(Err(e));
+							return;
+						}
+					}
+				}
+				loop {
+					let message = read_message(&mut output);
+					let failed = message.is_err();
+					if sender.send(message).is_err() || failed {
+						return;
+					}
+				}
+			})?;
+
+		Ok(Worker {
+			child,
+			input,
+			messages,
+			deadline: None,
+		})
+	}
+
+	/// Sends `request` and serves the GPU calls of the scripts until the worker answers.
+	///
+	/// The worker is killed when it takes longer than `timeout`, and is unusable after any error.
+	pub fn request(
+		&mut self,
+		request: &Request,
+		timeout: Duration,
+	) -> Result<Response, Box<ScriptError>> {
+		self.post(request, timeout)?;
+		loop {
+			if let Some(response) = self.poll(timeout)? {
+				return Ok(response);
+			}
+		}
+	}
+
+	/// Sends `request` without waiting for the answer, which [`Worker::poll`] collects.
+	pub fn post(&mut self, request: &Request, timeout: Duration) -> Result<(), Box<ScriptError>> {
+		self.send(request)?;
+		self.deadline = Some((timeout, Instant::now() + timeout));
+		Ok(())
+	}
+
+	/// Serves the GPU calls of the scripts for up to `wait`, returning the answer to the
+	/// posted request once the worker sent it.
+	///
+	/// The worker is killed when the request takes longer than its timeout, and is unusable
+	/// after any error.
+	pub fn poll(&mut self, wait: Duration) -> Result<Option<Response>, Box<ScriptError>> {
+		let Some((timeout, deadline)) = self.deadline else {
+			return Ok(None);
+		};
+		let now = Instant::now();
+		let mut until = now + wait.min(deadline.saturating_duration_since(now));
+		loop {
+			let remaining = until.saturating_duration_since(Instant::now());
+			match self.messages.recv_timeout(remaining) {
+				Ok(Ok((Response::Gpu(call), data))) => {
+					let result = call.apply(&mut node_graphics().lock().unwrap(), &data);
+					self.send(&Request::GpuResult(result))?;
+					until = until.max((Instant::now() + GPU_CALL_WAIT).min(deadline));
+				}
+				Ok(Ok((response, _))) => {
+					self.deadline = None;
+					return Ok(Some(response));
+				}
+				Err(mpsc::RecvTimeoutError::Timeout) if Instant::now() < deadline => {
+					return Ok(None)
+				}
+				Err(mpsc::RecvTimeoutError::Timeout) => {
+					let _ = self.child.kill();
+					return Err(ScriptError::new(
+						"Timeout",
+						format!(
+							"The Python worker did not answer within {:.1}s and was stopped",
+							timeout.as_secs_f32()
+						),
+					)
+					.into());
+				}
+				Ok(Err(_)) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+					return Err(self.exited().into())
+				}
+			}
+		}
+	}
+
+	fn send(&mut self, request: &Request) -> Result<(), Box<ScriptError>> {
+		write_message(&mut self.input, request, &[]).map_err(|_| self.exited().into())
+	}
+
+	/// Error of a worker that stopped answering, with its exit status.
+	fn exited(&mut self) -> ScriptError {
+		let _ = self.child.kill();
+		let message = match self.child.wait() {
+			Ok(status) => format!("The Python worker stopped ({})", status),
+			Err(e) => format!("The Python worker stopped: {}", e),
+		};
+		ScriptError::new("WorkerError", message)
+	}
+}
+
+impl Drop for Worker {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+/// Error of a worker answering a request with the wrong message.
+pub fn unexpected(response: Response) -> ScriptError {
+	ScriptError::new(
+		"WorkerError",
+		format!("Unexpected worker response {:?}", response),
+	)
+}
+
+/// Entry point of the worker processes of tests, a no-op in the test run itself.
+#[test]
+fn test_worker_entry() {
+	if std::env::var_os(TEST_WORKER_ENV).is_some() {
+		run_worker();
+		std::process::exit(0);
+	}
+}
@@ -0,0 +1,581 @@
+//! Render graph of a frame.
+//!
+//! Passes declare the resources they read and write by name. The graph runs each pass after
+//! the passes writing what it reads, drops the passes whose results nothing uses, and gives
+//! the transient textures slots of a [`TexturePool`], shared by textures whose uses do not
+//! overlap. Layers are then composited over the output by a [`Compositor`].
+//!
+//! Resources which are not transient textures, like storage buffers, history targets or the
+//! output, live outside of the graph and the passes writing them always run. Passes reading
+//! the previous frame of a resource do not declare it, it does not order them.
+
+use std::collections::HashMap;
+
+use wgpu::{Device, TextureFormat, TextureView};
+
+/// Name of the target the graph draws the frame into.
+pub const OUTPUT: &str = "output";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+	pub size: (u32, u32),
+	pub format: TextureFormat,
+}
+
+/// Pass recorded by the owner of the graph, which tells passes apart by their `payload`.
+#[derive(Debug, Clone)]
+pub struct GraphPass<P> {
+	pub name: String,
+	pub reads: Vec<String>,
+	pub writes: Vec<String>,
+	pub payload: P,
+}
+
+/// Transient texture composited over the output, in `area` (`[x, y, width, height]` in
+/// pixels) or over all of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+	pub texture: String,
+	pub area: Option<[f32; 4]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderGraph<P> {
+	passes: Vec<GraphPass<P>>,
+	textures: HashMap<String, TextureDesc>,
+	layers: Vec<Layer>,
+}
+
+impl<P> Default for RenderGraph<P> {
+	fn default() -> Self {
+		RenderGraph {
+			passes: Vec::new(),
+			textures: HashMap::new(),
+			layers: Vec::new(),
+		}
+	}
+}
+
+impl<P> RenderGraph<P> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Declares a texture allocated by the graph for the frame.
+	pub fn add_texture(&mut self, name: impl Into<String>, desc: TextureDesc) {
+		self.textures.insert(name.into(), desc);
+	}
+
+	pub fn add_pass(
+		&mut self,
+		name: impl Into<String>,
+		reads: Vec<String>,
+		writes: Vec<String>,
+		payload: P,
+	) {
+		self.passes.push(GraphPass {
+			name: name.into(),
+			reads,
+			writes,
+			payload,
+		});
+	}
+
+	/// Composites a transient texture over the output, after the layers added before it.
+	pub fn add_layer(&mut self, texture: impl Into<String>, area: Option<[f32; 4]>) {
+		self.layers.push(Layer {
+			texture: texture.into(),
+			area,
+		});
+	}
+
+	pub fn pass(&self, index: usize) -> &GraphPass<P> {
+		&self.passes[index]
+	}
+
+	pub fn layers(&self) -> &[Layer] {
+		&self.layers
+	}
+
+	/// Orders the passes used by the frame and assigns slots to the transient textures.
+	pub fn compile(&self) -> Result<GraphPlan, String> {
+		let transient = |name: &String| self.textures.contains_key(name);
+		for layer in &self.layers {
+			if !transient(&layer.texture) {
+				return Err(format!(
+					"Layer '{}' is not a transient texture",
+					layer.texture
+				));
+			}
+		}
+		for pass in &self.passes {
+			for read in pass.reads.iter().filter(|read| transient(read)) {
+				if !self.passes.iter().any(|other| other.writes.contains(read)) {
+					return Err(format!(
+						"Pass '{}' reads '{}', which no pass writes",
+						pass.name, read
+					));
+				}
+			}
+		}
+
+		// Passes writing external resources or transient textures used by kept passes.
+		let mut kept = vec![false; self.passes.len()];
+		let mut used: Vec<&String> = self.layers.iter().map(|layer| &layer.texture).collect();
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for (i, pass) in self.passes.iter().enumerate() {
+				if kept[i]
+					|| !pass
+						.writes
+						.iter()
+						.any(|write| !transient(write) || used.contains(&write))
+				{
+					continue;
+				}
+				kept[i] = true;
+				used.extend(&pass.reads);
+				changed = true;
+			}
+		}
+
+		// Kahn's algorithm, running the first declared of the passes ready.
+		let depends = |reader: usize, writer: usize| {
+			reader != writer
+				&& self.passes[reader]
+					.reads
+					.iter()
+					.any(|read| self.passes[writer].writes.contains(read))
+		};
+		let mut remaining: Vec<usize> = (0..self.passes.len()).filter(|&i| kept[i]).collect();
+		let mut order = Vec::new();
+		while !remaining.is_empty() {
+			let Some(position) = remaining
+				.iter()
+				.position(|&pass| !remaining.iter().any(|&other| depends(pass, other)))
+			else {
+				let names: Vec<&str> = remaining
+					.iter()
+					.map(|&i| self.passes[i].name.as_str())
+					.collect();
+				return Err(format!("Passes depend on each other: {}", names.join(", ")));
+			};
+			order.push(remaining.remove(position));
+		}
+
+		// Steps using each transient texture, layers using them until the composite.
+		let mut lifetimes: HashMap<&String, (usize, usize)> = HashMap::new();
+		for (step, pass) in order.iter().map(|&i| &self.passes[i]).enumerate() {
+			for name in pass.writes.iter().filter(|name| transient(name)) {
+				lifetimes.entry(name).or_insert((step, step));
+			}
+			for name in pass.reads.iter().filter(|name| transient(name)) {
+				if let Some(lifetime) = lifetimes.get_mut(name) {
+					lifetime.1 = step;
+				}
+			}
+		}
+		for layer in &self.layers {
+			if let Some(lifetime) = lifetimes.get_mut(&layer.texture) {
+				lifetime.1 = order.len();
+			}
+		}
+
+		let mut textures: Vec<(&String, (usize, usize))> = lifetimes.into_iter().collect();
+		textures.sort_by_key(|(name, (first, _))| (*first, *name));
+		let mut plan = GraphPlan {
+			order,
+			slots: HashMap::new(),
+			slot_descs: Vec::new(),
+		};
+		// Step after which each slot is free.
+		let mut slot_ends: Vec<usize> = Vec::new();
+		for (name, (first, last)) in textures {
+			let desc = self.textures[name];
+			let slot = (0..plan.slot_descs.len())
+				.find(|&slot| plan.slot_descs[slot] == desc && slot_ends[slot] < first)
+				.unwrap_or_else(|| {
+					plan.slot_descs.push(desc);
+					slot_ends.push(0);
+					plan.slot_descs.len() - 1
+				});
+			slot_ends[slot] = last;
+			plan.slots.insert(name.clone(), slot);
+		}
+		Ok(plan)
+	}
+}
+
+/// Passes of a frame, with the slots of its transient textures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphPlan {
+	/// Indices of the passes to run, in order.
+	pub order: Vec<usize>,
+	slots: HashMap<String, usize>,
+	slot_descs: Vec<TextureDesc>,
+}
+
+impl GraphPlan {
+	pub fn slot(&self, texture: &str) -> Option<usize> {
+		self.slots.get(texture).copied()
+	}
+
+	pub fn slot_count(&self) -> usize {
+		self.slot_descs.len()
+	}
+}
+
+/// Textures backing the slots of transient textures, kept from frame to frame.
+#[derive(Default)]
+pub struct TexturePool {
+	textures: Vec<(TextureDesc, TextureView)>,
+}
+
+impl TexturePool {
+	/// Views of the transient textures of `plan`, creating the textures of the slots which
+	/// are new or changed description since the last frame.
+	pub fn views(&mut self, device: &Device, plan: &GraphPlan) -> HashMap<String, TextureView> {
+		self.textures.truncate(plan.slot_count());
+		for (slot, desc) in plan.slot_descs.iter().enumerate() {
+			if self.textures.get(slot).is_some_and(|(d, _)| d == desc) {
+				continue;
+			}
+			let texture = device.create_texture(&wgpu::TextureDescriptor {
+				label: Some(&format!("Transient {}", slot)),
+				size: wgpu::Extent3d {
+					width: desc.size.0,
+					height: desc.size.1,
+					depth_or_array_layers: 1,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: wgpu::TextureDimension::D2,
+				format: desc.format,
+				usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+					| wgpu::TextureUsages::TEXTURE_BINDING,
+				view_formats: &[],
+			});
+			let entry = (*desc, texture.create_view(&Default::default()));
+			if slot < self.textures.len() {
+				self.textures[slot] = entry;
+			} else {
+				self.textures.push(entry);
+			}
+		}
+		plan.slots
+			.iter()
+			.map(|(name, &slot)| (name.clone(), self.textures[slot].1.clone()))
+			.collect()
+	}
+
+	pub fn len(&self) -> usize {
+		self.textures.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.textures.is_empty()
+	}
+}
+
+/// Part of `area` (`[x, y, width, height]`) inside a target of `size`, as wgpu requires of
+/// viewports. `None` when less than a pixel is left.
+pub fn clip_viewport(area: [f32; 4], size: (u32, u32)) -> Option<[f32; 4]> {
+	let [x, y, w, h] = area;
+	let (width, height) = (size.0 as f32, size.1 as f32);
+	let (x0, y0) = (x.clamp(0.0, width), y.clamp(0.0, height));
+	let (x1, y1) = ((x + w).clamp(0.0, width), (y + h).clamp(0.0, height));
+	if x1 - x0 < 1.0 || y1 - y0 < 1.0 {
+		return None;
+	}
+	Some([x0, y0, x1 - x0, y1 - y0])
+}
+
+const COMPOSITE_SHADER: &str = "
+@group(0) @binding(0) var layer: texture_2d<f32>;
+@group(0) @binding(1) var layer_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return VertexOutput(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0), uv);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(layer, layer_sampler, in.uv);
+}
+";
+
+/// Draws the layers of a graph over its output, blending them by their alpha.
+pub struct Compositor {
+	format: TextureFormat,
+	pipeline: wgpu::RenderPipeline,
+	layout: wgpu::BindGroupLayout,
+	sampler: wgpu::Sampler,
+}
+
+impl Compositor {
+	/// Creates the composite pipeline for outputs of `format`.
+	pub fn new(device: &Device, format: TextureFormat) -> Self {
+		let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("Composite"),
+			source: wgpu::ShaderSource::Wgsl(COMPOSITE_SHADER.into()),
+		});
+		let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Composite"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+			],
+		});
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Composite"),
+			layout: Some(
+				&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+					label: Some("Composite"),
+					bind_group_layouts: &[&layout],
+					push_constant_ranges: &[],
+				}),
+			),
+			vertex: wgpu::VertexState {
+				module: &module,
+				entry_point: Some("vs_main"),
+				compilation_options: Default::default(),
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &module,
+				entry_point: Some("fs_main"),
+				compilation_options: Default::default(),
+				targets: &[Some(wgpu::ColorTargetState {
+					format,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			}),
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			multiview: None,
+			cache: None,
+		});
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Composite"),
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+		Compositor {
+			format,
+			pipeline,
+			layout,
+			sampler,
+		}
+	}
+
+	pub fn format(&self) -> TextureFormat {
+		self.format
+	}
+
+	/// Records the composite of the `layers` into `render_pass`, drawing into an output of
+	/// `size` pixels. `views` are the transient textures.
+	pub fn composite(
+		&self,
+		device: &Device,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		size: (u32, u32),
+		layers: &[Layer],
+		views: &HashMap<String, TextureView>,
+	) {
+		render_pass.set_pipeline(&self.pipeline);
+		for layer in layers {
+			let Some(view) = views.get(&layer.texture) else {
+				continue;
+			};
+			let area = layer
+				.area
+				.unwrap_or([0.0, 0.0, size.0 as f32, size.1 as f32]);
+			let Some([x, y, w, h]) = clip_viewport(area, size) else {
+				continue;
+			};
+			let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Composite"),
+				layout: &self.layout,
+				entries: &[
+					wgpu::BindGroupEntry {
+						binding: 0,
+						resource: wgpu::BindingResource::TextureView(view),
+					},
+					wgpu::BindGroupEntry {
+						binding: 1,
+						resource: wgpu::BindingResource::Sampler(&self.sampler),
+					},
+				],
+			});
+			render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+			render_pass.set_bind_group(0, &bind_group, &[]);
+			render_pass.draw(0..3, 0..1);
+		}
+	}
+}
+
+#[test]
+fn test_render_graph_plan() {
+	let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+	let desc = TextureDesc {
+		size: (4, 4),
+		format: TextureFormat::Rgba8Unorm,
+	};
+	let mut graph = RenderGraph::new();
+	graph.add_texture("blur", desc);
+	graph.add_texture("scene", desc);
+	graph.add_texture("bloom", desc);
+	graph.add_texture("graded", desc);
+	graph.add_texture("unused", desc);
+	// Declared before the passes writing what they read.
+	graph.add_pass(
+		"composite",
+		names(&["scene", "graded"]),
+		names(&[OUTPUT]),
+		0,
+	);
+	graph.add_pass("bloom", names(&["blur"]), names(&["bloom"]), 1);
+	graph.add_pass("blur", names(&["scene"]), names(&["blur"]), 2);
+	graph.add_pass("scene", names(&["particles"]), names(&["scene"]), 3);
+	graph.add_pass("simulate", Vec::new(), names(&["particles"]), 4);
+	graph.add_pass("debug", names(&["scene"]), names(&["unused"]), 5);
+	graph.add_pass("grade", names(&["bloom"]), names(&["graded"]), 6);
+
+	let plan = graph.compile().unwrap();
+	let order: Vec<usize> = plan.order.iter().map(|&i| graph.pass(i).payload).collect();
+	assert_eq!(order, [4, 3, 2, 1, 6, 0]);
+	assert_eq!(plan.slot("unused"), None);
+	// The blur is done with when the bloom is graded, which reuses its texture.
+	assert_eq!(plan.slot("scene"), Some(0));
+	assert_eq!(plan.slot("blur"), Some(1));
+	assert_eq!(plan.slot("bloom"), Some(2));
+	assert_eq!(plan.slot("graded"), Some(1));
+	assert_eq!(plan.slot_count(), 3);
+
+	// Layers are used until the composite, and textures of other sizes are not shared.
+	graph.add_layer("blur", None);
+	graph.add_texture(
+		"bloom",
+		TextureDesc {
+			size: (2, 2),
+			..desc
+		},
+	);
+	let plan = graph.compile().unwrap();
+	assert_eq!(plan.slot_count(), 4);
+	assert_ne!(plan.slot("blur"), plan.slot("graded"));
+
+	graph.add_pass("feedback", names(&["bloom"]), names(&["particles"]), 6);
+	assert!(graph.compile().unwrap_err().contains("feedback"));
+	let mut graph = RenderGraph::new();
+	graph.add_texture("scene", desc);
+	graph.add_pass("composite", names(&["scene"]), names(&[OUTPUT]), 0);
+	assert!(graph.compile().unwrap_err().contains("no pass writes"));
+	graph.add_layer(OUTPUT, None);
+	assert!(graph.compile().is_err());
+}
+
+#[test]
+fn test_render_graph_composite() {
+	let context = match pollster::block_on(crate::IADQContext::init_headless::<
+		crate::offscreen::ShaderRenderer,
+	>()) {
+		Ok(context) => context,
+		Err(error) => {
+			crate::golden::skip_without_adapter("render graph test", &error);
+			return;
+		}
+	};
+	let (device, queue) = (&context.device, &context.queue);
+	let target = crate::OffscreenTarget::new(device, 8, 4, false);
+	let desc = TextureDesc {
+		size: (4, 4),
+		format: target.format(),
+	};
+	let compositor = Compositor::new(device, target.format());
+	let mut pool = TexturePool::default();
+
+	let mut graph = RenderGraph::new();
+	let colors = [wgpu::Color::RED, wgpu::Color::BLUE];
+	for (i, area) in [[0.0, 0.0, 4.0, 4.0], [4.0, 0.0, 4.0, 4.0]]
+		.into_iter()
+		.enumerate()
+	{
+		let layer = format!("layer {}", i);
+		graph.add_texture(layer.clone(), desc);
+		graph.add_pass(layer.clone(), Vec::new(), vec![layer.clone()], colors[i]);
+		graph.add_layer(layer, Some(area));
+	}
+	for frame in 0..2 {
+		let plan = graph.compile().unwrap();
+		let views = pool.views(device, &plan);
+		let mut encoder = device.create_command_encoder(&Default::default());
+		for &index in &plan.order {
+			let pass = graph.pass(index);
+			encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: None,
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &views[&pass.name],
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(pass.payload),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+		}
+		let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: None,
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view: &target.view(),
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		compositor.composite(
+			device,
+			&mut render_pass,
+			target.size(),
+			graph.layers(),
+			&views,
+		);
+		drop(render_pass);
+		queue.submit(std::iter::once(encoder.finish()));
+		assert_eq!(pool.len(), 2, "frame {}", frame);
+	}
+
+	let pixels = target.read_rgba(device, queue);
+	let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..][..4];
+	assert_eq!(pixel(1, 1), [255, 0, 0, 255]);
+	assert_eq!(pixel(6, 2), [0, 0, 255, 255]);
+}
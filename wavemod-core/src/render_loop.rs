@@ -0,0 +1,342 @@
+//! Render loop on a dedicated thread.
+//!
+//! [`RenderLoop::spawn`] moves a [`LoopTarget`] to a thread drawing its frames at the pace of
+//! the display or of a target frame rate. The returned handle sends the loop
+//! [`RenderCommand`]s and stops it when dropped, so its owner keeps it for as long as the
+//! window is drawn. While waiting for the next frame or while the window is hidden, the loop
+//! sleeps on its command channel, so commands are handled as soon as they are sent.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePacing {
+	/// Frames are presented at the refresh rate of the display, which blocks the loop.
+	VSync,
+	/// Frames are drawn at most this many times per second, without waiting for the display.
+	Fps(f32),
+}
+
+pub enum RenderCommand {
+	/// Replaces the shader at `index`, or adds it after the last one.
+	SetShader {
+		index: usize,
+		source: String,
+	},
+	Resize {
+		width: u32,
+		height: u32,
+	},
+	/// Draws a frame into a PNG file, then sends the result to `done`.
+	Screenshot {
+		path: PathBuf,
+		done: Sender<Result<(), String>>,
+	},
+	/// Pauses the loop while the window is hidden.
+	SetVisible(bool),
+	SetPacing(FramePacing),
+	Stop,
+}
+
+/// Drawing driven by a [`RenderLoop`].
+pub trait LoopTarget: Send + 'static {
+	fn render_frame(&mut self);
+
+	/// Handles the commands about the drawing: shaders, size, screenshots and pacing.
+	fn command(&mut self, command: RenderCommand);
+}
+
+/// Time of the next frame for a [`FramePacing`].
+#[derive(Debug, Clone)]
+struct FramePacer {
+	interval: Option<Duration>,
+	next: Option<Instant>,
+}
+
+impl FramePacer {
+	fn new(pacing: FramePacing) -> Self {
+		let interval = match pacing {
+			FramePacing::VSync => None,
+			FramePacing::Fps(fps) => Some(Duration::from_secs_f32(1.0 / fps.max(1.0))),
+		};
+		FramePacer {
+			interval,
+			next: None,
+		}
+	}
+
+	/// Schedules the frame after the one drawn at `now`. Late frames are not caught up on.
+	fn frame_drawn(&mut self, now: Instant) {
+		self.next = self.interval.map(|interval| {
+			let next = self.next.unwrap_or(now) + interval;
+			next.max(now)
+		});
+	}
+
+	/// Time left before the next frame, `None` when it is due.
+	fn wait(&self, now: Instant) -> Option<Duration> {
+		self.next
+			.and_then(|next| next.checked_duration_since(now))
+			.filter(|wait| !wait.is_zero())
+	}
+}
+
+/// Handle of a render thread, which stops it when dropped.
+pub struct RenderLoop {
+	commands: Sender<RenderCommand>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl RenderLoop {
+	pub fn spawn<T: LoopTarget>(target: T, pacing: FramePacing) -> Self {
+		let (commands, receiver) = mpsc::channel();
+		let thread = std::thread::Builder::new()
+			.name("wavemod-render".to_string())
+			.spawn(move || run(target, pacing, receiver))
+			.expect("Cannot spawn the render thread");
+		RenderLoop {
+			commands,
+			thread: Some(thread),
+		}
+	}
+
+	pub fn send(&self, command: RenderCommand) -> Result<(), String> {
+		self.commands
+			.send(command)
+			.map_err(|_| "The render loop has stopped".to_string())
+	}
+
+	/// Draws the next frame into a PNG file, waiting for it to be written.
+	pub fn screenshot(&self, path: impl Into<PathBuf>) -> Result<(), String> {
+		let (done, result) = mpsc::channel();
+		self.send(RenderCommand::Screenshot {
+			path: path.into(),
+			done,
+		})?;
+		result
+			.recv()
+			.map_err(|_| "The render loop stopped before the screenshot".to_string())?
+	}
+
+	/// Follows the window: the loop pauses while it is occluded or minimised, and draws at
+	/// its new size once resized.
+	pub fn window_event(&self, event: &winit::event::WindowEvent) -> Result<(), String> {
+		use winit::event::WindowEvent;
+		match *event {
+			WindowEvent::Occluded(occluded) => self.send(RenderCommand::SetVisible(!occluded)),
+			// Minimised windows are resized to nothing on some platforms.
+			WindowEvent::Resized(size) if size.width == 0 || size.height == 0 => {
+				self.send(RenderCommand::SetVisible(false))
+			}
+			WindowEvent::Resized(size) => {
+				self.send(RenderCommand::SetVisible(true))?;
+				self.send(RenderCommand::Resize {
+					width: size.width,
+					height: size.height,
+				})
+			}
+			_ => Ok(()),
+		}
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.thread
+			.as_ref()
+			.is_some_and(|thread| !thread.is_finished())
+	}
+
+	/// Stops the loop and waits for its current frame to end.
+	pub fn stop(mut self) {
+		self.join();
+	}
+
+	fn join(&mut self) {
+		let _ = self.commands.send(RenderCommand::Stop);
+		if let Some(thread) = self.thread.take() {
+			if thread.join().is_err() {
+				log::error!("The render thread panicked");
+			}
+		}
+	}
+}
+
+impl Drop for RenderLoop {
+	fn drop(&mut self) {
+		self.join();
+	}
+}
+
+fn run<T: LoopTarget>(mut target: T, pacing: FramePacing, commands: Receiver<RenderCommand>) {
+	let mut pacer = FramePacer::new(pacing);
+	let mut visible = true;
+	log::info!("Render loop started");
+	loop {
+		// Commands are handled before the frame, waiting for them while nothing is drawn.
+		let wait = pacer.wait(Instant::now());
+		let command = match (visible, wait) {
+			(false, _) => commands.recv().map_err(|_| RecvTimeoutError::Disconnected),
+			(true, Some(wait)) => commands.recv_timeout(wait),
+			(true, None) => commands.try_recv().map_err(|error| match error {
+				TryRecvError::Empty => RecvTimeoutError::Timeout,
+				TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+			}),
+		};
+		match command {
+			Ok(RenderCommand::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+			Ok(RenderCommand::SetVisible(shown)) => {
+				visible = shown;
+				// Frames missed while hidden are not caught up on.
+				pacer.next = None;
+			}
+			Ok(RenderCommand::SetPacing(pacing)) => {
+				pacer = FramePacer::new(pacing);
+				target.command(RenderCommand::SetPacing(pacing));
+			}
+			Ok(command) => target.command(command),
+			Err(RecvTimeoutError::Timeout) if wait.is_some() => {}
+			Err(RecvTimeoutError::Timeout) => {
+				target.render_frame();
+				pacer.frame_drawn(Instant::now());
+			}
+		}
+	}
+	log::info!("Render loop stopped");
+}
+
+#[test]
+fn test_frame_pacer() {
+	let start = Instant::now();
+	let mut pacer = FramePacer::new(FramePacing::Fps(50.0));
+	assert_eq!(pacer.wait(start), None);
+	pacer.frame_drawn(start);
+	assert_eq!(pacer.wait(start), Some(Duration::from_millis(20)));
+	assert_eq!(
+		pacer.wait(start + Duration::from_millis(5)),
+		Some(Duration::from_millis(15))
+	);
+	assert_eq!(pacer.wait(start + Duration::from_millis(20)), None);
+	// Drawn late, the next frame is a full interval after the schedule, not the late frame.
+	pacer.frame_drawn(start + Duration::from_millis(25));
+	assert_eq!(
+		pacer.wait(start + Duration::from_millis(25)),
+		Some(Duration::from_millis(15))
+	);
+	// Too late, it does not catch up.
+	pacer.frame_drawn(start + Duration::from_millis(100));
+	assert_eq!(pacer.wait(start + Duration::from_millis(100)), None);
+
+	let mut vsync = FramePacer::new(FramePacing::VSync);
+	vsync.frame_drawn(start);
+	assert_eq!(vsync.wait(start), None);
+}
+
+#[test]
+fn test_render_loop() {
+	use std::sync::{Arc, Mutex};
+	use winit::dpi::PhysicalSize;
+	use winit::event::WindowEvent;
+
+	// Frames are sent to the test, which waits for them instead of sleeping.
+	struct Target {
+		frames: Sender<()>,
+		commands: Arc<Mutex<Vec<String>>>,
+	}
+	impl LoopTarget for Target {
+		fn render_frame(&mut self) {
+			let _ = self.frames.send(());
+		}
+
+		fn command(&mut self, command: RenderCommand) {
+			let name = match command {
+				RenderCommand::SetShader { index, .. } => format!("shader {}", index),
+				RenderCommand::Resize { width, height } => format!("{}x{}", width, height),
+				RenderCommand::Screenshot { path, done } => {
+					let _ = done.send(Ok(()));
+					format!("screenshot {}", path.display())
+				}
+				RenderCommand::SetPacing(pacing) => format!("{:?}", pacing),
+				RenderCommand::SetVisible(_) | RenderCommand::Stop => unreachable!(),
+			};
+			self.commands.lock().unwrap().push(name);
+		}
+	}
+
+	let (sender, frames) = mpsc::channel();
+	let commands = Arc::new(Mutex::new(Vec::new()));
+	let started = Instant::now();
+	let render_loop = RenderLoop::spawn(
+		Target {
+			frames: sender,
+			commands: commands.clone(),
+		},
+		FramePacing::Fps(100.0),
+	);
+	let next_frame = || {
+		frames
+			.recv_timeout(Duration::from_secs(10))
+			.expect("No frame was drawn")
+	};
+	// The loop has handled the commands sent before once a screenshot is taken, so the
+	// frames drawn before then can be told apart.
+	let drain = |screenshot: &str| {
+		render_loop.screenshot(screenshot).unwrap();
+		frames.try_iter().count()
+	};
+
+	// The loop is paced: six frames at 100 FPS take five intervals at least.
+	for _ in 0..6 {
+		next_frame();
+	}
+	assert!(started.elapsed() >= Duration::from_millis(50));
+
+	render_loop
+		.window_event(&WindowEvent::Occluded(true))
+		.unwrap();
+	drain("hidden.png");
+	// Commands are still handled while paused, and no frame is drawn.
+	render_loop
+		.send(RenderCommand::Resize {
+			width: 4,
+			height: 2,
+		})
+		.unwrap();
+	assert_eq!(drain("paused.png"), 0);
+	render_loop
+		.window_event(&WindowEvent::Occluded(false))
+		.unwrap();
+	render_loop
+		.send(RenderCommand::SetPacing(FramePacing::Fps(1000.0)))
+		.unwrap();
+	next_frame();
+
+	// Minimised, then restored.
+	render_loop
+		.window_event(&WindowEvent::Resized(PhysicalSize::new(0, 0)))
+		.unwrap();
+	drain("minimised.png");
+	assert_eq!(drain("still minimised.png"), 0);
+	render_loop
+		.window_event(&WindowEvent::Resized(PhysicalSize::new(8, 4)))
+		.unwrap();
+	next_frame();
+	assert_eq!(
+		*commands.lock().unwrap(),
+		[
+			"screenshot hidden.png",
+			"4x2",
+			"screenshot paused.png",
+			"Fps(1000.0)",
+			"screenshot minimised.png",
+			"screenshot still minimised.png",
+			"8x4"
+		]
+	);
+
+	assert!(render_loop.is_running());
+	render_loop.stop();
+	// The target is dropped with the thread, no frame follows.
+	frames.try_iter().count();
+	assert!(frames.recv().is_err());
+}
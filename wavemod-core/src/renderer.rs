@@ -37,5 +37,8 @@ pub trait Renderer: 'static + Sized {
 
 	fn update(&mut self, event: winit::event::WindowEvent);
 
+	/// Fixes the time between two rendered frames, for reproducible renders.
+	fn set_timestep(&mut self, _dt: f32) {}
+
 	fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue);
 }
@@ -8,6 +8,7 @@ pub enum SchemaValue {
 	String(String),
 	U64(u64),
 	I64(i64),
+	F64(f64),
 	Bool(bool),
 	SchemaLink(String),
 	SchemaObject(SchemaObject),
@@ -60,6 +61,12 @@ impl From<i64> for SchemaValue {
 	}
 }
 
+impl From<f64> for SchemaValue {
+	fn from(n: f64) -> Self {
+		SchemaValue::F64(n)
+	}
+}
+
 impl From<bool> for SchemaValue {
 	fn from(b: bool) -> Self {
 		SchemaValue::Bool(b)
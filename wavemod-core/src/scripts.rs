@@ -0,0 +1,58 @@
+use crate::board::{BoardState, NodeEvent, ScriptError};
+
+/// The script hosts of a board, dispatching node events to every language.
+pub struct ScriptHosts {
+	js: crate::js::JsHost,
+	#[cfg(not(target_arch = "wasm32"))]
+	python: crate::python::PyHost,
+	#[cfg(not(target_arch = "wasm32"))]
+	c: crate::c::CHost,
+}
+
+impl ScriptHosts {
+	pub fn load_board(board: &BoardState) -> Self {
+		#[cfg(not(target_arch = "wasm32"))]
+		crate::graphics::node_graphics()
+			.lock()
+			.unwrap()
+			.load_board(board);
+		ScriptHosts {
+			js: crate::js::JsHost::load_board(board),
+			#[cfg(not(target_arch = "wasm32"))]
+			python: crate::python::PyHost::load_board(board),
+			#[cfg(not(target_arch = "wasm32"))]
+			c: crate::c::CHost::load_board(board),
+		}
+	}
+
+	/// Dispatches an event to `node`, or to every node, logging the handlers that failed.
+	pub fn dispatch(&mut self, node: Option<&str>, event: NodeEvent) {
+		let mut errors: Vec<(String, ScriptError)> = self.js.dispatch(node, &event);
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			errors.extend(self.python.dispatch(node, &event));
+			errors.extend(self.c.dispatch(node, &event));
+		}
+		for (node, error) in errors {
+			log::error!("Node '{}' handler failed: {}", node, error);
+		}
+	}
+
+	/// Advances the board by a frame: starts rebuilding the changed C nodes and swaps in the
+	/// built ones, delivers the queued board events, then dispatches `Frame` and applies the
+	/// property writes of C nodes.
+	pub fn frame(&mut self, board: &mut BoardState, dt: f32) {
+		#[cfg(not(target_arch = "wasm32"))]
+		for (node, error) in self.c.sync(board) {
+			log::error!("C node '{}' failed: {}", node, error);
+		}
+		for (node, event) in board.take_events() {
+			self.dispatch(Some(&node), event);
+		}
+		self.dispatch(None, NodeEvent::Frame { dt });
+		#[cfg(not(target_arch = "wasm32"))]
+		for (node, key, value) in self.c.take_writes() {
+			board.set_property(&node, &key, value);
+		}
+	}
+}
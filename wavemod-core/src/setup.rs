@@ -122,16 +122,23 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 	#[cfg(target_arch = "wasm32")]
 	let mut frame_counter = FrameCounter::new();
 
-	let mut board = crate::board::create_board();
-	board.add_node(crate::board::Node::new("xplat-node"));
+	// Shared with the commands, whose property changes reach the scripts on the next frame.
+	let board = crate::board::board_state();
+	board
+		.lock()
+		.unwrap()
+		.add_node(crate::board::Node::new("xplat-node"));
 	cfg_if::cfg_if! {
 		if #[cfg(target_arch = "wasm32")] {
-		 board.add_node(crate::board::Node::new("wasm-node"));
+		 board.lock().unwrap().add_node(crate::board::Node::new("wasm-node"));
 		} else {
-		 board.add_node(crate::board::Node::new("native-node"));
+		 board.lock().unwrap().add_node(crate::board::Node::new("native-node"));
 		}
 	}
 
+	let mut scripts = crate::scripts::ScriptHosts::load_board(&board.lock().unwrap());
+	let (mut cursor, mut last_frame) = ((0.0, 0.0), web_time::Instant::now());
+
 	let mut example = None;
 
 	cfg_if::cfg_if! {
@@ -150,6 +157,12 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 			match event {
 				ref e if crate::SurfaceWrapper::start_condition(e) => {
 					surface.resume(&context, window.clone(), R::SRGB);
+					#[cfg(not(target_arch = "wasm32"))]
+					crate::graphics::node_graphics().lock().unwrap().attach(
+						context.device.clone(),
+						context.queue.clone(),
+						surface.config().view_formats[0],
+					);
 
 					// If we haven't created the example yet, do so now.
 					if example.is_none() {
@@ -172,6 +185,13 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 							&context.device,
 							&context.queue,
 						);
+						scripts.dispatch(
+							None,
+							crate::board::NodeEvent::Resize {
+								width: size.width,
+								height: size.height,
+							},
+						);
 
 						window.request_redraw();
 					}
@@ -196,6 +216,10 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 						#[cfg(target_arch = "wasm32")]
 						frame_counter.update();
 
+						let dt = last_frame.elapsed().as_secs_f32();
+						last_frame = web_time::Instant::now();
+						scripts.frame(&mut board.lock().unwrap(), dt);
+
 						let frame = surface.acquire(&context);
 						let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
 							format: Some(surface.config().view_formats[0]),
@@ -206,12 +230,27 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 							.as_mut()
 							.unwrap()
 							.render(&view, &context.device, &context.queue);
+						#[cfg(not(target_arch = "wasm32"))]
+						crate::graphics::node_graphics()
+							.lock()
+							.unwrap()
+							.render(&view, (surface.config().width, surface.config().height));
 
 						frame.present();
 
 						window.request_redraw();
 					}
-					_ => example.as_mut().unwrap().update(event),
+					_ => {
+						if let Some(pointer) = pointer_event(&event, &mut cursor) {
+							#[cfg(not(target_arch = "wasm32"))]
+							crate::graphics::node_graphics()
+								.lock()
+								.unwrap()
+								.pointer(&pointer);
+							scripts.dispatch(None, crate::board::NodeEvent::Pointer(pointer));
+						}
+						example.as_mut().unwrap().update(event)
+					}
 				},
 				_ => {}
 			}
@@ -220,6 +259,60 @@ async fn setup_eventloop<R: crate::Renderer>(event_loop: EventLoop<()>, window:
 	log::info!("...Finished event loop.");
 }
 
+/// Converts a winit pointer input into a `PointerEvent`, tracking the cursor position
+/// since button and wheel events don't carry it.
+fn pointer_event(
+	event: &WindowEvent,
+	cursor: &mut (f32, f32),
+) -> Option<crate::board::PointerEvent> {
+	use crate::board::{PointerEvent, PointerKind};
+	use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+
+	let pointer = |kind, button, delta| PointerEvent {
+		kind,
+		x: cursor.0,
+		y: cursor.1,
+		button,
+		delta,
+	};
+	match event {
+		WindowEvent::CursorMoved { position, .. } => {
+			*cursor = (position.x as f32, position.y as f32);
+			Some(PointerEvent {
+				kind: PointerKind::Move,
+				x: cursor.0,
+				y: cursor.1,
+				button: None,
+				delta: (0.0, 0.0),
+			})
+		}
+		WindowEvent::MouseInput { state, button, .. } => {
+			let kind = match state {
+				ElementState::Pressed => PointerKind::Down,
+				ElementState::Released => PointerKind::Up,
+			};
+			let button = match button {
+				MouseButton::Left => 0,
+				MouseButton::Right => 1,
+				MouseButton::Middle => 2,
+				MouseButton::Back => 3,
+				MouseButton::Forward => 4,
+				MouseButton::Other(n) => 5 + *n as u32,
+			};
+			Some(pointer(kind, Some(button), (0.0, 0.0)))
+		}
+		WindowEvent::MouseWheel { delta, .. } => {
+			// Lines are converted with the usual browser line height.
+			let delta = match delta {
+				MouseScrollDelta::LineDelta(x, y) => (x * 16.0, y * 16.0),
+				MouseScrollDelta::PixelDelta(position) => (position.x as f32, position.y as f32),
+			};
+			Some(pointer(PointerKind::Wheel, None, delta))
+		}
+		_ => None,
+	}
+}
+
 #[cfg(any(target_os = "macos", target_os = "windows"))]
 fn apply_window_effect(window: &winit::window::Window) -> Result<(), Box<dyn std::error::Error>> {
 	#[cfg(target_os = "macos")]
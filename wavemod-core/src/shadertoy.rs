@@ -0,0 +1,322 @@
+//! Shadertoy inputs of fragment shaders.
+//!
+//! Shaders read the inputs from variables with the Shadertoy names: `iResolution`, `iTime`,
+//! `iTimeDelta`, `iFrame`, `iFrameRate`, `iMouse`, `iDate`, `iChannelResolution`, and the
+//! `iChannel0` to `iChannel3` textures sampled with `iSampler0` to `iSampler3`. A shader can
+//! use them without declaring them: the missing declarations are appended to its source, in
+//! group [`SHADERTOY_GROUP`] or the first one the shader leaves free, so the lines of the
+//! shader are unchanged.
+
+use std::borrow::Cow;
+
+use crate::board::ScriptError;
+use crate::graphics::Shader;
+
+/// Bind group of the inputs appended to shaders, unless the shader uses it.
+pub const SHADERTOY_GROUP: u32 = 2;
+
+/// Bind groups available with the default limits of wgpu.
+const MAX_GROUPS: u32 = 4;
+
+/// Number of `iChannel` textures.
+pub const CHANNEL_COUNT: usize = 4;
+
+/// Declarations of the inputs, with their binding in the group of the inputs.
+const DECLARATIONS: &[(&str, u32, &str)] = &[
+	("iResolution", 0, "var<uniform> iResolution: vec3<f32>;"),
+	("iTime", 1, "var<uniform> iTime: f32;"),
+	("iTimeDelta", 2, "var<uniform> iTimeDelta: f32;"),
+	("iFrame", 3, "var<uniform> iFrame: i32;"),
+	("iFrameRate", 4, "var<uniform> iFrameRate: f32;"),
+	("iMouse", 5, "var<uniform> iMouse: vec4<f32>;"),
+	("iDate", 6, "var<uniform> iDate: vec4<f32>;"),
+	(
+		"iChannelResolution",
+		7,
+		"var<uniform> iChannelResolution: array<vec4<f32>, 4>;",
+	),
+	("iChannel0", 8, "var iChannel0: texture_2d<f32>;"),
+	("iChannel1", 9, "var iChannel1: texture_2d<f32>;"),
+	("iChannel2", 10, "var iChannel2: texture_2d<f32>;"),
+	("iChannel3", 11, "var iChannel3: texture_2d<f32>;"),
+	("iSampler0", 12, "var iSampler0: sampler;"),
+	("iSampler1", 13, "var iSampler1: sampler;"),
+	("iSampler2", 14, "var iSampler2: sampler;"),
+	("iSampler3", 15, "var iSampler3: sampler;"),
+];
+
+/// Name of the texture variable of a channel.
+pub fn channel_name(channel: usize) -> String {
+	format!("iChannel{}", channel)
+}
+
+fn is_identifier_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+/// Variables declared in `source`: the name right after `var`, or after `var<...>` as in
+/// `var<uniform> iTime`.
+fn declared_variables(source: &str) -> Vec<&str> {
+	let mut names = Vec::new();
+	let mut rest = source;
+	while let Some(start) = rest.find("var") {
+		let preceded = rest[..start].ends_with(is_identifier_char);
+		let mut after = &rest[start + 3..];
+		rest = after;
+		if preceded || after.starts_with(is_identifier_char) {
+			continue;
+		}
+		after = after.trim_start();
+		if let Some(template) = after.strip_prefix('<') {
+			let Some(end) = template.find('>') else {
+				break;
+			};
+			after = template[end + 1..].trim_start();
+		}
+		let end = after
+			.find(|c| !is_identifier_char(c))
+			.unwrap_or(after.len());
+		names.push(&after[..end]);
+	}
+	names
+}
+
+/// Bind groups `source` declares variables in.
+fn used_groups(source: &str) -> Vec<u32> {
+	source
+		.match_indices("@group(")
+		.filter_map(|(start, attribute)| {
+			let rest = &source[start + attribute.len()..];
+			rest[..rest.find(')')?].trim().parse().ok()
+		})
+		.collect()
+}
+
+/// Appends the declarations of the inputs `source` uses without declaring them, failing
+/// when the shader leaves no bind group for them.
+pub fn declare_inputs(source: &str) -> Result<Cow<'_, str>, Box<ScriptError>> {
+	let declared = declared_variables(source);
+	let missing: Vec<_> = DECLARATIONS
+		.iter()
+		.filter(|(name, _, _)| !declared.contains(name))
+		.filter(|(name, _, _)| {
+			source
+				.split(|c: char| !is_identifier_char(c))
+				.any(|token| token == *name)
+		})
+		.collect();
+	if missing.is_empty() {
+		return Ok(Cow::Borrowed(source));
+	}
+
+	let used = used_groups(source);
+	let group = std::iter::once(SHADERTOY_GROUP)
+		.chain(0..MAX_GROUPS)
+		.find(|group| !used.contains(group))
+		.ok_or_else(|| {
+			ScriptError::new(
+				"ValidationError",
+				format!(
+					"The shader uses all {} bind groups, none is left for the Shadertoy inputs",
+					MAX_GROUPS
+				),
+			)
+		})?;
+	let mut source = source.to_string();
+	source.push_str("\n\n// Shadertoy inputs\n");
+	for (_, binding, declaration) in missing {
+		source.push_str(&format!(
+			"@group({}) @binding({}) {}\n",
+			group, binding, declaration
+		));
+	}
+	Ok(Cow::Owned(source))
+}
+
+/// Values of the inputs for a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderToyInputs {
+	/// Size of the area drawn, in pixels.
+	pub resolution: (f32, f32),
+	pub time: f32,
+	pub time_delta: f32,
+	pub frame: i32,
+	/// Position while the button is pressed, then the click position, whose coordinates are
+	/// negative when released and the `y` only positive on the frame of the click. Pixels
+	/// from the bottom-left corner.
+	pub mouse: [f32; 4],
+	/// Year, month from 0, day of the month and seconds since midnight, in UTC.
+	pub date: [f32; 4],
+	/// Size of the channel textures, in pixels.
+	pub channel_resolution: [(f32, f32); CHANNEL_COUNT],
+}
+
+impl ShaderToyInputs {
+	/// Writes the inputs the shader declares.
+	pub fn write(&self, shader: &Shader, queue: &wgpu::Queue) {
+		let frame_rate = if self.time_delta > 0.0 {
+			1.0 / self.time_delta
+		} else {
+			0.0
+		};
+		let channel_resolution: Vec<f32> = self
+			.channel_resolution
+			.iter()
+			.flat_map(|(width, height)| [*width, *height, 1.0, 0.0])
+			.collect();
+		let resolution = [self.resolution.0, self.resolution.1, 1.0];
+		let values: [(&str, &[u8]); 8] = [
+			("iResolution", bytemuck::cast_slice(&resolution)),
+			("iTime", bytemuck::bytes_of(&self.time)),
+			("iTimeDelta", bytemuck::bytes_of(&self.time_delta)),
+			("iFrame", bytemuck::bytes_of(&self.frame)),
+			("iFrameRate", bytemuck::bytes_of(&frame_rate)),
+			("iMouse", bytemuck::cast_slice(&self.mouse)),
+			("iDate", bytemuck::cast_slice(&self.date)),
+			(
+				"iChannelResolution",
+				bytemuck::cast_slice(&channel_resolution),
+			),
+		];
+		for (name, data) in values {
+			if shader.has_buffer(name) {
+				let _ = shader.update_buffer(queue, name, data, 0);
+			}
+		}
+	}
+}
+
+/// Current `iDate`.
+pub fn date_now() -> [f32; 4] {
+	let seconds = web_time::SystemTime::now()
+		.duration_since(web_time::UNIX_EPOCH)
+		.map_or(0.0, |duration| duration.as_secs_f64());
+	date_from_unix(seconds)
+}
+
+/// `iDate` of a UNIX timestamp, with the days to civil date conversion of
+/// http://howardhinnant.github.io/date_algorithms.html.
+fn date_from_unix(seconds: f64) -> [f32; 4] {
+	let days = (seconds / 86400.0).floor() as i64;
+	let day_seconds = seconds - days as f64 * 86400.0;
+
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z.rem_euclid(146097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + (month <= 2) as i64;
+	[
+		year as f32,
+		(month - 1) as f32,
+		day as f32,
+		day_seconds as f32,
+	]
+}
+
+/// Shadertoy mouse state, in pixels of the board from its top-left corner.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShaderMouse {
+	pub position: (f32, f32),
+	pub click: (f32, f32),
+	pub down: bool,
+	/// Whether the button was pressed since the last frame.
+	pub clicked: bool,
+}
+
+impl ShaderMouse {
+	pub fn pointer(&mut self, event: &crate::board::PointerEvent) {
+		use crate::board::PointerKind;
+		match (event.kind, event.button) {
+			(PointerKind::Down, Some(0)) => {
+				self.down = true;
+				self.clicked = true;
+				self.click = (event.x, event.y);
+				self.position = self.click;
+			}
+			(PointerKind::Up, Some(0)) => self.down = false,
+			(PointerKind::Move, _) if self.down => self.position = (event.x, event.y),
+			_ => {}
+		}
+	}
+
+	/// `iMouse` of a shader drawn in `area` (`[x, y, width, height]`).
+	pub fn input(&self, area: [f32; 4]) -> [f32; 4] {
+		let [x, y, _, height] = area;
+		let local = |(px, py): (f32, f32)| (px - x, height - (py - y));
+		let (position, click) = (local(self.position), local(self.click));
+		[
+			position.0,
+			position.1,
+			if self.down { click.0 } else { -click.0 },
+			if self.clicked { click.1 } else { -click.1 },
+		]
+	}
+}
+
+#[test]
+fn test_shadertoy_inputs() {
+	let source = "@group(0) @binding(0) var<uniform> iTime: f32;\n@fragment\nfn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {\n    let uv = p.xy / iResolution.xy;\n    return textureSample(iChannel0, iSampler0, uv) * iTime;\n}\n";
+	let declared = declare_inputs(source).unwrap();
+	assert!(declared.starts_with(source));
+	let appended = &declared[source.len()..];
+	assert!(appended.contains("@group(2) @binding(0) var<uniform> iResolution: vec3<f32>;"));
+	assert!(appended.contains("var iChannel0: texture_2d<f32>;"));
+	assert!(appended.contains("var iSampler0: sampler;"));
+	assert!(!appended.contains("iTime") && !appended.contains("iMouse"));
+	crate::graphics::validate_wgsl(&declared).unwrap();
+
+	let plain =
+		"@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0);\n}\n";
+	assert!(matches!(declare_inputs(plain), Ok(Cow::Borrowed(_))));
+
+	// Local variables are not declarations of the inputs they are initialized with.
+	let local = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    var t = iTime;\n    var m: vec4<f32> = iMouse;\n    var iFrame = 1;\n    return vec4<f32>(t) + m + f32(iFrame);\n}\n";
+	let declared = declare_inputs(local).unwrap();
+	let appended = &declared[local.len()..];
+	assert!(appended.contains("var<uniform> iTime: f32;"));
+	assert!(appended.contains("var<uniform> iMouse: vec4<f32>;"));
+	assert!(!appended.contains("iFrame"));
+	crate::graphics::validate_wgsl(&declared).unwrap();
+
+	// The inputs move to a group the shader leaves free.
+	let grouped = "@group(2) @binding(0) var<uniform> tint: vec4<f32>;\n@group(0) @binding(0) var<uniform> scale: f32;\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return tint * iTime * scale;\n}\n";
+	let declared = declare_inputs(grouped).unwrap();
+	assert!(declared.contains("@group(1) @binding(1) var<uniform> iTime: f32;"));
+	crate::graphics::validate_wgsl(&declared).unwrap();
+	let full = (0..4)
+		.map(|group| {
+			format!(
+				"@group({}) @binding(0) var<uniform> g{}: f32;\n",
+				group, group
+			)
+		})
+		.collect::<String>()
+		+ "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(iTime);\n}\n";
+	assert!(declare_inputs(&full).is_err());
+
+	// 2024-02-29 12:30:00 UTC
+	assert_eq!(date_from_unix(1709209800.0), [2024.0, 1.0, 29.0, 45000.0]);
+	assert_eq!(date_from_unix(0.0), [1970.0, 0.0, 1.0, 0.0]);
+
+	let mut mouse = ShaderMouse::default();
+	let pointer = |kind, x, y| crate::board::PointerEvent {
+		kind,
+		x,
+		y,
+		button: Some(0),
+		delta: (0.0, 0.0),
+	};
+	use crate::board::PointerKind;
+	mouse.pointer(&pointer(PointerKind::Down, 110.0, 60.0));
+	mouse.pointer(&pointer(PointerKind::Move, 120.0, 70.0));
+	let area = [100.0, 50.0, 40.0, 30.0];
+	assert_eq!(mouse.input(area), [20.0, 10.0, 10.0, 20.0]);
+	mouse.clicked = false;
+	mouse.pointer(&pointer(PointerKind::Up, 120.0, 70.0));
+	mouse.pointer(&pointer(PointerKind::Move, 130.0, 75.0));
+	assert_eq!(mouse.input(area), [20.0, 10.0, -10.0, -20.0]);
+}